@@ -0,0 +1,587 @@
+use std::cmp;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::rc::Rc;
+use std::str;
+use artifact::error::*;
+use ast::*;
+use code::*;
+use compiler::*;
+use object::*;
+
+// On-disk format of compiled programs. All integers are big-endian, like the
+// operands in the instruction stream.
+//
+//  header      magic "MNKY", format version (u16), flags (u8), body length (u32)
+//  body        constant count (u32), constants
+//              main instructions: length (u32), bytes
+//              main line table, only with FLAG_DEBUG_INFO
+//  trailer     Adler-32 checksum of the body (u32)
+//
+// A constant is a tag byte followed by its payload:
+//
+//  TAG_INTEGER     i64
+//  TAG_STRING      length (u32), UTF-8 bytes
+//  TAG_FUNCTION    name (a string, empty if anonymous), locals (u32),
+//                  parameters (u32), instructions, line table
+//
+// A line table is an entry count (u32) followed by entries of offset, line
+// and column (u32 each) and the ast::NodeKind of the node (u8).
+
+pub static MAGIC: &[u8; 4] = b"MNKY";
+pub static FORMAT_VERSION: u16 = 3;      // 3: functions carry their name for stack traces
+
+pub const FLAG_DEBUG_INFO: u8 = 0x01;     // line tables are included
+
+const TAG_INTEGER: u8 = 0;
+const TAG_STRING: u8 = 1;
+const TAG_FUNCTION: u8 = 2;
+
+// Serializes bytecode. Line tables are only written when debug_info is set.
+//
+// Panics if the constant pool holds an object the compiler never emits.
+pub fn encode(bytecode: &compiler::Bytecode, debug_info: bool) -> Vec<u8> {
+    let mut body: Encoder = Encoder{ out: Vec::new(), debug_info: debug_info };
+
+    body.write_u32(bytecode.constants.len());
+    for constant in bytecode.constants.iter() {
+        body.write_constant(constant);
+    }
+    body.write_instructions(&bytecode.instructions, &bytecode.lines);
+
+    let flags: u8 = if debug_info { FLAG_DEBUG_INFO } else { 0 };
+
+    let mut file: Encoder = Encoder{ out: Vec::new(), debug_info: debug_info };
+    file.out.extend_from_slice(MAGIC);
+    file.write_u16(FORMAT_VERSION);
+    file.write_u8(flags);
+    file.write_u32(body.out.len());
+    file.out.extend_from_slice(&body.out);
+    file.write_u32(adler32(&body.out) as usize);
+
+    return file.out;
+}
+
+// Deserializes bytecode, checking the header, the checksum and that every
+// instruction stream only refers to constants, locals and jump targets that
+// exist. Bytecode without debug info loads with empty line tables.
+pub fn decode(data: &[u8]) -> Result<compiler::Bytecode, LoadError> {
+    let mut header: Decoder = Decoder{ data: data, pos: 0, debug_info: false };
+
+    if header.read_bytes(MAGIC.len())? != &MAGIC[..] {
+        return Err(LoadError::BadMagic);
+    }
+
+    let version: u16 = header.read_u16()?;
+    if version != FORMAT_VERSION {
+        return Err(LoadError::UnsupportedVersion{ found: version, supported: FORMAT_VERSION });
+    }
+
+    let flags: u8 = header.read_u8()?;
+    if flags & !FLAG_DEBUG_INFO != 0 {
+        return Err(LoadError::UnknownFlags(flags));
+    }
+
+    let body_len: usize = header.read_u32()?;
+    let body_start: usize = header.pos;
+    let body_end: usize = body_start + body_len;
+
+    if data.len() < body_end + 4 {
+        return Err(LoadError::Truncated{ offset: data.len() });
+    }
+    if data.len() > body_end + 4 {
+        return Err(LoadError::TrailingData{ offset: body_end + 4 });
+    }
+
+    let mut trailer: Decoder = Decoder{ data: data, pos: body_end, debug_info: false };
+    let expected: u32 = trailer.read_u32()? as u32;
+    let found: u32 = adler32(&data[body_start..body_end]);
+    if expected != found {
+        return Err(LoadError::ChecksumMismatch{ expected: expected, found: found });
+    }
+
+    let mut body: Decoder = Decoder{ data: &data[..body_end], pos: body_start, debug_info: flags & FLAG_DEBUG_INFO != 0 };
+
+    let num_constants: usize = body.read_u32()?;
+    let mut constants: Vec<object::Object> = Vec::new();
+    for _ in 0..num_constants {
+        constants.push(body.read_constant()?);
+    }
+    let (instructions, lines) = body.read_instructions()?;
+
+    if body.pos != body_end {
+        return Err(LoadError::TrailingData{ offset: body.pos });
+    }
+
+    for constant in constants.iter() {
+        match *constant {
+            object::Object::CompiledFunction(ref func) =>
+                validate(&func.instructions, &constants, func.num_locals, free_count(&func.instructions))?,
+            _ => (),
+        }
+    }
+    validate(&instructions, &constants, 0, 0)?;
+
+    return Ok(compiler::Bytecode{
+        instructions:   instructions,
+        constants:      constants,
+        lines:          lines,
+    });
+}
+
+pub fn save<P: AsRef<Path>>(path: P, bytecode: &compiler::Bytecode, debug_info: bool) -> io::Result<()> {
+    return fs::write(path, encode(bytecode, debug_info));
+}
+
+pub fn load<P: AsRef<Path>>(path: P) -> Result<compiler::Bytecode, LoadError> {
+    let data: Vec<u8> = fs::read(path)?;
+    return decode(&data);
+}
+
+struct Encoder {
+    out:            Vec<u8>,
+    debug_info:     bool,
+}
+
+impl Encoder {
+    fn write_u8(&mut self, value: u8) {
+        self.out.push(value);
+    }
+
+    fn write_u16(&mut self, value: u16) {
+        self.out.extend_from_slice(&value.to_be_bytes());
+    }
+
+    fn write_u32(&mut self, value: usize) {
+        self.out.extend_from_slice(&(value as u32).to_be_bytes());
+    }
+
+    fn write_string(&mut self, value: &str) {
+        self.write_u32(value.len());
+        self.out.extend_from_slice(value.as_bytes());
+    }
+
+    fn write_constant(&mut self, constant: &object::Object) {
+        match *constant {
+            object::Object::Integer(value) => {
+                self.write_u8(TAG_INTEGER);
+                self.out.extend_from_slice(&value.to_be_bytes());
+            },
+            object::Object::String(ref value) => {
+                self.write_u8(TAG_STRING);
+                self.write_string(value);
+            },
+            object::Object::CompiledFunction(ref func) => {
+                self.write_u8(TAG_FUNCTION);
+                self.write_string(func.name.as_ref().map_or("", |name| name.as_str()));
+                self.write_u32(func.num_locals);
+                self.write_u32(func.num_parameters);
+                self.write_instructions(&func.instructions, &func.lines);
+            },
+            _ => panic!("constant of type {} cannot be serialized", constant.object_type()),
+        }
+    }
+
+    fn write_instructions(&mut self, instructions: &[u8], lines: &[code::SourcePosition]) {
+        self.write_u32(instructions.len());
+        self.out.extend_from_slice(instructions);
+
+        if ! self.debug_info {
+            return;
+        }
+        self.write_u32(lines.len());
+        for pos in lines.iter() {
+            self.write_u32(pos.offset);
+            self.write_u32(pos.line);
+            self.write_u32(pos.column);
+            self.write_u8(pos.node as u8);
+        }
+    }
+}
+
+struct Decoder<'a> {
+    data:           &'a [u8],   // ends where the section being decoded ends
+    pos:            usize,
+    debug_info:     bool,
+}
+
+impl<'a> Decoder<'a> {
+    fn read_bytes(&mut self, n: usize) -> Result<&'a [u8], LoadError> {
+        if self.data.len() - self.pos < n {
+            return Err(LoadError::Truncated{ offset: self.data.len() });
+        }
+        let bytes: &'a [u8] = &self.data[self.pos..self.pos + n];
+        self.pos += n;
+        return Ok(bytes);
+    }
+
+    fn read_u8(&mut self) -> Result<u8, LoadError> {
+        return Ok(self.read_bytes(1)?[0]);
+    }
+
+    fn read_u16(&mut self) -> Result<u16, LoadError> {
+        return Ok(code::read_u16(self.read_bytes(2)?));
+    }
+
+    fn read_u32(&mut self) -> Result<usize, LoadError> {
+        let bytes: &[u8] = self.read_bytes(4)?;
+        return Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize);
+    }
+
+    fn read_i64(&mut self) -> Result<i64, LoadError> {
+        let mut bytes: [u8; 8] = [0; 8];
+        bytes.copy_from_slice(self.read_bytes(8)?);
+        return Ok(i64::from_be_bytes(bytes));
+    }
+
+    fn read_string(&mut self) -> Result<String, LoadError> {
+        let len: usize = self.read_u32()?;
+        let start: usize = self.pos;
+        return match str::from_utf8(self.read_bytes(len)?) {
+            Ok(value) => Ok(String::from(value)),
+            Err(_) => Err(LoadError::InvalidString{ offset: start }),
+        };
+    }
+
+    fn read_constant(&mut self) -> Result<object::Object, LoadError> {
+        let offset: usize = self.pos;
+        return match self.read_u8()? {
+            TAG_INTEGER => Ok(object::Object::Integer(self.read_i64()?)),
+            TAG_STRING => Ok(object::Object::String(self.read_string()?)),
+            TAG_FUNCTION => {
+                let name: String = self.read_string()?;
+                let num_locals: usize = self.read_u32()?;
+                let num_parameters: usize = self.read_u32()?;
+                let (instructions, lines) = self.read_instructions()?;
+                let mut func: object::CompiledFunction = object::CompiledFunction::new(instructions, lines, num_locals, num_parameters);
+                if ! name.is_empty() {
+                    func.name = Some(name);
+                }
+                Ok(object::Object::CompiledFunction(Rc::new(func)))
+            },
+            tag => Err(LoadError::InvalidConstantTag{ tag: tag, offset: offset }),
+        };
+    }
+
+    fn read_instructions(&mut self) -> Result<(code::Instructions, code::LineTable), LoadError> {
+        let len: usize = self.read_u32()?;
+        let instructions: code::Instructions = self.read_bytes(len)?.to_vec();
+
+        let mut lines: code::LineTable = Vec::new();
+        if self.debug_info {
+            let count: usize = self.read_u32()?;
+            for _ in 0..count {
+                let offset: usize = self.read_u32()?;
+                let line: usize = self.read_u32()?;
+                let column: usize = self.read_u32()?;
+                let byte: u8 = self.read_u8()?;
+                let node: ast::NodeKind = match ast::NodeKind::from_byte(byte) {
+                    Some(node) => node,
+                    None => return Err(LoadError::InvalidNodeKind{ byte: byte, offset: self.pos - 1 }),
+                };
+                lines.push(code::SourcePosition{ offset: offset, line: line, column: column, node: node });
+            }
+        }
+
+        return Ok((instructions, lines));
+    }
+}
+
+// Checks that an instruction stream can be executed without reading past its
+// end or outside the constant pool, the builtins, the frame's locals and the
+// closure's free variables. A function may read as many free variables as it
+// likes as long as every OpClosure creating it captures enough of them. Stack
+// depth is not modelled; the VM reports an underflow when it runs.
+fn validate(instructions: &[u8], constants: &[object::Object], num_locals: usize, num_free: usize) -> Result<(), LoadError> {
+    let mut starts: Vec<bool> = vec![false; instructions.len() + 1];
+    let mut jumps: Vec<(usize, usize)> = Vec::new();
+
+    let mut ip: usize = 0;
+    while ip < instructions.len() {
+        starts[ip] = true;
+
+        let op: code::Opcode = match code::Opcode::from_byte(instructions[ip]) {
+            Some(op) => op,
+            None => return Err(LoadError::InvalidOpcode{ byte: instructions[ip], offset: ip }),
+        };
+        let def: code::Definition = op.definition();
+        let width: usize = def.operand_widths.iter().sum();
+        if ip + 1 + width > instructions.len() {
+            return Err(LoadError::TruncatedInstruction{ offset: ip });
+        }
+
+        let (operands, _) = code::read_operands(&def, &instructions[ip + 1..]);
+        match op {
+            code::OpConstant | code::OpClosure if operands[0] >= constants.len() =>
+                return Err(LoadError::ConstantOutOfRange{ index: operands[0], offset: ip }),
+            code::OpClosure => match constants[operands[0]] {
+                object::Object::CompiledFunction(ref func) if free_count(&func.instructions) <= operands[1] => (),
+                _ => return Err(LoadError::InvalidClosure{ index: operands[0], offset: ip }),
+            },
+            code::OpGetLocal | code::OpSetLocal if operands[0] >= num_locals =>
+                return Err(LoadError::LocalOutOfRange{ index: operands[0], offset: ip }),
+            code::OpGetBuiltin if operands[0] >= builtins::BUILTINS.len() =>
+                return Err(LoadError::BuiltinOutOfRange{ index: operands[0], offset: ip }),
+            code::OpGetFree if operands[0] >= num_free =>
+                return Err(LoadError::FreeOutOfRange{ index: operands[0], offset: ip }),
+            code::OpJump | code::OpJumpNotTruthy => jumps.push((ip, operands[0])),
+            _ => (),
+        }
+
+        ip += 1 + width;
+    }
+    starts[instructions.len()] = true;
+
+    for (offset, target) in jumps {
+        if target > instructions.len() || ! starts[target] {
+            return Err(LoadError::InvalidJumpTarget{ target: target, offset: offset });
+        }
+    }
+    return Ok(());
+}
+
+// The number of free variables an instruction stream reads. Decoding stops at
+// the first malformed instruction, which validate reports.
+fn free_count(instructions: &[u8]) -> usize {
+    let mut count: usize = 0;
+
+    let mut ip: usize = 0;
+    while ip < instructions.len() {
+        let def: code::Definition = match code::Opcode::from_byte(instructions[ip]) {
+            Some(op) => op.definition(),
+            None => break,
+        };
+        let width: usize = def.operand_widths.iter().sum();
+        if ip + 1 + width > instructions.len() {
+            break;
+        }
+        if instructions[ip] == code::OpGetFree as u8 {
+            count = cmp::max(count, instructions[ip + 1] as usize + 1);
+        }
+        ip += 1 + width;
+    }
+
+    return count;
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let mut a: u32 = 1;
+    let mut b: u32 = 0;
+    for byte in data.iter() {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    return (b << 16) | a;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lexer::*;
+    use parser::*;
+    use object::error;
+    use vm::*;
+
+    fn compile(input: &str) -> compiler::Bytecode {
+        let mut l = lexer::Lexer::new(input);
+        let mut p = parser::Parser::new(&mut l);
+        let program: ast::Program = p.parse_program().unwrap();
+
+        let mut c = compiler::Compiler::new();
+        c.compile_program(&program).unwrap();
+        return c.bytecode();
+    }
+
+    fn run(bytecode: compiler::Bytecode) -> String {
+        let mut machine = vm::VM::new(bytecode);
+        machine.run().unwrap();
+        return machine.last_popped_stack_elem().unwrap().inspect();
+    }
+
+    // Builds a file around a hand-written body, with a valid header and checksum.
+    fn wrap(body: &[u8], flags: u8) -> Vec<u8> {
+        let mut file: Vec<u8> = MAGIC.to_vec();
+        file.extend_from_slice(&FORMAT_VERSION.to_be_bytes());
+        file.push(flags);
+        file.extend_from_slice(&(body.len() as u32).to_be_bytes());
+        file.extend_from_slice(body);
+        file.extend_from_slice(&adler32(body).to_be_bytes());
+        return file;
+    }
+
+    // A body with the given encoded constants and main instruction stream.
+    fn main_body(constants: &[&[u8]], instructions: &[u8]) -> Vec<u8> {
+        let mut body: Vec<u8> = (constants.len() as u32).to_be_bytes().to_vec();
+        for constant in constants.iter() {
+            body.extend_from_slice(constant);
+        }
+        body.extend_from_slice(&(instructions.len() as u32).to_be_bytes());
+        body.extend_from_slice(instructions);
+        return body;
+    }
+
+    static PROGRAM: &str = "let greet = fn(name) { let suffix = \"!\"; fn() { \"hi \" + name + suffix } };\nlet n = if (2 > 1) { len([1]) * 10 } else { -10 };\ngreet(\"monkey\")() + \" \" + \"ok\"; n * 3";
+
+    #[test]
+    fn test_round_trip() {
+        for &debug_info in [false, true].iter() {
+            let bytecode: compiler::Bytecode = compile(PROGRAM);
+            let loaded: compiler::Bytecode = decode(&encode(&bytecode, debug_info)).unwrap();
+
+            assert_eq!(loaded.instructions, bytecode.instructions);
+            assert_eq!(loaded.constants.len(), bytecode.constants.len());
+            for (i, (got, want)) in loaded.constants.iter().zip(bytecode.constants.iter()).enumerate() {
+                match (got, want) {
+                    (object::Object::CompiledFunction(got), object::Object::CompiledFunction(want)) => {
+                        assert_eq!(got.instructions, want.instructions, "constants[{}]", i);
+                        assert_eq!(got.num_locals, want.num_locals, "constants[{}]", i);
+                        assert_eq!(got.num_parameters, want.num_parameters, "constants[{}]", i);
+                        assert_eq!(got.name, want.name, "constants[{}]", i);
+                        assert_eq!(got.lines.is_empty(), ! debug_info, "constants[{}]", i);
+                        if debug_info {
+                            assert_eq!(got.lines, want.lines, "constants[{}]", i);
+                        }
+                    },
+                    _ => assert_eq!(got.inspect(), want.inspect(), "constants[{}]", i),
+                }
+            }
+
+            if debug_info {
+                assert_eq!(loaded.lines, bytecode.lines);
+            } else {
+                assert!(loaded.lines.is_empty());
+            }
+            assert_eq!(run(loaded), "30");
+        }
+    }
+
+    #[test]
+    fn test_header() {
+        let data: Vec<u8> = encode(&compile("1"), false);
+        assert_eq!(&data[..4], b"MNKY");
+        assert_eq!(&data[4..6], &[0, 3]);
+        assert_eq!(data[6], 0);
+        assert_eq!(encode(&compile("1"), true)[6], FLAG_DEBUG_INFO);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let path = ::std::env::temp_dir().join(format!("monkey-artifact-test-{}.mkyc", ::std::process::id()));
+        save(&path, &compile(PROGRAM), true).unwrap();
+        let loaded = load(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(run(loaded.unwrap()), "30");
+
+        match load(&path) {
+            Err(LoadError::Io(ref err)) => assert_eq!(err.kind(), io::ErrorKind::NotFound),
+            other => assert!(false, "expected an I/O error, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_truncated_files_are_rejected() {
+        let data: Vec<u8> = encode(&compile(PROGRAM), true);
+        for len in 0..data.len() {
+            match decode(&data[..len]) {
+                Err(LoadError::Truncated{ offset }) => assert_eq!(offset, len),
+                other => assert!(false, "len {}: expected Truncated, got {:?}", len, other.map(|_| ())),
+            }
+        }
+    }
+
+    #[test]
+    fn test_corrupted_files_are_rejected() {
+        let data: Vec<u8> = encode(&compile(PROGRAM), true);
+
+        // every single-bit flip is caught by the header checks or the checksum
+        for i in 0..data.len() {
+            for bit in 0..8 {
+                let mut corrupted: Vec<u8> = data.clone();
+                corrupted[i] ^= 1 << bit;
+                assert!(decode(&corrupted).is_err(), "byte {} bit {} was not detected", i, bit);
+            }
+        }
+
+        let mut corrupted: Vec<u8> = data.clone();
+        corrupted[20] ^= 0xff;
+        match decode(&corrupted) {
+            Err(LoadError::ChecksumMismatch{ .. }) => (),
+            other => assert!(false, "expected ChecksumMismatch, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_load_errors() {
+        let valid: Vec<u8> = encode(&compile("1"), false);
+
+        let mut bad_magic: Vec<u8> = valid.clone();
+        bad_magic[0] = b'X';
+        let mut bad_version: Vec<u8> = valid.clone();
+        bad_version[5] = 9;
+        let mut bad_flags: Vec<u8> = valid.clone();
+        bad_flags[6] = 0x82;
+        let mut trailing: Vec<u8> = valid.clone();
+        trailing.push(0);
+
+        let tests: Vec<(Vec<u8>, &str)> = vec![
+            (bad_magic,                                                               "not a Monkey bytecode file"),
+            (bad_version,                                                             "unsupported format version 9, expected 3"),
+            (bad_flags,                                                               "unknown header flags 0x82"),
+            (trailing,                                                                "unexpected data after byte 36"),
+            (wrap(&[0, 0, 0, 1, 7], 0),                                               "byte 15: invalid constant tag 7"),
+            (wrap(&[0, 0, 0, 1, TAG_STRING, 0, 0, 0, 1, 0xff, 0, 0, 0, 0], 0),        "byte 20: string constant is not valid UTF-8"),
+            (wrap(&[0, 0, 0, 0, 0, 0, 0, 0, 9], 0),                                   "unexpected data after byte 19"),
+            (wrap(&[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 1, 99], FLAG_DEBUG_INFO),
+                                                                                      "byte 35: invalid node kind 99"),
+            (wrap(&main_body(&[], &[255]), 0),                                        "instruction 0: invalid opcode 255"),
+            (wrap(&main_body(&[], &[code::OpTrue as u8, code::OpJump as u8, 0]), 0),  "instruction 1: operands run past the end of the instructions"),
+            (wrap(&main_body(&[], &code::make(code::OpConstant, &[0])), 0),           "instruction 0: constant 0 out of range"),
+            (wrap(&main_body(&[], &code::make(code::OpGetLocal, &[0])), 0),           "instruction 0: local 0 out of range"),
+            (wrap(&main_body(&[], &code::make(code::OpJump, &[1])), 0),               "instruction 0: invalid jump target 1"),
+            (wrap(&main_body(&[], &code::make(code::OpJump, &[4])), 0),               "instruction 0: invalid jump target 4"),
+            (wrap(&main_body(&[], &code::make(code::OpGetBuiltin, &[200])), 0),       "instruction 0: builtin 200 out of range"),
+            (wrap(&main_body(&[], &code::make(code::OpGetFree, &[0])), 0),            "instruction 0: free variable 0 out of range"),
+            (wrap(&main_body(&[], &code::make(code::OpClosure, &[0, 0])), 0),         "instruction 0: constant 0 out of range"),
+            (wrap(&main_body(&[&[TAG_INTEGER, 0, 0, 0, 0, 0, 0, 0, 7]], &code::make(code::OpClosure, &[0, 0])), 0),
+                                                                                      "instruction 0: invalid closure over constant 0"),
+            (wrap(&main_body(&[&[TAG_FUNCTION, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 3, code::OpGetFree as u8, 1, code::OpReturnValue as u8]],
+                             &code::make(code::OpClosure, &[0, 1])), 0),
+                                                                                      "instruction 0: invalid closure over constant 0"),
+        ];
+
+        for (i, &(ref data, expected)) in tests.iter().enumerate() {
+            match decode(data) {
+                Ok(_) => assert!(false, "tests[{}]: file was accepted", i),
+                Err(err) => assert_eq!(err.to_string(), expected, "tests[{}]", i),
+            }
+        }
+
+        // jumping to the end of the instructions is allowed
+        assert!(decode(&wrap(&main_body(&[], &code::make(code::OpJump, &[3])), 0)).is_ok());
+    }
+
+    #[test]
+    fn test_loaded_bytecode_fails_with_errors() {
+        // these pass every loader check, but take values from an empty stack
+        let tests: Vec<code::Instructions> = vec![
+            code::make(code::OpPop, &[]),
+            code::make(code::OpAdd, &[]),
+            code::make(code::OpArray, &[3]),
+            code::make(code::OpCall, &[0]),
+            code::make(code::OpReturnValue, &[]),
+        ];
+
+        for (i, instructions) in tests.iter().enumerate() {
+            let bytecode: compiler::Bytecode = decode(&wrap(&main_body(&[], instructions), 0)).unwrap();
+            let mut machine = vm::VM::new(bytecode);
+            match machine.run() {
+                Err(err) => {
+                    assert_eq!(err.kind, error::ErrorKind::InvalidBytecode, "tests[{}]", i);
+                    assert_eq!(err.message, "stack underflow", "tests[{}]", i);
+                },
+                Ok(()) => assert!(false, "tests[{}]: program ran", i),
+            }
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+// Reasons a bytecode file is rejected by the loader. Offsets count bytes from
+// the start of the file, except for instruction errors, which count from the
+// start of the instruction stream that contains the bad instruction.
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    BadMagic,                           // the file does not start with the magic number
+    UnsupportedVersion {
+        found:      u16,
+        supported:  u16,
+    },
+    UnknownFlags(u8),                   // header flags this version does not define
+    Truncated {                         // the file ends before the data it declares
+        offset:     usize,
+    },
+    TrailingData {                      // bytes follow the data the header declares
+        offset:     usize,
+    },
+    ChecksumMismatch {
+        expected:   u32,
+        found:      u32,
+    },
+    InvalidConstantTag {
+        tag:        u8,
+        offset:     usize,
+    },
+    InvalidString {                     // a string constant is not valid UTF-8
+        offset:     usize,
+    },
+    InvalidNodeKind {                   // a line table entry names no ast::NodeKind
+        byte:       u8,
+        offset:     usize,
+    },
+    InvalidOpcode {
+        byte:       u8,
+        offset:     usize,
+    },
+    TruncatedInstruction {              // the operands of the last instruction are cut off
+        offset:     usize,
+    },
+    ConstantOutOfRange {
+        index:      usize,
+        offset:     usize,
+    },
+    LocalOutOfRange {
+        index:      usize,
+        offset:     usize,
+    },
+    BuiltinOutOfRange {
+        index:      usize,
+        offset:     usize,
+    },
+    FreeOutOfRange {
+        index:      usize,
+        offset:     usize,
+    },
+    InvalidClosure {                    // the constant is no function, or it reads more free variables than captured
+        index:      usize,
+        offset:     usize,
+    },
+    InvalidJumpTarget {                 // the target is not the start of an instruction
+        target:     usize,
+        offset:     usize,
+    },
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match *self {
+            LoadError::Io(ref err) =>
+                write!(f, "{}", err),
+            LoadError::BadMagic =>
+                write!(f, "not a Monkey bytecode file"),
+            LoadError::UnsupportedVersion{ found, supported } =>
+                write!(f, "unsupported format version {}, expected {}", found, supported),
+            LoadError::UnknownFlags(flags) =>
+                write!(f, "unknown header flags {:#04x}", flags),
+            LoadError::Truncated{ offset } =>
+                write!(f, "file is truncated at byte {}", offset),
+            LoadError::TrailingData{ offset } =>
+                write!(f, "unexpected data after byte {}", offset),
+            LoadError::ChecksumMismatch{ expected, found } =>
+                write!(f, "checksum mismatch: expected {:#010x}, found {:#010x}", expected, found),
+            LoadError::InvalidConstantTag{ tag, offset } =>
+                write!(f, "byte {}: invalid constant tag {}", offset, tag),
+            LoadError::InvalidString{ offset } =>
+                write!(f, "byte {}: string constant is not valid UTF-8", offset),
+            LoadError::InvalidNodeKind{ byte, offset } =>
+                write!(f, "byte {}: invalid node kind {}", offset, byte),
+            LoadError::InvalidOpcode{ byte, offset } =>
+                write!(f, "instruction {}: invalid opcode {}", offset, byte),
+            LoadError::TruncatedInstruction{ offset } =>
+                write!(f, "instruction {}: operands run past the end of the instructions", offset),
+            LoadError::ConstantOutOfRange{ index, offset } =>
+                write!(f, "instruction {}: constant {} out of range", offset, index),
+            LoadError::LocalOutOfRange{ index, offset } =>
+                write!(f, "instruction {}: local {} out of range", offset, index),
+            LoadError::BuiltinOutOfRange{ index, offset } =>
+                write!(f, "instruction {}: builtin {} out of range", offset, index),
+            LoadError::FreeOutOfRange{ index, offset } =>
+                write!(f, "instruction {}: free variable {} out of range", offset, index),
+            LoadError::InvalidClosure{ index, offset } =>
+                write!(f, "instruction {}: invalid closure over constant {}", offset, index),
+            LoadError::InvalidJumpTarget{ target, offset } =>
+                write!(f, "instruction {}: invalid jump target {}", offset, target),
+        };
+    }
+}
+
+impl Error for LoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        return match *self {
+            LoadError::Io(ref err) => Some(err),
+            _ => None,
+        };
+    }
+}
+
+impl From<io::Error> for LoadError {
+    fn from(err: io::Error) -> LoadError {
+        return LoadError::Io(err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        assert_eq!(LoadError::BadMagic.to_string(), "not a Monkey bytecode file");
+        assert_eq!(LoadError::UnsupportedVersion{ found: 7, supported: 1 }.to_string(), "unsupported format version 7, expected 1");
+        assert_eq!(LoadError::UnknownFlags(0x80).to_string(), "unknown header flags 0x80");
+        assert_eq!(LoadError::Truncated{ offset: 12 }.to_string(), "file is truncated at byte 12");
+        assert_eq!(LoadError::ChecksumMismatch{ expected: 1, found: 2 }.to_string(), "checksum mismatch: expected 0x00000001, found 0x00000002");
+        assert_eq!(LoadError::InvalidJumpTarget{ target: 5, offset: 1 }.to_string(), "instruction 1: invalid jump target 5");
+    }
+}
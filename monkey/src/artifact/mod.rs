@@ -0,0 +1,3 @@
+pub mod artifact;
+
+pub mod error;
@@ -1,20 +1,209 @@
+use std::rc::Rc;
 use token::*;
 
 pub trait Node {
     fn token_literal(&self) -> String;
     fn to_string(&self) -> String;
+    fn span(&self) -> token::Span;      // location of the token that introduced the node
 }
 
-pub trait Statement: Node {
-    fn statement_node(&self);
+#[derive(Clone, Debug, PartialEq)]
+pub enum Statement {
+    Let(LetStatement),
+    Return(ReturnStatement),
+    Expression(ExpressionStatement),
 }
 
-pub trait Expression: Node {
-    fn expression_node(&self);
+impl Node for Statement {
+    fn token_literal(&self) -> String {
+        return match *self {
+            Statement::Let(ref stmt) => stmt.token_literal(),
+            Statement::Return(ref stmt) => stmt.token_literal(),
+            Statement::Expression(ref stmt) => stmt.token_literal(),
+        };
+    }
+
+    fn to_string(&self) -> String {
+        return match *self {
+            Statement::Let(ref stmt) => stmt.to_string(),
+            Statement::Return(ref stmt) => stmt.to_string(),
+            Statement::Expression(ref stmt) => stmt.to_string(),
+        };
+    }
+
+    fn span(&self) -> token::Span {
+        return match *self {
+            Statement::Let(ref stmt) => stmt.span(),
+            Statement::Return(ref stmt) => stmt.span(),
+            Statement::Expression(ref stmt) => stmt.span(),
+        };
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expression {
+    Identifier(Identifier),
+    IntegerLiteral(IntegerLiteral),
+    Boolean(Boolean),
+    StringLiteral(StringLiteral),
+    Prefix(PrefixExpression),
+    Infix(InfixExpression),
+    If(IfExpression),
+    FunctionLiteral(FunctionLiteral),
+    Call(CallExpression),
+    Array(ArrayLiteral),
+    Index(IndexExpression),
+    Hash(HashLiteral),
+}
+
+impl Node for Expression {
+    fn token_literal(&self) -> String {
+        return match *self {
+            Expression::Identifier(ref exp) => exp.token_literal(),
+            Expression::IntegerLiteral(ref exp) => exp.token_literal(),
+            Expression::Boolean(ref exp) => exp.token_literal(),
+            Expression::StringLiteral(ref exp) => exp.token_literal(),
+            Expression::Prefix(ref exp) => exp.token_literal(),
+            Expression::Infix(ref exp) => exp.token_literal(),
+            Expression::If(ref exp) => exp.token_literal(),
+            Expression::FunctionLiteral(ref exp) => exp.token_literal(),
+            Expression::Call(ref exp) => exp.token_literal(),
+            Expression::Array(ref exp) => exp.token_literal(),
+            Expression::Index(ref exp) => exp.token_literal(),
+            Expression::Hash(ref exp) => exp.token_literal(),
+        };
+    }
+
+    fn to_string(&self) -> String {
+        return match *self {
+            Expression::Identifier(ref exp) => exp.to_string(),
+            Expression::IntegerLiteral(ref exp) => exp.to_string(),
+            Expression::Boolean(ref exp) => exp.to_string(),
+            Expression::StringLiteral(ref exp) => exp.to_string(),
+            Expression::Prefix(ref exp) => exp.to_string(),
+            Expression::Infix(ref exp) => exp.to_string(),
+            Expression::If(ref exp) => exp.to_string(),
+            Expression::FunctionLiteral(ref exp) => exp.to_string(),
+            Expression::Call(ref exp) => exp.to_string(),
+            Expression::Array(ref exp) => exp.to_string(),
+            Expression::Index(ref exp) => exp.to_string(),
+            Expression::Hash(ref exp) => exp.to_string(),
+        };
+    }
+
+    fn span(&self) -> token::Span {
+        return match *self {
+            Expression::Identifier(ref exp) => exp.span(),
+            Expression::IntegerLiteral(ref exp) => exp.span(),
+            Expression::Boolean(ref exp) => exp.span(),
+            Expression::StringLiteral(ref exp) => exp.span(),
+            Expression::Prefix(ref exp) => exp.span(),
+            Expression::Infix(ref exp) => exp.span(),
+            Expression::If(ref exp) => exp.span(),
+            Expression::FunctionLiteral(ref exp) => exp.span(),
+            Expression::Call(ref exp) => exp.span(),
+            Expression::Array(ref exp) => exp.span(),
+            Expression::Index(ref exp) => exp.span(),
+            Expression::Hash(ref exp) => exp.span(),
+        };
+    }
+}
+
+// The kind of a statement or expression node, e.g. to tell which node an
+// instruction was compiled from. The discriminants are written to bytecode
+// files, so a kind that goes away leaves its byte reserved rather than
+// renumbering the rest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum NodeKind {
+    LetStatement,
+    ReturnStatement,
+    ExpressionStatement,
+    // 3 was BlockStatement, which no node reports since blocks only appear inside expressions
+    Identifier = 4,
+    IntegerLiteral,
+    Boolean,
+    StringLiteral,
+    PrefixExpression,
+    InfixExpression,
+    IfExpression,
+    FunctionLiteral,
+    CallExpression,
+    ArrayLiteral,
+    IndexExpression,
+    HashLiteral,
+}
+
+// Every node kind in byte order, so that a byte can be mapped back to its kind.
+// Reserved bytes map to None.
+static NODE_KINDS: &[Option<NodeKind>] = &[
+    Some(NodeKind::LetStatement), Some(NodeKind::ReturnStatement), Some(NodeKind::ExpressionStatement), None,
+    Some(NodeKind::Identifier), Some(NodeKind::IntegerLiteral), Some(NodeKind::Boolean), Some(NodeKind::StringLiteral),
+    Some(NodeKind::PrefixExpression), Some(NodeKind::InfixExpression), Some(NodeKind::IfExpression), Some(NodeKind::FunctionLiteral),
+    Some(NodeKind::CallExpression), Some(NodeKind::ArrayLiteral), Some(NodeKind::IndexExpression), Some(NodeKind::HashLiteral),
+];
+
+impl NodeKind {
+    pub fn from_byte(byte: u8) -> Option<NodeKind> {
+        return match NODE_KINDS.get(byte as usize) {
+            Some(kind) => *kind,
+            None => None,
+        };
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        return match *self {
+            NodeKind::LetStatement          => "LetStatement",
+            NodeKind::ReturnStatement       => "ReturnStatement",
+            NodeKind::ExpressionStatement   => "ExpressionStatement",
+            NodeKind::Identifier            => "Identifier",
+            NodeKind::IntegerLiteral        => "IntegerLiteral",
+            NodeKind::Boolean               => "Boolean",
+            NodeKind::StringLiteral         => "StringLiteral",
+            NodeKind::PrefixExpression      => "PrefixExpression",
+            NodeKind::InfixExpression       => "InfixExpression",
+            NodeKind::IfExpression          => "IfExpression",
+            NodeKind::FunctionLiteral       => "FunctionLiteral",
+            NodeKind::CallExpression        => "CallExpression",
+            NodeKind::ArrayLiteral          => "ArrayLiteral",
+            NodeKind::IndexExpression       => "IndexExpression",
+            NodeKind::HashLiteral           => "HashLiteral",
+        };
+    }
+}
+
+impl Statement {
+    pub fn kind(&self) -> NodeKind {
+        return match *self {
+            Statement::Let(_) => NodeKind::LetStatement,
+            Statement::Return(_) => NodeKind::ReturnStatement,
+            Statement::Expression(_) => NodeKind::ExpressionStatement,
+        };
+    }
+}
+
+impl Expression {
+    pub fn kind(&self) -> NodeKind {
+        return match *self {
+            Expression::Identifier(_) => NodeKind::Identifier,
+            Expression::IntegerLiteral(_) => NodeKind::IntegerLiteral,
+            Expression::Boolean(_) => NodeKind::Boolean,
+            Expression::StringLiteral(_) => NodeKind::StringLiteral,
+            Expression::Prefix(_) => NodeKind::PrefixExpression,
+            Expression::Infix(_) => NodeKind::InfixExpression,
+            Expression::If(_) => NodeKind::IfExpression,
+            Expression::FunctionLiteral(_) => NodeKind::FunctionLiteral,
+            Expression::Call(_) => NodeKind::CallExpression,
+            Expression::Array(_) => NodeKind::ArrayLiteral,
+            Expression::Index(_) => NodeKind::IndexExpression,
+            Expression::Hash(_) => NodeKind::HashLiteral,
+        };
+    }
 }
 
+#[derive(Clone, Debug, PartialEq)]
 pub struct Program {
-    pub statements: Vec<Box<dyn Statement>>,
+    pub statements: Vec<Statement>,
 }
 
 impl Program {
@@ -39,22 +228,31 @@ impl Node for Program {
         let mut builder: String = String::new();
 
         for stmt in self.statements.iter() {
-            builder.push_str(&(**stmt).to_string());
+            builder.push_str(&stmt.to_string());
             builder.push('\n');
         }
 
         return builder;
     }
+
+    fn span(&self) -> token::Span {
+        if self.statements.len() > 0 {
+            return self.statements[0].span();
+        } else {
+            return token::Span::default();
+        }
+    }
 }
 
+#[derive(Clone, Debug, PartialEq)]
 pub struct LetStatement {
     pub token:      token::Token,       // the token.LET token
     pub name:       Identifier,
-    pub value:      Option<Box<dyn Expression>>,
+    pub value:      Expression,
 }
 
 impl LetStatement {
-    pub fn new(tok: token::Token, name: Identifier, value: Option<Box<dyn Expression>>) -> LetStatement {
+    pub fn new(tok: token::Token, name: Identifier, value: Expression) -> LetStatement {
         return LetStatement{
             token:      tok,
             name:       name,
@@ -75,29 +273,25 @@ impl Node for LetStatement {
         builder.push(' ');
         builder.push_str(&self.name.to_string());
         builder.push(' '); builder.push('='); builder.push(' ');
-
-        match self.value.as_ref().clone() {
-            Some(val) => builder.push_str(&(*val).to_string()),
-            None => (),
-        }
-
+        builder.push_str(&self.value.to_string());
         builder.push(';');
 
         return builder;
     }
-}
 
-impl Statement for LetStatement {
-    fn statement_node(&self) {}
+    fn span(&self) -> token::Span {
+        return self.token.span;
+    }
 }
 
+#[derive(Clone, Debug, PartialEq)]
 pub struct ReturnStatement {
     pub token:          token::Token,   // the token.RETURN token
-    pub return_value:   Option<Box<dyn Expression>>,
+    pub return_value:   Expression,
 }
 
 impl ReturnStatement {
-    pub fn new(tok: token::Token, ret_value: Option<Box<dyn Expression>>) -> ReturnStatement {
+    pub fn new(tok: token::Token, ret_value: Expression) -> ReturnStatement {
         return ReturnStatement{
             token:          tok,
             return_value:   ret_value,
@@ -115,29 +309,25 @@ impl Node for ReturnStatement {
 
         builder.push_str(&self.token_literal());
         builder.push(' ');
-
-        match self.return_value.as_ref().clone() {
-            Some(ret_val) => builder.push_str(&(*ret_val).to_string()),
-            None => (),
-        }
-
+        builder.push_str(&self.return_value.to_string());
         builder.push(';');
 
         return builder;
     }
-}
 
-impl Statement for ReturnStatement {
-    fn statement_node(&self) {}
+    fn span(&self) -> token::Span {
+        return self.token.span;
+    }
 }
 
+#[derive(Clone, Debug, PartialEq)]
 pub struct ExpressionStatement {
     pub token:          token::Token,   // the first token of the expression
-    pub expression:     Option<Box<dyn Expression>>,
+    pub expression:     Expression,
 }
 
 impl ExpressionStatement {
-    pub fn new(tok: token::Token, exp: Option<Box<dyn Expression>>) -> ExpressionStatement {
+    pub fn new(tok: token::Token, exp: Expression) -> ExpressionStatement {
         return ExpressionStatement{
             token:          tok,
             expression:     exp,
@@ -153,21 +343,57 @@ impl Node for ExpressionStatement {
     fn to_string(&self) -> String {
         let mut builder: String = String::new();
 
-        match self.expression.as_ref().clone() {
-            Some(exp) => builder.push_str(&(*exp).to_string()),
-            None => (),
-        }
-
+        builder.push_str(&self.expression.to_string());
         builder.push(';');
 
         return builder;
     }
+
+    fn span(&self) -> token::Span {
+        return self.token.span;
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlockStatement {
+    pub token:          token::Token,   // the { token
+    pub statements:     Vec<Statement>,
+}
+
+impl BlockStatement {
+    pub fn new(tok: token::Token) -> BlockStatement {
+        return BlockStatement{
+            token:          tok,
+            statements:     Vec::new(),
+        };
+    }
 }
 
-impl Statement for ExpressionStatement {
-    fn statement_node(&self) {}
+impl Node for BlockStatement {
+    fn token_literal(&self) -> String {
+        return self.token.literal.clone();
+    }
+
+    fn to_string(&self) -> String {
+        let mut builder: String = String::new();
+
+        builder.push('{');
+        for stmt in self.statements.iter() {
+            builder.push(' ');
+            builder.push_str(&stmt.to_string());
+        }
+        builder.push(' ');
+        builder.push('}');
+
+        return builder;
+    }
+
+    fn span(&self) -> token::Span {
+        return self.token.span;
+    }
 }
 
+#[derive(Clone, Debug, PartialEq)]
 pub struct Identifier {
     pub token:      token::Token,       // the token.IDENT token
     pub value:      String,
@@ -190,12 +416,13 @@ impl Node for Identifier {
     fn to_string(&self) -> String {
         return self.value.clone();
     }
-}
 
-impl Expression for Identifier {
-    fn expression_node(&self) {}
+    fn span(&self) -> token::Span {
+        return self.token.span;
+    }
 }
 
+#[derive(Clone, Debug, PartialEq)]
 pub struct IntegerLiteral {
     pub token:      token::Token,       // the token.INT token
     pub value:      i64,
@@ -218,24 +445,95 @@ impl Node for IntegerLiteral {
     fn to_string(&self) -> String {
         return self.token_literal();
     }
+
+    fn span(&self) -> token::Span {
+        return self.token.span;
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Boolean {
+    pub token:      token::Token,       // the token.TRUE or token.FALSE token
+    pub value:      bool,
+}
+
+impl Boolean {
+    pub fn new(tok: token::Token, value: bool) -> Boolean {
+        return Boolean{
+            token:      tok,
+            value:      value,
+        };
+    }
+}
+
+impl Node for Boolean {
+    fn token_literal(&self) -> String {
+        return self.token.literal.clone();
+    }
+
+    fn to_string(&self) -> String {
+        return self.token_literal();
+    }
+
+    fn span(&self) -> token::Span {
+        return self.token.span;
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StringLiteral {
+    pub token:      token::Token,       // the token.STRING token
+    pub value:      String,             // the contents with escape sequences resolved
+}
+
+impl StringLiteral {
+    pub fn new(tok: token::Token, value: String) -> StringLiteral {
+        return StringLiteral{
+            token:      tok,
+            value:      value,
+        };
+    }
 }
 
-impl Expression for IntegerLiteral {
-    fn expression_node(&self) {}
+impl Node for StringLiteral {
+    fn token_literal(&self) -> String {
+        return self.token.literal.clone();
+    }
+
+    // Quotes and re-escapes the value so the output lexes back to the same string.
+    fn to_string(&self) -> String {
+        let mut out: String = String::from("\"");
+        for ch in self.value.chars() {
+            match ch {
+                '\n' => out.push_str("\\n"),
+                '\t' => out.push_str("\\t"),
+                '"' => out.push_str("\\\""),
+                '\\' => out.push_str("\\\\"),
+                _ => out.push(ch),
+            }
+        }
+        out.push('"');
+        return out;
+    }
+
+    fn span(&self) -> token::Span {
+        return self.token.span;
+    }
 }
 
+#[derive(Clone, Debug, PartialEq)]
 pub struct PrefixExpression {
     pub token:          token::Token,       // The prefix, e.g. !
     pub operator:       String,
-    pub right:          Option<Box<dyn Expression>>,
+    pub right:          Box<Expression>,
 }
 
 impl PrefixExpression {
-    pub fn new(tok: token::Token, op: String, right_exp: Option<Box<dyn Expression>>) -> PrefixExpression {
+    pub fn new(tok: token::Token, op: String, right_exp: Expression) -> PrefixExpression {
         return PrefixExpression{
             token:          tok,
             operator:       op,
-            right:          right_exp,
+            right:          Box::new(right_exp),
         };
     }
 }
@@ -250,34 +548,32 @@ impl Node for PrefixExpression {
 
         builder.push('(');
         builder.push_str(&self.operator);
-        match self.right.as_ref().clone() {
-            Some(exp) => builder.push_str(&(**exp).to_string()),
-            None => (),
-        }
+        builder.push_str(&self.right.to_string());
         builder.push(')');
 
         return builder;
     }
-}
 
-impl Expression for PrefixExpression {
-    fn expression_node(&self) {}
+    fn span(&self) -> token::Span {
+        return self.token.span;
+    }
 }
 
+#[derive(Clone, Debug, PartialEq)]
 pub struct InfixExpression {
     pub token:          token::Token,       // The operator token, e.g. +
-    pub left:           Option<Box<dyn Expression>>,
+    pub left:           Box<Expression>,
     pub operator:       String,
-    pub right:          Option<Box<dyn Expression>>,
+    pub right:          Box<Expression>,
 }
 
 impl InfixExpression {
-    pub fn new(tok: token::Token, left_exp: Option<Box<dyn Expression>>, op: String, right_exp: Option<Box<dyn Expression>>) -> InfixExpression {
+    pub fn new(tok: token::Token, left_exp: Expression, op: String, right_exp: Expression) -> InfixExpression {
         return InfixExpression{
             token:          tok,
-            left:           left_exp,
+            left:           Box::new(left_exp),
             operator:       op,
-            right:          right_exp,
+            right:          Box::new(right_exp),
         };
     }
 }
@@ -291,77 +587,384 @@ impl Node for InfixExpression {
         let mut builder: String = String::new();
 
         builder.push('(');
-        match self.left.as_ref().clone() {
-            Some(exp) => builder.push_str(&(**exp).to_string()),
-            None => (),
-        }
+        builder.push_str(&self.left.to_string());
         builder.push(' ');
         builder.push_str(&self.operator);
         builder.push(' ');
-        match self.right.as_ref().clone() {
-            Some(exp) => builder.push_str(&(**exp).to_string()),
+        builder.push_str(&self.right.to_string());
+        builder.push(')');
+
+        return builder;
+    }
+
+    fn span(&self) -> token::Span {
+        return self.token.span;
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct IfExpression {
+    pub token:          token::Token,       // the token.IF token
+    pub condition:      Box<Expression>,
+    pub consequence:    BlockStatement,
+    pub alternative:    Option<BlockStatement>,
+}
+
+impl IfExpression {
+    pub fn new(tok: token::Token, cond: Expression, cons: BlockStatement, alt: Option<BlockStatement>) -> IfExpression {
+        return IfExpression{
+            token:          tok,
+            condition:      Box::new(cond),
+            consequence:    cons,
+            alternative:    alt,
+        };
+    }
+}
+
+impl Node for IfExpression {
+    fn token_literal(&self) -> String {
+        return self.token.literal.clone();
+    }
+
+    fn to_string(&self) -> String {
+        let mut builder: String = String::new();
+
+        builder.push_str(&self.token_literal());
+        builder.push(' ');
+        builder.push('(');
+        builder.push_str(&self.condition.to_string());
+        builder.push(')');
+        builder.push(' ');
+        builder.push_str(&self.consequence.to_string());
+
+        match self.alternative.as_ref() {
+            Some(alt) => {
+                builder.push_str(" else ");
+                builder.push_str(&alt.to_string());
+            },
             None => (),
         }
+
+        return builder;
+    }
+
+    fn span(&self) -> token::Span {
+        return self.token.span;
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FunctionLiteral {
+    pub token:          token::Token,       // the token.FUNCTION token
+    pub parameters:     Rc<Vec<Identifier>>,    // shared with the function objects created from this literal
+    pub body:           Rc<BlockStatement>,
+}
+
+impl FunctionLiteral {
+    pub fn new(tok: token::Token, params: Vec<Identifier>, body: BlockStatement) -> FunctionLiteral {
+        return FunctionLiteral{
+            token:          tok,
+            parameters:     Rc::new(params),
+            body:           Rc::new(body),
+        };
+    }
+}
+
+impl Node for FunctionLiteral {
+    fn token_literal(&self) -> String {
+        return self.token.literal.clone();
+    }
+
+    fn to_string(&self) -> String {
+        let mut builder: String = String::new();
+
+        let params: Vec<String> = self.parameters.iter().map(|p| p.to_string()).collect();
+
+        builder.push_str(&self.token_literal());
+        builder.push('(');
+        builder.push_str(&params.join(", "));
+        builder.push(')');
+        builder.push(' ');
+        builder.push_str(&self.body.to_string());
+
+        return builder;
+    }
+
+    fn span(&self) -> token::Span {
+        return self.token.span;
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CallExpression {
+    pub token:          token::Token,       // the ( token
+    pub function:       Box<Expression>,    // Identifier or FunctionLiteral
+    pub arguments:      Vec<Expression>,
+}
+
+impl CallExpression {
+    pub fn new(tok: token::Token, func: Expression, args: Vec<Expression>) -> CallExpression {
+        return CallExpression{
+            token:          tok,
+            function:       Box::new(func),
+            arguments:      args,
+        };
+    }
+}
+
+impl Node for CallExpression {
+    fn token_literal(&self) -> String {
+        return self.token.literal.clone();
+    }
+
+    fn to_string(&self) -> String {
+        let mut builder: String = String::new();
+
+        let args: Vec<String> = self.arguments.iter().map(|a| a.to_string()).collect();
+
+        builder.push_str(&self.function.to_string());
+        builder.push('(');
+        builder.push_str(&args.join(", "));
         builder.push(')');
 
         return builder;
     }
+
+    fn span(&self) -> token::Span {
+        return self.token.span;
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ArrayLiteral {
+    pub token:          token::Token,       // the [ token
+    pub elements:       Vec<Expression>,
+}
+
+impl ArrayLiteral {
+    pub fn new(tok: token::Token, elements: Vec<Expression>) -> ArrayLiteral {
+        return ArrayLiteral{
+            token:          tok,
+            elements:       elements,
+        };
+    }
+}
+
+impl Node for ArrayLiteral {
+    fn token_literal(&self) -> String {
+        return self.token.literal.clone();
+    }
+
+    fn to_string(&self) -> String {
+        let mut builder: String = String::new();
+
+        let elements: Vec<String> = self.elements.iter().map(|e| e.to_string()).collect();
+
+        builder.push('[');
+        builder.push_str(&elements.join(", "));
+        builder.push(']');
+
+        return builder;
+    }
+
+    fn span(&self) -> token::Span {
+        return self.token.span;
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct IndexExpression {
+    pub token:          token::Token,       // the [ token
+    pub left:           Box<Expression>,
+    pub index:          Box<Expression>,
+}
+
+impl IndexExpression {
+    pub fn new(tok: token::Token, left: Expression, index: Expression) -> IndexExpression {
+        return IndexExpression{
+            token:          tok,
+            left:           Box::new(left),
+            index:          Box::new(index),
+        };
+    }
+}
+
+impl Node for IndexExpression {
+    fn token_literal(&self) -> String {
+        return self.token.literal.clone();
+    }
+
+    fn to_string(&self) -> String {
+        let mut builder: String = String::new();
+
+        builder.push('(');
+        builder.push_str(&self.left.to_string());
+        builder.push('[');
+        builder.push_str(&self.index.to_string());
+        builder.push_str("])");
+
+        return builder;
+    }
+
+    fn span(&self) -> token::Span {
+        return self.token.span;
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct HashLiteral {
+    pub token:          token::Token,       // the { token
+    pub pairs:          Vec<(Expression, Expression)>,  // key-value pairs in source order
+}
+
+impl HashLiteral {
+    pub fn new(tok: token::Token, pairs: Vec<(Expression, Expression)>) -> HashLiteral {
+        return HashLiteral{
+            token:          tok,
+            pairs:          pairs,
+        };
+    }
 }
 
-impl Expression for InfixExpression {
-    fn expression_node(&self) {}
+impl Node for HashLiteral {
+    fn token_literal(&self) -> String {
+        return self.token.literal.clone();
+    }
+
+    fn to_string(&self) -> String {
+        let mut builder: String = String::new();
+
+        let pairs: Vec<String> = self.pairs.iter().map(|(k, v)| format!("{}: {}", k.to_string(), v.to_string())).collect();
+
+        builder.push('{');
+        builder.push_str(&pairs.join(", "));
+        builder.push('}');
+
+        return builder;
+    }
+
+    fn span(&self) -> token::Span {
+        return self.token.span;
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn ident(name: &str) -> Identifier {
+        return Identifier::new(token::Token::new(token::IDENT, String::from(name)), String::from(name));
+    }
+
+    fn int(value: i64) -> Expression {
+        return Expression::IntegerLiteral(IntegerLiteral::new(token::Token::new(token::INT, value.to_string()), value));
+    }
+
     #[test]
     fn test_to_string() {
-        let ls = LetStatement::new(
-            token::Token::new(token::LET, String::from(token::LET)),
-            Identifier::new(token::Token::new(token::IDENT, String::from("myVar")), String::from("myVar")),
-            Some(Box::new(Identifier::new(token::Token::new(token::IDENT, String::from("anotherVar")), String::from("anotherVar"))))
-        );
+        let ls = Statement::Let(LetStatement::new(
+            token::Token::new(token::LET, token::LET.to_string()),
+            ident("myVar"),
+            Expression::Identifier(ident("anotherVar"))
+        ));
         assert_eq!(ls.to_string(), "let myVar = anotherVar;");
-        let rs = ReturnStatement::new(
-            token::Token::new(token::RETURN, String::from(token::RETURN)),
-            Some(Box::new(Identifier::new(token::Token::new(token::IDENT, String::from("myVar")), String::from("myVar"))))
-        );
+        let rs = Statement::Return(ReturnStatement::new(
+            token::Token::new(token::RETURN, token::RETURN.to_string()),
+            Expression::Identifier(ident("myVar"))
+        ));
         assert_eq!(rs.to_string(), "return myVar;");
-        let es = ExpressionStatement::new(
-            token::Token::new(token::PLUS, String::from(token::PLUS)),
-            Some(Box::new(Identifier::new(token::Token::new(token::IDENT, String::from("myVar")), String::from("myVar"))))
-        );
+        let es = Statement::Expression(ExpressionStatement::new(
+            token::Token::new(token::PLUS, token::PLUS.to_string()),
+            Expression::Identifier(ident("myVar"))
+        ));
         assert_eq!(es.to_string(), "myVar;");
-        let id = Identifier::new(
-            token::Token::new(token::IDENT, String::from("myVar")),
-            String::from("myVar")
-        );
+        let id = ident("myVar");
         assert_eq!(id.to_string(), "myVar");
-        let il = IntegerLiteral::new(
-            token::Token::new(token::INT, String::from("5")),
-            5
-        );
+        let il = int(5);
         assert_eq!(il.to_string(), "5");
+        let bl = Boolean::new(
+            token::Token::new(token::TRUE, String::from("true")),
+            true
+        );
+        assert_eq!(bl.to_string(), "true");
         let pe = PrefixExpression::new(
             token::Token::new(token::MINUS, String::from("-")),
             String::from("-"),
-            Some(Box::new(IntegerLiteral::new(token::Token::new(token::INT, String::from("5")), 5)))
+            int(5)
         );
         assert_eq!(pe.to_string(), "(-5)");
         let ie = InfixExpression::new(
-            token::Token::new(token::INT, String::from("5")),
-            Some(Box::new(IntegerLiteral::new(token::Token::new(token::INT, String::from("5")), 5))),
+            token::Token::new(token::PLUS, String::from("+")),
+            int(5),
             String::from("+"),
-            Some(Box::new(IntegerLiteral::new(token::Token::new(token::INT, String::from("10")), 10)))
+            int(10)
         );
         assert_eq!(ie.to_string(), "(5 + 10)");
+        let mut bs = BlockStatement::new(token::Token::new(token::LBRACE, String::from("{")));
+        bs.statements.push(Statement::Expression(ExpressionStatement::new(
+            token::Token::new(token::IDENT, String::from("x")),
+            Expression::Identifier(ident("x"))
+        )));
+        assert_eq!(bs.to_string(), "{ x; }");
+        let ife = IfExpression::new(
+            token::Token::new(token::IF, String::from("if")),
+            Expression::Identifier(ident("x")),
+            bs.clone(),
+            Some(BlockStatement::new(token::Token::new(token::LBRACE, String::from("{"))))
+        );
+        assert_eq!(ife.to_string(), "if (x) { x; } else { }");
+        let fl = FunctionLiteral::new(
+            token::Token::new(token::FUNCTION, String::from("fn")),
+            vec![ident("x"), ident("y")],
+            bs
+        );
+        assert_eq!(fl.to_string(), "fn(x, y) { x; }");
+        let ce = CallExpression::new(
+            token::Token::new(token::LPAREN, String::from("(")),
+            Expression::Identifier(ident("add")),
+            vec![int(1), Expression::Identifier(ident("y"))]
+        );
+        assert_eq!(ce.to_string(), "add(1, y)");
         let mut program = Program::new();
-        program.statements.push(Box::new(ls));
-        program.statements.push(Box::new(rs));
-        program.statements.push(Box::new(es));
+        program.statements.push(ls);
+        program.statements.push(rs);
+        program.statements.push(es);
         assert_eq!(program.to_string(), "let myVar = anotherVar;\nreturn myVar;\nmyVar;\n");
     }
-}
 
+    #[test]
+    fn test_nodes_are_comparable() {
+        let a = Expression::Infix(InfixExpression::new(
+            token::Token::new(token::PLUS, String::from("+")),
+            int(1),
+            String::from("+"),
+            int(2)
+        ));
+        let b = a.clone();
+        assert_eq!(a, b);
+
+        match a {
+            Expression::Infix(ref ie) => {
+                assert_eq!(*ie.left, int(1));
+                assert_eq!(*ie.right, int(2));
+            },
+            ref other => assert!(false, "expression is not Infix, got={:?}", other),
+        }
+
+        assert!(a != int(3));
+    }
+
+    #[test]
+    fn test_node_kind_from_byte() {
+        for (i, kind) in NODE_KINDS.iter().enumerate() {
+            match *kind {
+                Some(kind) => assert_eq!(kind as usize, i, "NODE_KINDS[{}] is out of order", i),
+                None => (),
+            }
+            assert_eq!(NodeKind::from_byte(i as u8), *kind);
+        }
+        assert_eq!(NodeKind::from_byte(3), None);
+        assert_eq!(NodeKind::from_byte(NODE_KINDS.len() as u8), None);
+        assert_eq!(NodeKind::HashLiteral.as_str(), "HashLiteral");
+    }
+}
@@ -0,0 +1,290 @@
+use std::rc::Rc;
+use ast::*;
+
+// Owning rewrite of the AST. Every method defaults to the matching noop_fold_*
+// function, which rebuilds the node from its folded children. Expression and
+// statement folds return the enum rather than the node struct, so a pass can
+// replace a node with one of a different kind (e.g. an infix expression with
+// the integer literal it evaluates to).
+pub trait Folder {
+    fn fold_program(&mut self, program: ast::Program) -> ast::Program {
+        return noop_fold_program(self, program);
+    }
+
+    fn fold_statement(&mut self, stmt: ast::Statement) -> ast::Statement {
+        return noop_fold_statement(self, stmt);
+    }
+
+    fn fold_let_statement(&mut self, stmt: ast::LetStatement) -> ast::Statement {
+        return noop_fold_let_statement(self, stmt);
+    }
+
+    fn fold_return_statement(&mut self, stmt: ast::ReturnStatement) -> ast::Statement {
+        return noop_fold_return_statement(self, stmt);
+    }
+
+    fn fold_expression_statement(&mut self, stmt: ast::ExpressionStatement) -> ast::Statement {
+        return noop_fold_expression_statement(self, stmt);
+    }
+
+    fn fold_block_statement(&mut self, block: ast::BlockStatement) -> ast::BlockStatement {
+        return noop_fold_block_statement(self, block);
+    }
+
+    fn fold_expression(&mut self, exp: ast::Expression) -> ast::Expression {
+        return noop_fold_expression(self, exp);
+    }
+
+    // Called for identifiers in expression position as well as for the names
+    // bound by let statements and function parameters.
+    fn fold_identifier(&mut self, ident: ast::Identifier) -> ast::Identifier {
+        return ident;
+    }
+
+    fn fold_integer_literal(&mut self, il: ast::IntegerLiteral) -> ast::Expression {
+        return ast::Expression::IntegerLiteral(il);
+    }
+
+    fn fold_boolean(&mut self, b: ast::Boolean) -> ast::Expression {
+        return ast::Expression::Boolean(b);
+    }
+
+    fn fold_string_literal(&mut self, s: ast::StringLiteral) -> ast::Expression {
+        return ast::Expression::StringLiteral(s);
+    }
+
+    fn fold_prefix_expression(&mut self, pe: ast::PrefixExpression) -> ast::Expression {
+        return noop_fold_prefix_expression(self, pe);
+    }
+
+    fn fold_infix_expression(&mut self, ie: ast::InfixExpression) -> ast::Expression {
+        return noop_fold_infix_expression(self, ie);
+    }
+
+    fn fold_if_expression(&mut self, ife: ast::IfExpression) -> ast::Expression {
+        return noop_fold_if_expression(self, ife);
+    }
+
+    fn fold_function_literal(&mut self, fl: ast::FunctionLiteral) -> ast::Expression {
+        return noop_fold_function_literal(self, fl);
+    }
+
+    fn fold_call_expression(&mut self, ce: ast::CallExpression) -> ast::Expression {
+        return noop_fold_call_expression(self, ce);
+    }
+
+    fn fold_array_literal(&mut self, al: ast::ArrayLiteral) -> ast::Expression {
+        return noop_fold_array_literal(self, al);
+    }
+
+    fn fold_index_expression(&mut self, ie: ast::IndexExpression) -> ast::Expression {
+        return noop_fold_index_expression(self, ie);
+    }
+
+    fn fold_hash_literal(&mut self, hl: ast::HashLiteral) -> ast::Expression {
+        return noop_fold_hash_literal(self, hl);
+    }
+}
+
+pub fn noop_fold_program<F: Folder + ?Sized>(folder: &mut F, program: ast::Program) -> ast::Program {
+    return ast::Program{
+        statements: program.statements.into_iter().map(|stmt| folder.fold_statement(stmt)).collect(),
+    };
+}
+
+pub fn noop_fold_statement<F: Folder + ?Sized>(folder: &mut F, stmt: ast::Statement) -> ast::Statement {
+    return match stmt {
+        ast::Statement::Let(stmt) => folder.fold_let_statement(stmt),
+        ast::Statement::Return(stmt) => folder.fold_return_statement(stmt),
+        ast::Statement::Expression(stmt) => folder.fold_expression_statement(stmt),
+    };
+}
+
+pub fn noop_fold_let_statement<F: Folder + ?Sized>(folder: &mut F, stmt: ast::LetStatement) -> ast::Statement {
+    let name: ast::Identifier = folder.fold_identifier(stmt.name);
+    let value: ast::Expression = folder.fold_expression(stmt.value);
+    return ast::Statement::Let(ast::LetStatement::new(stmt.token, name, value));
+}
+
+pub fn noop_fold_return_statement<F: Folder + ?Sized>(folder: &mut F, stmt: ast::ReturnStatement) -> ast::Statement {
+    let return_value: ast::Expression = folder.fold_expression(stmt.return_value);
+    return ast::Statement::Return(ast::ReturnStatement::new(stmt.token, return_value));
+}
+
+pub fn noop_fold_expression_statement<F: Folder + ?Sized>(folder: &mut F, stmt: ast::ExpressionStatement) -> ast::Statement {
+    let expression: ast::Expression = folder.fold_expression(stmt.expression);
+    return ast::Statement::Expression(ast::ExpressionStatement::new(stmt.token, expression));
+}
+
+pub fn noop_fold_block_statement<F: Folder + ?Sized>(folder: &mut F, block: ast::BlockStatement) -> ast::BlockStatement {
+    return ast::BlockStatement{
+        token:          block.token,
+        statements:     block.statements.into_iter().map(|stmt| folder.fold_statement(stmt)).collect(),
+    };
+}
+
+pub fn noop_fold_expression<F: Folder + ?Sized>(folder: &mut F, exp: ast::Expression) -> ast::Expression {
+    return match exp {
+        ast::Expression::Identifier(ident) => ast::Expression::Identifier(folder.fold_identifier(ident)),
+        ast::Expression::IntegerLiteral(il) => folder.fold_integer_literal(il),
+        ast::Expression::Boolean(b) => folder.fold_boolean(b),
+        ast::Expression::StringLiteral(s) => folder.fold_string_literal(s),
+        ast::Expression::Prefix(pe) => folder.fold_prefix_expression(pe),
+        ast::Expression::Infix(ie) => folder.fold_infix_expression(ie),
+        ast::Expression::If(ife) => folder.fold_if_expression(ife),
+        ast::Expression::FunctionLiteral(fl) => folder.fold_function_literal(fl),
+        ast::Expression::Call(ce) => folder.fold_call_expression(ce),
+        ast::Expression::Array(al) => folder.fold_array_literal(al),
+        ast::Expression::Index(ie) => folder.fold_index_expression(ie),
+        ast::Expression::Hash(hl) => folder.fold_hash_literal(hl),
+    };
+}
+
+pub fn noop_fold_prefix_expression<F: Folder + ?Sized>(folder: &mut F, pe: ast::PrefixExpression) -> ast::Expression {
+    let right: ast::Expression = folder.fold_expression(*pe.right);
+    return ast::Expression::Prefix(ast::PrefixExpression::new(pe.token, pe.operator, right));
+}
+
+pub fn noop_fold_infix_expression<F: Folder + ?Sized>(folder: &mut F, ie: ast::InfixExpression) -> ast::Expression {
+    let left: ast::Expression = folder.fold_expression(*ie.left);
+    let right: ast::Expression = folder.fold_expression(*ie.right);
+    return ast::Expression::Infix(ast::InfixExpression::new(ie.token, left, ie.operator, right));
+}
+
+pub fn noop_fold_if_expression<F: Folder + ?Sized>(folder: &mut F, ife: ast::IfExpression) -> ast::Expression {
+    let condition: ast::Expression = folder.fold_expression(*ife.condition);
+    let consequence: ast::BlockStatement = folder.fold_block_statement(ife.consequence);
+    let alternative: Option<ast::BlockStatement> = ife.alternative.map(|alt| folder.fold_block_statement(alt));
+    return ast::Expression::If(ast::IfExpression::new(ife.token, condition, consequence, alternative));
+}
+
+pub fn noop_fold_function_literal<F: Folder + ?Sized>(folder: &mut F, fl: ast::FunctionLiteral) -> ast::Expression {
+    // the parameters and body are only shared once the literal has been evaluated
+    let parameters: Vec<ast::Identifier> = Rc::try_unwrap(fl.parameters).unwrap_or_else(|params| (*params).clone());
+    let body: ast::BlockStatement = Rc::try_unwrap(fl.body).unwrap_or_else(|body| (*body).clone());
+
+    let parameters: Vec<ast::Identifier> = parameters.into_iter().map(|param| folder.fold_identifier(param)).collect();
+    let body: ast::BlockStatement = folder.fold_block_statement(body);
+    return ast::Expression::FunctionLiteral(ast::FunctionLiteral::new(fl.token, parameters, body));
+}
+
+pub fn noop_fold_call_expression<F: Folder + ?Sized>(folder: &mut F, ce: ast::CallExpression) -> ast::Expression {
+    let function: ast::Expression = folder.fold_expression(*ce.function);
+    let arguments: Vec<ast::Expression> = ce.arguments.into_iter().map(|arg| folder.fold_expression(arg)).collect();
+    return ast::Expression::Call(ast::CallExpression::new(ce.token, function, arguments));
+}
+
+pub fn noop_fold_array_literal<F: Folder + ?Sized>(folder: &mut F, al: ast::ArrayLiteral) -> ast::Expression {
+    let elements: Vec<ast::Expression> = al.elements.into_iter().map(|e| folder.fold_expression(e)).collect();
+    return ast::Expression::Array(ast::ArrayLiteral::new(al.token, elements));
+}
+
+pub fn noop_fold_index_expression<F: Folder + ?Sized>(folder: &mut F, ie: ast::IndexExpression) -> ast::Expression {
+    let left: ast::Expression = folder.fold_expression(*ie.left);
+    let index: ast::Expression = folder.fold_expression(*ie.index);
+    return ast::Expression::Index(ast::IndexExpression::new(ie.token, left, index));
+}
+
+pub fn noop_fold_hash_literal<F: Folder + ?Sized>(folder: &mut F, hl: ast::HashLiteral) -> ast::Expression {
+    let pairs: Vec<(ast::Expression, ast::Expression)> = hl.pairs.into_iter()
+        .map(|(key, value)| (folder.fold_expression(key), folder.fold_expression(value)))
+        .collect();
+    return ast::Expression::Hash(ast::HashLiteral::new(hl.token, pairs));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ast::ast::Node;
+    use lexer::*;
+    use parser::*;
+    use token::*;
+
+    fn parse(input: &str) -> ast::Program {
+        let mut l = lexer::Lexer::new(input);
+        let mut p = parser::Parser::new(&mut l);
+        return p.parse_program().unwrap();
+    }
+
+    // Folds infix expressions over two integer literals into a single literal.
+    struct ConstantFolder;
+
+    impl Folder for ConstantFolder {
+        fn fold_infix_expression(&mut self, ie: ast::InfixExpression) -> ast::Expression {
+            let ie = match noop_fold_infix_expression(self, ie) {
+                ast::Expression::Infix(ie) => ie,
+                other => return other,
+            };
+
+            let value: Option<i64> = match (&*ie.left, &*ie.right) {
+                (ast::Expression::IntegerLiteral(l), ast::Expression::IntegerLiteral(r)) => match ie.operator.as_str() {
+                    "+" => l.value.checked_add(r.value),
+                    "-" => l.value.checked_sub(r.value),
+                    "*" => l.value.checked_mul(r.value),
+                    _ => None,
+                },
+                _ => None,
+            };
+
+            return match value {
+                Some(value) => {
+                    let tok = token::Token{ ttype: token::INT, literal: value.to_string(), span: ie.token.span };
+                    ast::Expression::IntegerLiteral(ast::IntegerLiteral::new(tok, value))
+                },
+                None => ast::Expression::Infix(ie),
+            };
+        }
+    }
+
+    struct Renamer {
+        from:   String,
+        to:     String,
+    }
+
+    impl Folder for Renamer {
+        fn fold_identifier(&mut self, mut ident: ast::Identifier) -> ast::Identifier {
+            if ident.value == self.from {
+                ident.value = self.to.clone();
+                ident.token.literal = self.to.clone();
+            }
+            return ident;
+        }
+    }
+
+    #[test]
+    fn test_noop_fold_is_identity() {
+        let program = parse("
+let add = fn(x, y) { x + y; };
+if (add(1, 2) > 2) { return !true; } else { -3 }");
+
+        struct Noop;
+        impl Folder for Noop {}
+
+        assert_eq!(Noop.fold_program(program.clone()), program);
+    }
+
+    #[test]
+    fn test_constant_folding() {
+        let tests: [(&str, &str); 4] = [
+            ("1 + 2 * 3",                       "7;\n"),
+            ("let x = (10 - 4) * y;",           "let x = (6 * y);\n"),
+            ("fn(a) { a + 2 * 2 }",             "fn(a) { (a + 4); };\n"),
+            ("if (x) { 1 + 1 } else { 5 / 5 }", "if (x) { 2; } else { (5 / 5); };\n"),
+        ];
+
+        for (i, &(input, expected)) in tests.iter().enumerate() {
+            let folded = ConstantFolder.fold_program(parse(input));
+            assert_eq!(folded.to_string(), expected, "tests[{}]", i);
+        }
+    }
+
+    #[test]
+    fn test_rename_identifiers() {
+        let program = parse("let x = 1; let f = fn(x) { x * 2 }; f(x);");
+
+        let mut renamer = Renamer{ from: String::from("x"), to: String::from("n") };
+        let renamed = renamer.fold_program(program);
+
+        assert_eq!(renamed.to_string(), "let n = 1;\nlet f = fn(n) { (n * 2); };\nf(n);\n");
+    }
+}
@@ -1,2 +1,8 @@
 pub mod ast;
 
+pub mod fold;
+
+pub mod visit;
+
+pub use self::fold::Folder;
+pub use self::visit::Visitor;
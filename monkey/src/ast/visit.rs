@@ -0,0 +1,242 @@
+use ast::*;
+
+// Read-only traversal of the AST. Every method defaults to the matching walk_*
+// function, so an implementation only overrides the nodes it cares about and
+// calls walk_* itself if it still wants the children visited.
+pub trait Visitor {
+    fn visit_program(&mut self, program: &ast::Program) {
+        walk_program(self, program);
+    }
+
+    fn visit_statement(&mut self, stmt: &ast::Statement) {
+        walk_statement(self, stmt);
+    }
+
+    fn visit_let_statement(&mut self, stmt: &ast::LetStatement) {
+        walk_let_statement(self, stmt);
+    }
+
+    fn visit_return_statement(&mut self, stmt: &ast::ReturnStatement) {
+        walk_return_statement(self, stmt);
+    }
+
+    fn visit_expression_statement(&mut self, stmt: &ast::ExpressionStatement) {
+        walk_expression_statement(self, stmt);
+    }
+
+    fn visit_block_statement(&mut self, block: &ast::BlockStatement) {
+        walk_block_statement(self, block);
+    }
+
+    fn visit_expression(&mut self, exp: &ast::Expression) {
+        walk_expression(self, exp);
+    }
+
+    // Called for identifiers in expression position as well as for the names
+    // bound by let statements and function parameters.
+    fn visit_identifier(&mut self, _ident: &ast::Identifier) {}
+
+    fn visit_integer_literal(&mut self, _il: &ast::IntegerLiteral) {}
+
+    fn visit_boolean(&mut self, _b: &ast::Boolean) {}
+
+    fn visit_string_literal(&mut self, _s: &ast::StringLiteral) {}
+
+    fn visit_prefix_expression(&mut self, pe: &ast::PrefixExpression) {
+        walk_prefix_expression(self, pe);
+    }
+
+    fn visit_infix_expression(&mut self, ie: &ast::InfixExpression) {
+        walk_infix_expression(self, ie);
+    }
+
+    fn visit_if_expression(&mut self, ife: &ast::IfExpression) {
+        walk_if_expression(self, ife);
+    }
+
+    fn visit_function_literal(&mut self, fl: &ast::FunctionLiteral) {
+        walk_function_literal(self, fl);
+    }
+
+    fn visit_call_expression(&mut self, ce: &ast::CallExpression) {
+        walk_call_expression(self, ce);
+    }
+
+    fn visit_array_literal(&mut self, al: &ast::ArrayLiteral) {
+        walk_array_literal(self, al);
+    }
+
+    fn visit_index_expression(&mut self, ie: &ast::IndexExpression) {
+        walk_index_expression(self, ie);
+    }
+
+    fn visit_hash_literal(&mut self, hl: &ast::HashLiteral) {
+        walk_hash_literal(self, hl);
+    }
+}
+
+pub fn walk_program<V: Visitor + ?Sized>(visitor: &mut V, program: &ast::Program) {
+    for stmt in program.statements.iter() {
+        visitor.visit_statement(stmt);
+    }
+}
+
+pub fn walk_statement<V: Visitor + ?Sized>(visitor: &mut V, stmt: &ast::Statement) {
+    match *stmt {
+        ast::Statement::Let(ref stmt) => visitor.visit_let_statement(stmt),
+        ast::Statement::Return(ref stmt) => visitor.visit_return_statement(stmt),
+        ast::Statement::Expression(ref stmt) => visitor.visit_expression_statement(stmt),
+    }
+}
+
+pub fn walk_let_statement<V: Visitor + ?Sized>(visitor: &mut V, stmt: &ast::LetStatement) {
+    visitor.visit_identifier(&stmt.name);
+    visitor.visit_expression(&stmt.value);
+}
+
+pub fn walk_return_statement<V: Visitor + ?Sized>(visitor: &mut V, stmt: &ast::ReturnStatement) {
+    visitor.visit_expression(&stmt.return_value);
+}
+
+pub fn walk_expression_statement<V: Visitor + ?Sized>(visitor: &mut V, stmt: &ast::ExpressionStatement) {
+    visitor.visit_expression(&stmt.expression);
+}
+
+pub fn walk_block_statement<V: Visitor + ?Sized>(visitor: &mut V, block: &ast::BlockStatement) {
+    for stmt in block.statements.iter() {
+        visitor.visit_statement(stmt);
+    }
+}
+
+pub fn walk_expression<V: Visitor + ?Sized>(visitor: &mut V, exp: &ast::Expression) {
+    match *exp {
+        ast::Expression::Identifier(ref ident) => visitor.visit_identifier(ident),
+        ast::Expression::IntegerLiteral(ref il) => visitor.visit_integer_literal(il),
+        ast::Expression::Boolean(ref b) => visitor.visit_boolean(b),
+        ast::Expression::StringLiteral(ref s) => visitor.visit_string_literal(s),
+        ast::Expression::Prefix(ref pe) => visitor.visit_prefix_expression(pe),
+        ast::Expression::Infix(ref ie) => visitor.visit_infix_expression(ie),
+        ast::Expression::If(ref ife) => visitor.visit_if_expression(ife),
+        ast::Expression::FunctionLiteral(ref fl) => visitor.visit_function_literal(fl),
+        ast::Expression::Call(ref ce) => visitor.visit_call_expression(ce),
+        ast::Expression::Array(ref al) => visitor.visit_array_literal(al),
+        ast::Expression::Index(ref ie) => visitor.visit_index_expression(ie),
+        ast::Expression::Hash(ref hl) => visitor.visit_hash_literal(hl),
+    }
+}
+
+pub fn walk_prefix_expression<V: Visitor + ?Sized>(visitor: &mut V, pe: &ast::PrefixExpression) {
+    visitor.visit_expression(&pe.right);
+}
+
+pub fn walk_infix_expression<V: Visitor + ?Sized>(visitor: &mut V, ie: &ast::InfixExpression) {
+    visitor.visit_expression(&ie.left);
+    visitor.visit_expression(&ie.right);
+}
+
+pub fn walk_if_expression<V: Visitor + ?Sized>(visitor: &mut V, ife: &ast::IfExpression) {
+    visitor.visit_expression(&ife.condition);
+    visitor.visit_block_statement(&ife.consequence);
+    match ife.alternative.as_ref() {
+        Some(alt) => visitor.visit_block_statement(alt),
+        None => (),
+    }
+}
+
+pub fn walk_function_literal<V: Visitor + ?Sized>(visitor: &mut V, fl: &ast::FunctionLiteral) {
+    for param in fl.parameters.iter() {
+        visitor.visit_identifier(param);
+    }
+    visitor.visit_block_statement(&fl.body);
+}
+
+pub fn walk_call_expression<V: Visitor + ?Sized>(visitor: &mut V, ce: &ast::CallExpression) {
+    visitor.visit_expression(&ce.function);
+    for arg in ce.arguments.iter() {
+        visitor.visit_expression(arg);
+    }
+}
+
+pub fn walk_array_literal<V: Visitor + ?Sized>(visitor: &mut V, al: &ast::ArrayLiteral) {
+    for element in al.elements.iter() {
+        visitor.visit_expression(element);
+    }
+}
+
+pub fn walk_index_expression<V: Visitor + ?Sized>(visitor: &mut V, ie: &ast::IndexExpression) {
+    visitor.visit_expression(&ie.left);
+    visitor.visit_expression(&ie.index);
+}
+
+pub fn walk_hash_literal<V: Visitor + ?Sized>(visitor: &mut V, hl: &ast::HashLiteral) {
+    for (key, value) in hl.pairs.iter() {
+        visitor.visit_expression(key);
+        visitor.visit_expression(value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lexer::*;
+    use parser::*;
+
+    fn parse(input: &str) -> ast::Program {
+        let mut l = lexer::Lexer::new(input);
+        let mut p = parser::Parser::new(&mut l);
+        return p.parse_program().unwrap();
+    }
+
+    struct IdentifierCollector {
+        names:      Vec<String>,
+    }
+
+    impl Visitor for IdentifierCollector {
+        fn visit_identifier(&mut self, ident: &ast::Identifier) {
+            self.names.push(ident.value.clone());
+        }
+    }
+
+    struct CallCounter {
+        calls:      usize,
+        literals:   i64,    // sum of the integer literals outside of function bodies
+    }
+
+    impl Visitor for CallCounter {
+        fn visit_call_expression(&mut self, ce: &ast::CallExpression) {
+            self.calls += 1;
+            walk_call_expression(self, ce);
+        }
+
+        fn visit_integer_literal(&mut self, il: &ast::IntegerLiteral) {
+            self.literals += il.value;
+        }
+
+        fn visit_function_literal(&mut self, _fl: &ast::FunctionLiteral) {}
+    }
+
+    #[test]
+    fn test_visit_identifiers() {
+        let program = parse("
+let add = fn(x, y) { x + y; };
+if (a < b) { add(a, b) } else { -c }");
+
+        let mut collector = IdentifierCollector{ names: Vec::new() };
+        collector.visit_program(&program);
+
+        assert_eq!(collector.names, vec!["add", "x", "y", "x", "y", "a", "b", "add", "a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_overridden_visit_skips_children() {
+        let program = parse("
+let f = fn(x) { g(x, 100) };
+f(f(1) + 2, 3);");
+
+        let mut counter = CallCounter{ calls: 0, literals: 0 };
+        counter.visit_program(&program);
+
+        assert_eq!(counter.calls, 2);
+        assert_eq!(counter.literals, 6);
+    }
+}
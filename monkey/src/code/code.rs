@@ -0,0 +1,252 @@
+use ast::*;
+
+pub use self::Opcode::*;
+
+pub type Instructions = Vec<u8>;
+
+// Maps the instructions starting at offset back to the node they were compiled
+// from and its source position. An entry holds until the next one begins.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SourcePosition {
+    pub offset:     usize,      // offset of the first instruction in the instruction stream
+    pub line:       usize,
+    pub column:     usize,
+    pub node:       ast::NodeKind,
+}
+
+pub type LineTable = Vec<SourcePosition>;
+
+// Finds the source position of the instruction at offset.
+pub fn lookup_position(lines: &[SourcePosition], offset: usize) -> Option<SourcePosition> {
+    return lines.iter().rev().find(|pos| pos.offset <= offset).cloned();
+}
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Opcode {
+    OpConstant,         // push constants[operand]
+    OpPop,              // discard the top of the stack
+
+    // Arithmetic
+    OpAdd,
+    OpSub,
+    OpMul,
+    OpDiv,
+    OpMod,
+
+    // Literals
+    OpTrue,
+    OpFalse,
+    OpNull,
+
+    // Comparison
+    OpEqual,
+    OpNotEqual,
+    OpLessThan,
+    OpLessEqual,
+    OpGreaterThan,
+    OpGreaterEqual,
+
+    // Prefix operators
+    OpMinus,
+    OpBang,
+
+    // Control flow
+    OpJumpNotTruthy,    // pop the condition and jump to the operand if it is not truthy
+    OpJump,             // jump to the operand unconditionally
+
+    // Bindings
+    OpGetGlobal,
+    OpSetGlobal,
+    OpGetLocal,
+    OpSetLocal,
+    OpGetBuiltin,
+    OpGetFree,          // push the captured variable operand of the running closure
+
+    // Collections
+    OpArray,            // build an array from the top operand elements
+    OpHash,             // build a hash from the top operand elements, keys and values alternating
+    OpIndex,
+
+    // Functions
+    OpCall,             // call the function below the top operand arguments
+    OpReturnValue,      // return the top of the stack from the current function
+    OpReturn,           // return null from the current function
+    OpClosure,          // wrap the function constant operand with the top operand values as its free variables
+    OpCurrentClosure,   // push the running closure, for functions that call themselves
+}
+
+// Every opcode in byte order, so that a byte can be mapped back to its opcode.
+static OPCODES: &[Opcode] = &[
+    OpConstant, OpPop,
+    OpAdd, OpSub, OpMul, OpDiv, OpMod,
+    OpTrue, OpFalse, OpNull,
+    OpEqual, OpNotEqual, OpLessThan, OpLessEqual, OpGreaterThan, OpGreaterEqual,
+    OpMinus, OpBang,
+    OpJumpNotTruthy, OpJump,
+    OpGetGlobal, OpSetGlobal, OpGetLocal, OpSetLocal, OpGetBuiltin, OpGetFree,
+    OpArray, OpHash, OpIndex,
+    OpCall, OpReturnValue, OpReturn, OpClosure, OpCurrentClosure,
+];
+
+pub struct Definition {
+    pub name:           &'static str,
+    pub operand_widths: &'static [usize],  // width of each operand in bytes
+}
+
+impl Opcode {
+    pub fn from_byte(byte: u8) -> Option<Opcode> {
+        return OPCODES.get(byte as usize).cloned();
+    }
+
+    pub fn definition(&self) -> Definition {
+        let (name, operand_widths): (&'static str, &'static [usize]) = match *self {
+            OpConstant      => ("OpConstant",       &[2]),
+            OpPop           => ("OpPop",            &[]),
+            OpAdd           => ("OpAdd",            &[]),
+            OpSub           => ("OpSub",            &[]),
+            OpMul           => ("OpMul",            &[]),
+            OpDiv           => ("OpDiv",            &[]),
+            OpMod           => ("OpMod",            &[]),
+            OpTrue          => ("OpTrue",           &[]),
+            OpFalse         => ("OpFalse",          &[]),
+            OpNull          => ("OpNull",           &[]),
+            OpEqual         => ("OpEqual",          &[]),
+            OpNotEqual      => ("OpNotEqual",       &[]),
+            OpLessThan      => ("OpLessThan",       &[]),
+            OpLessEqual     => ("OpLessEqual",      &[]),
+            OpGreaterThan   => ("OpGreaterThan",    &[]),
+            OpGreaterEqual  => ("OpGreaterEqual",   &[]),
+            OpMinus         => ("OpMinus",          &[]),
+            OpBang          => ("OpBang",           &[]),
+            OpJumpNotTruthy => ("OpJumpNotTruthy",  &[2]),
+            OpJump          => ("OpJump",           &[2]),
+            OpGetGlobal     => ("OpGetGlobal",      &[2]),
+            OpSetGlobal     => ("OpSetGlobal",      &[2]),
+            OpGetLocal      => ("OpGetLocal",       &[1]),
+            OpSetLocal      => ("OpSetLocal",       &[1]),
+            OpGetBuiltin    => ("OpGetBuiltin",     &[1]),
+            OpGetFree       => ("OpGetFree",        &[1]),
+            OpArray         => ("OpArray",          &[2]),
+            OpHash          => ("OpHash",           &[2]),
+            OpIndex         => ("OpIndex",          &[]),
+            OpCall          => ("OpCall",           &[1]),
+            OpReturnValue   => ("OpReturnValue",    &[]),
+            OpReturn        => ("OpReturn",         &[]),
+            OpClosure       => ("OpClosure",        &[2, 1]),
+            OpCurrentClosure => ("OpCurrentClosure", &[]),
+        };
+        return Definition{ name: name, operand_widths: operand_widths };
+    }
+}
+
+// Encodes a single instruction. Operands are stored big-endian in the widths
+// given by the opcode's definition.
+pub fn make(op: Opcode, operands: &[usize]) -> Instructions {
+    let def: Definition = op.definition();
+
+    let mut instruction: Instructions = Vec::with_capacity(1 + def.operand_widths.iter().sum::<usize>());
+    instruction.push(op as u8);
+
+    for (operand, width) in operands.iter().zip(def.operand_widths.iter()) {
+        match *width {
+            2 => {
+                instruction.push((*operand >> 8) as u8);
+                instruction.push(*operand as u8);
+            },
+            1 => instruction.push(*operand as u8),
+            _ => (),
+        }
+    }
+
+    return instruction;
+}
+
+// Decodes the operands that follow an opcode. Returns the operands and the
+// number of bytes they occupied.
+pub fn read_operands(def: &Definition, ins: &[u8]) -> (Vec<usize>, usize) {
+    let mut operands: Vec<usize> = Vec::with_capacity(def.operand_widths.len());
+    let mut offset: usize = 0;
+
+    for width in def.operand_widths.iter() {
+        match *width {
+            2 => operands.push(read_u16(&ins[offset..]) as usize),
+            1 => operands.push(read_u8(&ins[offset..]) as usize),
+            _ => (),
+        }
+        offset += width;
+    }
+
+    return (operands, offset);
+}
+
+pub fn read_u16(ins: &[u8]) -> u16 {
+    return (ins[0] as u16) << 8 | ins[1] as u16;
+}
+
+pub fn read_u8(ins: &[u8]) -> u8 {
+    return ins[0];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_make() {
+        let tests: [(Opcode, &[usize], &[u8]); 5] = [
+            (OpConstant,    &[65534],   &[OpConstant as u8, 255, 254]),
+            (OpClosure,     &[65534, 255], &[OpClosure as u8, 255, 254, 255]),
+            (OpAdd,         &[],        &[OpAdd as u8]),
+            (OpGetLocal,    &[255],     &[OpGetLocal as u8, 255]),
+            (OpCall,        &[3],       &[OpCall as u8, 3]),
+        ];
+
+        for (i, &(op, operands, expected)) in tests.iter().enumerate() {
+            assert_eq!(make(op, operands), expected, "tests[{}]", i);
+        }
+    }
+
+    #[test]
+    fn test_lookup_position() {
+        let lines: LineTable = vec![
+            SourcePosition{ offset: 0, line: 1, column: 1, node: ast::NodeKind::IntegerLiteral },
+            SourcePosition{ offset: 3, line: 1, column: 5, node: ast::NodeKind::IntegerLiteral },
+            SourcePosition{ offset: 7, line: 2, column: 1, node: ast::NodeKind::InfixExpression },
+        ];
+
+        assert_eq!(lookup_position(&lines, 0), Some(lines[0]));
+        assert_eq!(lookup_position(&lines, 4), Some(lines[1]));
+        assert_eq!(lookup_position(&lines, 100), Some(lines[2]));
+        assert_eq!(lookup_position(&[], 0), None);
+    }
+
+    #[test]
+    fn test_read_operands() {
+        let tests: [(Opcode, &[usize], usize); 4] = [
+            (OpConstant,    &[65535],   2),
+            (OpClosure,     &[65535, 255], 3),
+            (OpGetLocal,    &[255],     1),
+            (OpPop,         &[],        0),
+        ];
+
+        for (i, &(op, operands, bytes_read)) in tests.iter().enumerate() {
+            let instruction: Instructions = make(op, operands);
+            let def: Definition = op.definition();
+
+            let (operands_read, n) = read_operands(&def, &instruction[1..]);
+            assert_eq!(n, bytes_read, "tests[{}]", i);
+            assert_eq!(operands_read, operands, "tests[{}]", i);
+        }
+    }
+
+    #[test]
+    fn test_from_byte() {
+        for (i, op) in OPCODES.iter().enumerate() {
+            assert_eq!(*op as usize, i, "opcode table out of order at {}", i);
+            assert_eq!(Opcode::from_byte(i as u8), Some(*op));
+        }
+        assert_eq!(Opcode::from_byte(OPCODES.len() as u8), None);
+    }
+}
@@ -0,0 +1,188 @@
+use code::*;
+use compiler::*;
+use object::*;
+
+// Renders bytecode as a listing of the constant pool, the main instructions and
+// the instructions of every compiled function, in that order. Each instruction
+// shows its offset, mnemonic and operands, followed by the source position and
+// kind of the node it was compiled from when the bytecode has line tables. With
+// the source text, every run of instructions from one line is preceded by that
+// line. The layout is stable, so listings can be compared verbatim in tests.
+pub fn disassemble(bytecode: &compiler::Bytecode, source: Option<&str>) -> String {
+    let mut listing: String = String::new();
+
+    listing.push_str("== constants ==\n");
+    for (i, constant) in bytecode.constants.iter().enumerate() {
+        listing.push_str(&format!("{:04} {}\n", i, describe_constant(constant)));
+    }
+
+    listing.push_str("== main ==\n");
+    listing.push_str(&disassemble_instructions(&bytecode.instructions, &bytecode.lines, source));
+
+    for (i, constant) in bytecode.constants.iter().enumerate() {
+        match *constant {
+            object::Object::CompiledFunction(ref func) => {
+                listing.push_str(&format!("== constant {:04} ==\n", i));
+                listing.push_str(&disassemble_instructions(&func.instructions, &func.lines, source));
+            },
+            _ => (),
+        }
+    }
+
+    return listing;
+}
+
+// Renders one instruction stream, one instruction per line. Bytes that do not
+// decode are listed as such instead of being skipped.
+pub fn disassemble_instructions(ins: &[u8], lines: &[code::SourcePosition], source: Option<&str>) -> String {
+    let source_lines: Vec<&str> = match source {
+        Some(source) => source.lines().collect(),
+        None => Vec::new(),
+    };
+
+    let mut listing: String = String::new();
+    let mut last_line: usize = 0;
+
+    let mut ip: usize = 0;
+    while ip < ins.len() {
+        let op: code::Opcode = match code::Opcode::from_byte(ins[ip]) {
+            Some(op) => op,
+            None => {
+                listing.push_str(&format!("{:04} <invalid opcode {}>\n", ip, ins[ip]));
+                ip += 1;
+                continue;
+            },
+        };
+        let def: code::Definition = op.definition();
+        let width: usize = def.operand_widths.iter().sum();
+        if ip + 1 + width > ins.len() {
+            listing.push_str(&format!("{:04} <truncated {}>\n", ip, def.name));
+            break;
+        }
+        let (operands, _) = code::read_operands(&def, &ins[ip + 1..]);
+
+        match code::lookup_position(lines, ip) {
+            Some(pos) => {
+                if pos.line != last_line && pos.line >= 1 && pos.line <= source_lines.len() {
+                    listing.push_str(&format!("     ; {} | {}\n", pos.line, source_lines[pos.line - 1].trim_end()));
+                }
+                last_line = pos.line;
+
+                let location: String = format!("{}:{}", pos.line, pos.column);
+                listing.push_str(&format!("{:04} {:<20} {:<7} {}\n", ip, format_instruction(&def, &operands), location, pos.node.as_str()));
+            },
+            None => listing.push_str(&format!("{:04} {}\n", ip, format_instruction(&def, &operands))),
+        }
+
+        ip += 1 + width;
+    }
+
+    return listing;
+}
+
+// Renders an instruction as its mnemonic followed by its operands.
+pub fn format_instruction(def: &code::Definition, operands: &[usize]) -> String {
+    let mut text: String = String::from(def.name);
+    for operand in operands.iter() {
+        text.push(' ');
+        text.push_str(&operand.to_string());
+    }
+    return text;
+}
+
+fn describe_constant(constant: &object::Object) -> String {
+    return match *constant {
+        object::Object::String(ref value) => format!("{} {:?}", constant.object_type(), value),
+        object::Object::CompiledFunction(ref func) =>
+            format!("{} parameters={} locals={}", constant.object_type(), func.num_parameters, func.num_locals),
+        _ => format!("{} {}", constant.object_type(), constant.inspect()),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ast::*;
+    use lexer::*;
+    use parser::*;
+
+    fn compile(input: &str) -> compiler::Bytecode {
+        let mut l = lexer::Lexer::new(input);
+        let mut p = parser::Parser::new(&mut l);
+        let program: ast::Program = p.parse_program().unwrap();
+
+        let mut c = compiler::Compiler::new();
+        c.compile_program(&program).unwrap();
+        return c.bytecode();
+    }
+
+    static PROGRAM: &str = "let double = fn(x) { x * 2 };\nif (double(2) > 3) { \"big\" }\n";
+
+    #[test]
+    fn test_disassemble() {
+        let expected: &str = "\
+== constants ==
+0000 INTEGER 2
+0001 COMPILED_FUNCTION parameters=1 locals=1
+0002 INTEGER 2
+0003 INTEGER 3
+0004 STRING \"big\"
+== main ==
+0000 OpClosure 1 0        1:14    FunctionLiteral
+0004 OpSetGlobal 0        1:1     LetStatement
+0007 OpGetGlobal 0        2:5     Identifier
+0010 OpConstant 2         2:12    IntegerLiteral
+0013 OpCall 1             2:11    CallExpression
+0015 OpConstant 3         2:17    IntegerLiteral
+0018 OpGreaterThan        2:15    InfixExpression
+0019 OpJumpNotTruthy 28   2:1     IfExpression
+0022 OpConstant 4         2:22    StringLiteral
+0025 OpJump 29            2:1     IfExpression
+0028 OpNull               2:1     IfExpression
+0029 OpPop                2:1     ExpressionStatement
+== constant 0001 ==
+0000 OpGetLocal 0         1:22    Identifier
+0002 OpConstant 0         1:26    IntegerLiteral
+0005 OpMul                1:24    InfixExpression
+0006 OpReturnValue        1:22    ExpressionStatement
+";
+        assert_eq!(disassemble(&compile(PROGRAM), None), expected);
+    }
+
+    #[test]
+    fn test_disassemble_with_source() {
+        let expected: &str = "\
+== constants ==
+0000 INTEGER 1
+== main ==
+     ; 1 | let a = 1;
+0000 OpConstant 0         1:9     IntegerLiteral
+0003 OpSetGlobal 0        1:1     LetStatement
+     ; 3 | -a
+0006 OpGetGlobal 0        3:2     Identifier
+0009 OpMinus              3:1     PrefixExpression
+0010 OpPop                3:1     ExpressionStatement
+";
+        let source: &str = "let a = 1;\n\n-a   \n";
+        assert_eq!(disassemble(&compile(source), Some(source)), expected);
+    }
+
+    #[test]
+    fn test_disassemble_instructions() {
+        let mut ins: code::Instructions = code::make(code::OpConstant, &[65535]);
+        ins.extend(code::make(code::OpGetLocal, &[1]));
+        ins.push(255);
+        ins.extend(code::make(code::OpCall, &[2]));
+        ins.extend(code::make(code::OpJump, &[1]));
+        ins.pop();
+
+        let expected: &str = "\
+0000 OpConstant 65535
+0003 OpGetLocal 1
+0005 <invalid opcode 255>
+0006 OpCall 2
+0008 <truncated OpJump>
+";
+        assert_eq!(disassemble_instructions(&ins, &[], None), expected);
+    }
+}
@@ -0,0 +1,3 @@
+pub mod code;
+
+pub mod disassembler;
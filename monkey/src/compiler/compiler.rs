@@ -0,0 +1,949 @@
+use std::mem;
+use std::rc::Rc;
+use ast::*;
+use ast::ast::Node;
+use code::*;
+use compiler::error::*;
+use compiler::symbol_table::*;
+use object::*;
+use token::*;
+
+// The instruction stream of the function currently being compiled, along with
+// the last two instructions emitted into it so they can be inspected or removed.
+struct CompilationScope {
+    instructions:           code::Instructions,
+    lines:                  code::LineTable,
+    last_instruction:       Option<EmittedInstruction>,
+    previous_instruction:   Option<EmittedInstruction>,
+}
+
+#[derive(Clone, Copy)]
+struct EmittedInstruction {
+    opcode:     code::Opcode,
+    position:   usize,
+}
+
+impl CompilationScope {
+    fn new() -> CompilationScope {
+        return CompilationScope{
+            instructions:           Vec::new(),
+            lines:                  Vec::new(),
+            last_instruction:       None,
+            previous_instruction:   None,
+        };
+    }
+}
+
+pub struct Bytecode {
+    pub instructions:   code::Instructions,
+    pub constants:      Vec<object::Object>,
+    pub lines:          code::LineTable,        // source positions of the top-level instructions
+}
+
+pub struct Compiler {
+    constants:          Vec<object::Object>,
+    symbol_table:       SymbolTable,
+    scopes:             Vec<CompilationScope>,
+    node:               Option<(ast::NodeKind, token::Span)>,  // the node being compiled
+}
+
+impl Compiler {
+    pub fn new() -> Compiler {
+        let mut symbol_table: SymbolTable = SymbolTable::new();
+        for (i, builtin) in builtins::BUILTINS.iter().enumerate() {
+            symbol_table.define_builtin(i, builtin.name);
+        }
+
+        return Compiler{
+            constants:          Vec::new(),
+            symbol_table:       symbol_table,
+            scopes:             vec![CompilationScope::new()],
+            node:               None,
+        };
+    }
+
+    pub fn compile_program(&mut self, program: &ast::Program) -> Result<(), CompileError> {
+        for stmt in program.statements.iter() {
+            self.compile_statement(stmt)?;
+        }
+        return Ok(());
+    }
+
+    pub fn bytecode(self) -> Bytecode {
+        let mut scopes: Vec<CompilationScope> = self.scopes;
+        return Bytecode{
+            instructions:   mem::take(&mut scopes[0].instructions),
+            constants:      self.constants,
+            lines:          mem::take(&mut scopes[0].lines),
+        };
+    }
+
+    // Instructions emitted for a node are attributed to the node's token; once
+    // the node is done, the enclosing node's position applies again.
+    fn compile_statement(&mut self, stmt: &ast::Statement) -> Result<(), CompileError> {
+        let outer: Option<(ast::NodeKind, token::Span)> = self.node.replace((stmt.kind(), stmt.span()));
+        let result: Result<(), CompileError> = self.compile_statement_node(stmt);
+        self.node = outer;
+        return result;
+    }
+
+    fn compile_statement_node(&mut self, stmt: &ast::Statement) -> Result<(), CompileError> {
+        match *stmt {
+            ast::Statement::Expression(ref stmt) => {
+                self.compile_expression(&stmt.expression)?;
+                self.emit(code::OpPop, &[])?;
+            },
+            ast::Statement::Let(ref stmt) => {
+                // a function literal is defined before it is compiled so that it can call
+                // itself; any other value still sees the binding the name shadows
+                let symbol: Symbol = match stmt.value {
+                    ast::Expression::FunctionLiteral(_) => {
+                        let symbol: Symbol = self.symbol_table.define(&stmt.name.value);
+                        self.compile_bound_expression(&stmt.value, Some(&stmt.name.value))?;
+                        symbol
+                    },
+                    _ => {
+                        self.compile_bound_expression(&stmt.value, Some(&stmt.name.value))?;
+                        self.symbol_table.define(&stmt.name.value)
+                    },
+                };
+                match symbol.scope {
+                    SymbolScope::GLOBAL => self.emit(code::OpSetGlobal, &[symbol.index])?,
+                    _ => self.emit(code::OpSetLocal, &[symbol.index])?,
+                };
+            },
+            ast::Statement::Return(ref stmt) => {
+                self.compile_expression(&stmt.return_value)?;
+                self.emit(code::OpReturnValue, &[])?;
+            },
+        }
+        return Ok(());
+    }
+
+    fn compile_block_statement(&mut self, block: &ast::BlockStatement) -> Result<(), CompileError> {
+        for stmt in block.statements.iter() {
+            self.compile_statement(stmt)?;
+        }
+        return Ok(());
+    }
+
+    fn compile_expression(&mut self, exp: &ast::Expression) -> Result<(), CompileError> {
+        return self.compile_bound_expression(exp, None);
+    }
+
+    // Compiles the value of a let statement, whose name a function literal
+    // binds in its own scope so that it can call itself.
+    fn compile_bound_expression(&mut self, exp: &ast::Expression, name: Option<&str>) -> Result<(), CompileError> {
+        let outer: Option<(ast::NodeKind, token::Span)> = self.node.replace((exp.kind(), exp.span()));
+        let result: Result<(), CompileError> = self.compile_expression_node(exp, name);
+        self.node = outer;
+        return result;
+    }
+
+    fn compile_expression_node(&mut self, exp: &ast::Expression, name: Option<&str>) -> Result<(), CompileError> {
+        match *exp {
+            ast::Expression::IntegerLiteral(ref il) => {
+                let index: usize = self.add_constant(object::Object::Integer(il.value));
+                self.emit(code::OpConstant, &[index])?;
+            },
+            ast::Expression::StringLiteral(ref sl) => {
+                let index: usize = self.add_constant(object::Object::String(sl.value.clone()));
+                self.emit(code::OpConstant, &[index])?;
+            },
+            ast::Expression::Boolean(ref b) => {
+                if b.value {
+                    self.emit(code::OpTrue, &[])?;
+                } else {
+                    self.emit(code::OpFalse, &[])?;
+                }
+            },
+            ast::Expression::Identifier(ref ident) => self.compile_identifier(ident)?,
+            ast::Expression::Prefix(ref pe) => {
+                self.compile_expression(&pe.right)?;
+                match pe.operator.as_str() {
+                    "!" => self.emit(code::OpBang, &[])?,
+                    "-" => self.emit(code::OpMinus, &[])?,
+                    _ => return Err(CompileError::UnknownOperator{ operator: pe.operator.clone(), span: pe.token.span }),
+                };
+            },
+            ast::Expression::Infix(ref ie) => self.compile_infix_expression(ie)?,
+            ast::Expression::If(ref ife) => self.compile_if_expression(ife)?,
+            ast::Expression::FunctionLiteral(ref fl) => self.compile_function_literal(fl, name)?,
+            ast::Expression::Call(ref ce) => {
+                self.compile_expression(&ce.function)?;
+                for arg in ce.arguments.iter() {
+                    self.compile_expression(arg)?;
+                }
+                self.emit(code::OpCall, &[ce.arguments.len()])?;
+            },
+            ast::Expression::Array(ref al) => {
+                for element in al.elements.iter() {
+                    self.compile_expression(element)?;
+                }
+                self.emit(code::OpArray, &[al.elements.len()])?;
+            },
+            ast::Expression::Hash(ref hl) => {
+                for (key, value) in hl.pairs.iter() {
+                    self.compile_expression(key)?;
+                    self.compile_expression(value)?;
+                }
+                self.emit(code::OpHash, &[hl.pairs.len() * 2])?;
+            },
+            ast::Expression::Index(ref ie) => {
+                self.compile_expression(&ie.left)?;
+                self.compile_expression(&ie.index)?;
+                self.emit(code::OpIndex, &[])?;
+            },
+        }
+        return Ok(());
+    }
+
+    fn compile_identifier(&mut self, ident: &ast::Identifier) -> Result<(), CompileError> {
+        let symbol: Symbol = match self.symbol_table.resolve(&ident.value) {
+            Some(symbol) => symbol,
+            None => return Err(CompileError::UndefinedVariable{ name: ident.value.clone(), span: ident.token.span }),
+        };
+
+        self.load_symbol(&symbol)?;
+        return Ok(());
+    }
+
+    fn load_symbol(&mut self, symbol: &Symbol) -> Result<(), CompileError> {
+        match symbol.scope {
+            SymbolScope::GLOBAL => self.emit(code::OpGetGlobal, &[symbol.index])?,
+            SymbolScope::LOCAL => self.emit(code::OpGetLocal, &[symbol.index])?,
+            SymbolScope::BUILTIN => self.emit(code::OpGetBuiltin, &[symbol.index])?,
+            SymbolScope::FREE => self.emit(code::OpGetFree, &[symbol.index])?,
+            SymbolScope::FUNCTION => self.emit(code::OpCurrentClosure, &[])?,
+        };
+        return Ok(());
+    }
+
+    fn compile_infix_expression(&mut self, ie: &ast::InfixExpression) -> Result<(), CompileError> {
+        if ie.operator == "&&" || ie.operator == "||" {
+            return self.compile_logical_expression(ie);
+        }
+
+        self.compile_expression(&ie.left)?;
+        self.compile_expression(&ie.right)?;
+
+        let op: code::Opcode = match ie.operator.as_str() {
+            "+" => code::OpAdd,
+            "-" => code::OpSub,
+            "*" => code::OpMul,
+            "/" => code::OpDiv,
+            "%" => code::OpMod,
+            "==" => code::OpEqual,
+            "!=" => code::OpNotEqual,
+            "<" => code::OpLessThan,
+            "<=" => code::OpLessEqual,
+            ">" => code::OpGreaterThan,
+            ">=" => code::OpGreaterEqual,
+            _ => return Err(CompileError::UnknownOperator{ operator: ie.operator.clone(), span: ie.token.span }),
+        };
+        self.emit(op, &[])?;
+        return Ok(());
+    }
+
+    // && and || leave a boolean on the stack and only evaluate their right
+    // operand when the left one does not already decide the result.
+    fn compile_logical_expression(&mut self, ie: &ast::InfixExpression) -> Result<(), CompileError> {
+        let mut jumps_to_false: Vec<usize> = Vec::new();
+        let mut jumps_to_end: Vec<usize> = Vec::new();
+
+        self.compile_expression(&ie.left)?;
+        let left_jump: usize = self.emit(code::OpJumpNotTruthy, &[9999])?;
+
+        if ie.operator == "&&" {
+            jumps_to_false.push(left_jump);
+        } else {
+            self.emit(code::OpTrue, &[])?;
+            jumps_to_end.push(self.emit(code::OpJump, &[9999])?);
+            let right_position: usize = self.current_instructions().len();
+            self.change_operand(left_jump, right_position)?;
+        }
+
+        self.compile_expression(&ie.right)?;
+        jumps_to_false.push(self.emit(code::OpJumpNotTruthy, &[9999])?);
+        self.emit(code::OpTrue, &[])?;
+        jumps_to_end.push(self.emit(code::OpJump, &[9999])?);
+
+        let false_position: usize = self.current_instructions().len();
+        for jump in jumps_to_false {
+            self.change_operand(jump, false_position)?;
+        }
+        self.emit(code::OpFalse, &[])?;
+
+        let end_position: usize = self.current_instructions().len();
+        for jump in jumps_to_end {
+            self.change_operand(jump, end_position)?;
+        }
+        return Ok(());
+    }
+
+    fn compile_if_expression(&mut self, ife: &ast::IfExpression) -> Result<(), CompileError> {
+        self.compile_expression(&ife.condition)?;
+
+        let jump_not_truthy_position: usize = self.emit(code::OpJumpNotTruthy, &[9999])?;
+
+        self.compile_block_statement(&ife.consequence)?;
+        self.leave_block_value()?;
+
+        let jump_position: usize = self.emit(code::OpJump, &[9999])?;
+
+        let after_consequence_position: usize = self.current_instructions().len();
+        self.change_operand(jump_not_truthy_position, after_consequence_position)?;
+
+        match ife.alternative.as_ref() {
+            Some(alt) => {
+                self.compile_block_statement(alt)?;
+                self.leave_block_value()?;
+            },
+            None => {
+                self.emit(code::OpNull, &[])?;
+            },
+        }
+
+        let after_alternative_position: usize = self.current_instructions().len();
+        self.change_operand(jump_position, after_alternative_position)?;
+        return Ok(());
+    }
+
+    // Keeps the value of a block's last expression statement on the stack, or
+    // pushes null when the block does not end in an expression.
+    fn leave_block_value(&mut self) -> Result<(), CompileError> {
+        if self.last_instruction_is(code::OpPop) {
+            self.remove_last_pop();
+        } else {
+            self.emit(code::OpNull, &[])?;
+        }
+        return Ok(());
+    }
+
+    fn compile_function_literal(&mut self, fl: &ast::FunctionLiteral, name: Option<&str>) -> Result<(), CompileError> {
+        self.enter_scope();
+
+        match name {
+            Some(name) => {
+                self.symbol_table.define_function_name(name);
+            },
+            None => (),
+        }
+
+        for param in fl.parameters.iter() {
+            self.symbol_table.define(&param.value);
+        }
+
+        match self.compile_block_statement(&fl.body) {
+            Ok(()) => (),
+            Err(err) => {
+                self.leave_scope();
+                return Err(err);
+            },
+        }
+
+        if self.last_instruction_is(code::OpPop) {
+            self.replace_last_pop_with_return();
+        }
+        if ! self.last_instruction_is(code::OpReturnValue) {
+            self.emit(code::OpReturn, &[])?;
+        }
+
+        let free_symbols: Vec<Symbol> = self.symbol_table.free_symbols.clone();
+        let num_locals: usize = self.symbol_table.num_definitions;
+        let scope: CompilationScope = self.leave_scope();
+
+        // the captured values are pushed in the enclosing scope and packed into the closure
+        for symbol in free_symbols.iter() {
+            self.load_symbol(symbol)?;
+        }
+
+        let mut func: object::CompiledFunction = object::CompiledFunction::new(scope.instructions, scope.lines, num_locals, fl.parameters.len());
+        func.name = name.map(String::from);
+        let index: usize = self.add_constant(object::Object::CompiledFunction(Rc::new(func)));
+        self.emit(code::OpClosure, &[index, free_symbols.len()])?;
+        return Ok(());
+    }
+
+    fn add_constant(&mut self, obj: object::Object) -> usize {
+        self.constants.push(obj);
+        return self.constants.len() - 1;
+    }
+
+    // Appends an instruction to the current scope and returns its position.
+    fn emit(&mut self, op: code::Opcode, operands: &[usize]) -> Result<usize, CompileError> {
+        self.check_operands(op, operands)?;
+
+        let instruction: code::Instructions = code::make(op, operands);
+        let node: Option<(ast::NodeKind, token::Span)> = self.node;
+        let scope: &mut CompilationScope = self.current_scope();
+
+        let position: usize = scope.instructions.len();
+        scope.instructions.extend(instruction);
+
+        match node {
+            Some((kind, span)) => {
+                let moved: bool = match scope.lines.last() {
+                    Some(last) => last.line != span.line || last.column != span.column || last.node != kind,
+                    None => true,
+                };
+                if moved {
+                    scope.lines.push(code::SourcePosition{ offset: position, line: span.line, column: span.column, node: kind });
+                }
+            },
+            None => (),
+        }
+
+        scope.previous_instruction = scope.last_instruction;
+        scope.last_instruction = Some(EmittedInstruction{ opcode: op, position: position });
+
+        return Ok(position);
+    }
+
+    // make keeps only as many bytes of an operand as its width, so an operand
+    // that does not fit is an error rather than a silently different program.
+    fn check_operands(&self, op: code::Opcode, operands: &[usize]) -> Result<(), CompileError> {
+        let span: token::Span = self.node.map(|(_, span)| span).unwrap_or_default();
+
+        for (i, (operand, width)) in operands.iter().zip(op.definition().operand_widths.iter()).enumerate() {
+            if *operand < 1 << (8 * width) {
+                continue;
+            }
+            return Err(match (op, i) {
+                (code::OpConstant, _) | (code::OpClosure, 0) => CompileError::TooManyConstants{ span: span },
+                // builtins live in the global scope of the symbol table
+                (code::OpGetGlobal, _) | (code::OpSetGlobal, _) | (code::OpGetBuiltin, _) => CompileError::TooManyGlobals{ span: span },
+                (code::OpGetLocal, _) | (code::OpSetLocal, _) => CompileError::TooManyLocals{ span: span },
+                (code::OpGetFree, _) | (code::OpClosure, _) => CompileError::TooManyFreeVariables{ span: span },
+                (code::OpCall, _) => CompileError::TooManyArguments{ span: span },
+                (code::OpArray, _) => CompileError::TooManyElements{ span: span },
+                // the operand counts keys and values, two per pair
+                (code::OpHash, _) => CompileError::TooManyPairs{ span: span },
+                // OpJump and OpJumpNotTruthy, the only other opcodes with operands
+                _ => CompileError::JumpTooFar{ span: span },
+            });
+        }
+        return Ok(());
+    }
+
+    fn current_scope(&mut self) -> &mut CompilationScope {
+        return self.scopes.last_mut().expect("compiler has no scope");
+    }
+
+    fn current_instructions(&mut self) -> &code::Instructions {
+        return &self.current_scope().instructions;
+    }
+
+    fn last_instruction_is(&mut self, op: code::Opcode) -> bool {
+        return match self.current_scope().last_instruction {
+            Some(last) => last.opcode == op,
+            None => false,
+        };
+    }
+
+    fn remove_last_pop(&mut self) {
+        let scope: &mut CompilationScope = self.current_scope();
+        match scope.last_instruction {
+            Some(last) => {
+                scope.instructions.truncate(last.position);
+                scope.lines.retain(|pos| pos.offset < last.position);
+            },
+            None => return,
+        }
+        scope.last_instruction = scope.previous_instruction;
+    }
+
+    fn replace_last_pop_with_return(&mut self) {
+        let position: usize = match self.current_scope().last_instruction {
+            Some(last) => last.position,
+            None => return,
+        };
+        self.replace_instruction(position, code::make(code::OpReturnValue, &[]));
+
+        match self.current_scope().last_instruction.as_mut() {
+            Some(last) => last.opcode = code::OpReturnValue,
+            None => (),
+        }
+    }
+
+    fn replace_instruction(&mut self, position: usize, instruction: code::Instructions) {
+        let scope: &mut CompilationScope = self.current_scope();
+        scope.instructions[position..position + instruction.len()].copy_from_slice(&instruction);
+    }
+
+    // Rewrites the operand of the instruction at position, e.g. to back-patch a
+    // jump target once it is known.
+    fn change_operand(&mut self, position: usize, operand: usize) -> Result<(), CompileError> {
+        let op: code::Opcode = match code::Opcode::from_byte(self.current_scope().instructions[position]) {
+            Some(op) => op,
+            None => return Ok(()),
+        };
+        self.check_operands(op, &[operand])?;
+        self.replace_instruction(position, code::make(op, &[operand]));
+        return Ok(());
+    }
+
+    fn enter_scope(&mut self) {
+        self.scopes.push(CompilationScope::new());
+        let outer: SymbolTable = mem::replace(&mut self.symbol_table, SymbolTable::new());
+        self.symbol_table = SymbolTable::new_enclosed(outer);
+    }
+
+    fn leave_scope(&mut self) -> CompilationScope {
+        let scope: CompilationScope = self.scopes.pop().expect("compiler has no scope");
+        match self.symbol_table.outer.take() {
+            Some(outer) => self.symbol_table = *outer,
+            None => (),
+        }
+        return scope;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lexer::*;
+    use parser::*;
+
+    fn parse(input: &str) -> ast::Program {
+        let mut l = lexer::Lexer::new(input);
+        let mut p = parser::Parser::new(&mut l);
+        return p.parse_program().unwrap();
+    }
+
+    fn compile(input: &str) -> Bytecode {
+        let mut c = Compiler::new();
+        match c.compile_program(&parse(input)) {
+            Ok(()) => (),
+            Err(err) => assert!(false, "compiler error: {}", err),
+        }
+        return c.bytecode();
+    }
+
+    fn concat(instructions: &[code::Instructions]) -> code::Instructions {
+        return instructions.iter().flat_map(|ins| ins.iter().cloned()).collect();
+    }
+
+    fn check_instructions(input: &str, expected: &[code::Instructions]) {
+        assert_eq!(compile(input).instructions, concat(expected), "input: {}", input);
+    }
+
+    fn check_integer_constants(input: &str, expected: &[i64]) {
+        let constants: Vec<i64> = compile(input).constants.iter().map(|c| match *c {
+            object::Object::Integer(value) => value,
+            _ => panic!("constant is not Integer, got={}", c.inspect()),
+        }).collect();
+        assert_eq!(constants, expected, "input: {}", input);
+    }
+
+    #[test]
+    fn test_integer_arithmetic() {
+        check_instructions("1 + 2", &[
+            code::make(code::OpConstant, &[0]),
+            code::make(code::OpConstant, &[1]),
+            code::make(code::OpAdd, &[]),
+            code::make(code::OpPop, &[]),
+        ]);
+        check_integer_constants("1 + 2", &[1, 2]);
+
+        check_instructions("1; 2", &[
+            code::make(code::OpConstant, &[0]),
+            code::make(code::OpPop, &[]),
+            code::make(code::OpConstant, &[1]),
+            code::make(code::OpPop, &[]),
+        ]);
+
+        check_instructions("-1 % 2", &[
+            code::make(code::OpConstant, &[0]),
+            code::make(code::OpMinus, &[]),
+            code::make(code::OpConstant, &[1]),
+            code::make(code::OpMod, &[]),
+            code::make(code::OpPop, &[]),
+        ]);
+    }
+
+    #[test]
+    fn test_comparisons() {
+        let tests: [(&str, code::Opcode); 6] = [
+            ("1 == 2",  code::OpEqual),
+            ("1 != 2",  code::OpNotEqual),
+            ("1 < 2",   code::OpLessThan),
+            ("1 <= 2",  code::OpLessEqual),
+            ("1 > 2",   code::OpGreaterThan),
+            ("1 >= 2",  code::OpGreaterEqual),
+        ];
+
+        for &(input, op) in tests.iter() {
+            check_instructions(input, &[
+                code::make(code::OpConstant, &[0]),
+                code::make(code::OpConstant, &[1]),
+                code::make(op, &[]),
+                code::make(code::OpPop, &[]),
+            ]);
+        }
+
+        check_instructions("!true", &[
+            code::make(code::OpTrue, &[]),
+            code::make(code::OpBang, &[]),
+            code::make(code::OpPop, &[]),
+        ]);
+    }
+
+    #[test]
+    fn test_conditionals() {
+        check_instructions("if (true) { 10 }; 3333;", &[
+            // 0000
+            code::make(code::OpTrue, &[]),
+            // 0001
+            code::make(code::OpJumpNotTruthy, &[10]),
+            // 0004
+            code::make(code::OpConstant, &[0]),
+            // 0007
+            code::make(code::OpJump, &[11]),
+            // 0010
+            code::make(code::OpNull, &[]),
+            // 0011
+            code::make(code::OpPop, &[]),
+            // 0012
+            code::make(code::OpConstant, &[1]),
+            // 0015
+            code::make(code::OpPop, &[]),
+        ]);
+
+        check_instructions("if (true) { 10 } else { 20 }; 3333;", &[
+            // 0000
+            code::make(code::OpTrue, &[]),
+            // 0001
+            code::make(code::OpJumpNotTruthy, &[10]),
+            // 0004
+            code::make(code::OpConstant, &[0]),
+            // 0007
+            code::make(code::OpJump, &[13]),
+            // 0010
+            code::make(code::OpConstant, &[1]),
+            // 0013
+            code::make(code::OpPop, &[]),
+            // 0014
+            code::make(code::OpConstant, &[2]),
+            // 0017
+            code::make(code::OpPop, &[]),
+        ]);
+
+        check_instructions("if (true) { }", &[
+            // 0000
+            code::make(code::OpTrue, &[]),
+            // 0001
+            code::make(code::OpJumpNotTruthy, &[8]),
+            // 0004
+            code::make(code::OpNull, &[]),
+            // 0005
+            code::make(code::OpJump, &[9]),
+            // 0008
+            code::make(code::OpNull, &[]),
+            // 0009
+            code::make(code::OpPop, &[]),
+        ]);
+    }
+
+    #[test]
+    fn test_logical_operators() {
+        check_instructions("true && false", &[
+            // 0000
+            code::make(code::OpTrue, &[]),
+            // 0001
+            code::make(code::OpJumpNotTruthy, &[12]),
+            // 0004
+            code::make(code::OpFalse, &[]),
+            // 0005
+            code::make(code::OpJumpNotTruthy, &[12]),
+            // 0008
+            code::make(code::OpTrue, &[]),
+            // 0009
+            code::make(code::OpJump, &[13]),
+            // 0012
+            code::make(code::OpFalse, &[]),
+            // 0013
+            code::make(code::OpPop, &[]),
+        ]);
+
+        check_instructions("true || false", &[
+            // 0000
+            code::make(code::OpTrue, &[]),
+            // 0001
+            code::make(code::OpJumpNotTruthy, &[8]),
+            // 0004
+            code::make(code::OpTrue, &[]),
+            // 0005
+            code::make(code::OpJump, &[17]),
+            // 0008
+            code::make(code::OpFalse, &[]),
+            // 0009
+            code::make(code::OpJumpNotTruthy, &[16]),
+            // 0012
+            code::make(code::OpTrue, &[]),
+            // 0013
+            code::make(code::OpJump, &[17]),
+            // 0016
+            code::make(code::OpFalse, &[]),
+            // 0017
+            code::make(code::OpPop, &[]),
+        ]);
+    }
+
+    #[test]
+    fn test_global_let_statements() {
+        check_instructions("let one = 1; let two = one; two;", &[
+            code::make(code::OpConstant, &[0]),
+            code::make(code::OpSetGlobal, &[0]),
+            code::make(code::OpGetGlobal, &[0]),
+            code::make(code::OpSetGlobal, &[1]),
+            code::make(code::OpGetGlobal, &[1]),
+            code::make(code::OpPop, &[]),
+        ]);
+    }
+
+    #[test]
+    fn test_collections() {
+        check_instructions("[1, 2][0]", &[
+            code::make(code::OpConstant, &[0]),
+            code::make(code::OpConstant, &[1]),
+            code::make(code::OpArray, &[2]),
+            code::make(code::OpConstant, &[2]),
+            code::make(code::OpIndex, &[]),
+            code::make(code::OpPop, &[]),
+        ]);
+
+        check_instructions("{1: 2, 3: 4}", &[
+            code::make(code::OpConstant, &[0]),
+            code::make(code::OpConstant, &[1]),
+            code::make(code::OpConstant, &[2]),
+            code::make(code::OpConstant, &[3]),
+            code::make(code::OpHash, &[4]),
+            code::make(code::OpPop, &[]),
+        ]);
+    }
+
+    #[test]
+    fn test_functions() {
+        let bytecode: Bytecode = compile("fn(a) { let b = a + 1; return b; }(2)");
+
+        assert_eq!(bytecode.instructions, concat(&[
+            code::make(code::OpClosure, &[1, 0]),
+            code::make(code::OpConstant, &[2]),
+            code::make(code::OpCall, &[1]),
+            code::make(code::OpPop, &[]),
+        ]));
+
+        match bytecode.constants[1] {
+            object::Object::CompiledFunction(ref func) => {
+                assert_eq!(func.num_parameters, 1);
+                assert_eq!(func.num_locals, 2);
+                assert_eq!(func.instructions, concat(&[
+                    code::make(code::OpGetLocal, &[0]),
+                    code::make(code::OpConstant, &[0]),
+                    code::make(code::OpAdd, &[]),
+                    code::make(code::OpSetLocal, &[1]),
+                    code::make(code::OpGetLocal, &[1]),
+                    code::make(code::OpReturnValue, &[]),
+                ]));
+            },
+            ref other => assert!(false, "constant is not CompiledFunction, got={}", other.inspect()),
+        }
+
+        // the last expression statement becomes the return value; an empty body returns null
+        match compile("fn() { 5 }").constants[1] {
+            object::Object::CompiledFunction(ref func) => assert_eq!(func.instructions, concat(&[
+                code::make(code::OpConstant, &[0]),
+                code::make(code::OpReturnValue, &[]),
+            ])),
+            ref other => assert!(false, "constant is not CompiledFunction, got={}", other.inspect()),
+        }
+        match compile("fn() { }").constants[0] {
+            object::Object::CompiledFunction(ref func) => assert_eq!(func.instructions, code::make(code::OpReturn, &[])),
+            ref other => assert!(false, "constant is not CompiledFunction, got={}", other.inspect()),
+        }
+    }
+
+    fn function_instructions(bytecode: &Bytecode, index: usize) -> code::Instructions {
+        return match bytecode.constants[index] {
+            object::Object::CompiledFunction(ref func) => func.instructions.clone(),
+            ref other => panic!("constant is not CompiledFunction, got={}", other.inspect()),
+        };
+    }
+
+    #[test]
+    fn test_closures() {
+        let bytecode: Bytecode = compile("fn(a) { fn(b) { a + b } }");
+
+        assert_eq!(function_instructions(&bytecode, 0), concat(&[
+            code::make(code::OpGetFree, &[0]),
+            code::make(code::OpGetLocal, &[0]),
+            code::make(code::OpAdd, &[]),
+            code::make(code::OpReturnValue, &[]),
+        ]));
+        assert_eq!(function_instructions(&bytecode, 1), concat(&[
+            code::make(code::OpGetLocal, &[0]),
+            code::make(code::OpClosure, &[0, 1]),
+            code::make(code::OpReturnValue, &[]),
+        ]));
+        assert_eq!(bytecode.instructions, concat(&[
+            code::make(code::OpClosure, &[1, 0]),
+            code::make(code::OpPop, &[]),
+        ]));
+
+        // a variable two functions out is captured by the function in between too
+        let bytecode: Bytecode = compile("fn(a) { fn() { fn() { a } } }");
+        assert_eq!(function_instructions(&bytecode, 0), concat(&[
+            code::make(code::OpGetFree, &[0]),
+            code::make(code::OpReturnValue, &[]),
+        ]));
+        assert_eq!(function_instructions(&bytecode, 1), concat(&[
+            code::make(code::OpGetFree, &[0]),
+            code::make(code::OpClosure, &[0, 1]),
+            code::make(code::OpReturnValue, &[]),
+        ]));
+    }
+
+    #[test]
+    fn test_recursive_closures() {
+        let bytecode: Bytecode = compile("let wrapper = fn() { let countDown = fn(x) { countDown(x - 1); }; countDown(1); }; wrapper();");
+
+        assert_eq!(function_instructions(&bytecode, 1), concat(&[
+            code::make(code::OpCurrentClosure, &[]),
+            code::make(code::OpGetLocal, &[0]),
+            code::make(code::OpConstant, &[0]),
+            code::make(code::OpSub, &[]),
+            code::make(code::OpCall, &[1]),
+            code::make(code::OpReturnValue, &[]),
+        ]));
+        assert_eq!(function_instructions(&bytecode, 3), concat(&[
+            code::make(code::OpClosure, &[1, 0]),
+            code::make(code::OpSetLocal, &[0]),
+            code::make(code::OpGetLocal, &[0]),
+            code::make(code::OpConstant, &[2]),
+            code::make(code::OpCall, &[1]),
+            code::make(code::OpReturnValue, &[]),
+        ]));
+        assert_eq!(bytecode.instructions, concat(&[
+            code::make(code::OpClosure, &[3, 0]),
+            code::make(code::OpSetGlobal, &[0]),
+            code::make(code::OpGetGlobal, &[0]),
+            code::make(code::OpCall, &[0]),
+            code::make(code::OpPop, &[]),
+        ]));
+    }
+
+    #[test]
+    fn test_builtins() {
+        check_instructions("len([]); push([], 1);", &[
+            code::make(code::OpGetBuiltin, &[0]),
+            code::make(code::OpArray, &[0]),
+            code::make(code::OpCall, &[1]),
+            code::make(code::OpPop, &[]),
+            code::make(code::OpGetBuiltin, &[4]),
+            code::make(code::OpArray, &[0]),
+            code::make(code::OpConstant, &[0]),
+            code::make(code::OpCall, &[2]),
+            code::make(code::OpPop, &[]),
+        ]);
+
+        assert_eq!(function_instructions(&compile("fn() { len }"), 0), concat(&[
+            code::make(code::OpGetBuiltin, &[0]),
+            code::make(code::OpReturnValue, &[]),
+        ]));
+
+        // a global of the same name shadows the builtin
+        check_instructions("let len = 1; len", &[
+            code::make(code::OpConstant, &[0]),
+            code::make(code::OpSetGlobal, &[0]),
+            code::make(code::OpGetGlobal, &[0]),
+            code::make(code::OpPop, &[]),
+        ]);
+    }
+
+    #[test]
+    fn test_line_table() {
+        let bytecode: Bytecode = compile("let x = fn(a) { a };\n1 +\n  x(2);");
+
+        let expected: code::LineTable = vec![
+            code::SourcePosition{ offset: 0,    line: 1, column: 9, node: ast::NodeKind::FunctionLiteral },
+            code::SourcePosition{ offset: 4,    line: 1, column: 1, node: ast::NodeKind::LetStatement },
+            code::SourcePosition{ offset: 7,    line: 2, column: 1, node: ast::NodeKind::IntegerLiteral },
+            code::SourcePosition{ offset: 10,   line: 3, column: 3, node: ast::NodeKind::Identifier },
+            code::SourcePosition{ offset: 13,   line: 3, column: 5, node: ast::NodeKind::IntegerLiteral },
+            code::SourcePosition{ offset: 16,   line: 3, column: 4, node: ast::NodeKind::CallExpression },
+            code::SourcePosition{ offset: 18,   line: 2, column: 3, node: ast::NodeKind::InfixExpression },
+            code::SourcePosition{ offset: 19,   line: 2, column: 1, node: ast::NodeKind::ExpressionStatement },
+        ];
+        assert_eq!(bytecode.lines, expected);
+
+        match bytecode.constants[0] {
+            object::Object::CompiledFunction(ref func) => assert_eq!(func.lines, vec![
+                code::SourcePosition{ offset: 0, line: 1, column: 17, node: ast::NodeKind::Identifier },
+                code::SourcePosition{ offset: 2, line: 1, column: 17, node: ast::NodeKind::ExpressionStatement },
+            ]),
+            ref other => assert!(false, "constant is not CompiledFunction, got={}", other.inspect()),
+        }
+    }
+
+    #[test]
+    fn test_compile_errors() {
+        let tests: [(&str, &str); 2] = [
+            ("x + 1",                           "1:1: undefined variable x"),
+            ("fn() { y }",                      "1:8: undefined variable y"),
+        ];
+
+        for (i, &(input, expected)) in tests.iter().enumerate() {
+            let mut c = Compiler::new();
+            match c.compile_program(&parse(input)) {
+                Ok(()) => assert!(false, "tests[{}]: compiler has no errors", i),
+                Err(err) => assert_eq!(err.to_string(), expected, "tests[{}]", i),
+            }
+        }
+    }
+
+    fn compile_error(input: &str) -> CompileError {
+        let mut c = Compiler::new();
+        return match c.compile_program(&parse(input)) {
+            Ok(()) => panic!("compiler has no errors"),
+            Err(err) => err,
+        };
+    }
+
+    #[test]
+    fn test_operand_limits() {
+        // constant 65536 no longer fits the two-byte operand of OpConstant
+        let input: String = "1;\n".repeat(65537);
+        assert_eq!(compile_error(&input), CompileError::TooManyConstants{ span: token::Span::new(3 * 65536, 3 * 65536 + 1, 65537, 1) });
+
+        let mut c = Compiler::new();
+        assert!(c.compile_program(&parse(&"1;\n".repeat(65536))).is_ok());
+
+        let args: Vec<&str> = vec!["true"; 256];
+        match compile_error(&format!("len({})", args.join(", "))) {
+            CompileError::TooManyArguments{ .. } => (),
+            other => assert!(false, "wrong error, got={}", other),
+        }
+
+        let pairs: Vec<&str> = vec!["true: true"; 32768];
+        match compile_error(&format!("{{{}}}", pairs.join(", "))) {
+            CompileError::TooManyPairs{ .. } => (),
+            other => assert!(false, "wrong error, got={}", other),
+        }
+
+        let mut c = Compiler::new();
+        assert!(c.compile_program(&parse(&format!("{{{}}}", pairs[1..].join(", ")))).is_ok());
+
+        let params: Vec<String> = (0..257).map(|i| format!("p{}", i)).collect();
+        match compile_error(&format!("fn({}) {{ p256 }}", params.join(", "))) {
+            CompileError::TooManyLocals{ .. } => (),
+            other => assert!(false, "wrong error, got={}", other),
+        }
+
+        // each `true;` is two bytes, so the consequence overflows the jump operand
+        match compile_error(&format!("if (true) {{ {} }}", "true; ".repeat(33000))) {
+            CompileError::JumpTooFar{ .. } => (),
+            other => assert!(false, "wrong error, got={}", other),
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use std::error::Error;
+use std::fmt;
+use token::*;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CompileError {
+    UndefinedVariable {                 // the identifier is not bound in any enclosing scope
+        name:       String,
+        span:       token::Span,
+    },
+    UnknownOperator {                   // the operator has no instruction
+        operator:   String,
+        span:       token::Span,
+    },
+    TooManyConstants {                  // a constant index does not fit its operand
+        span:       token::Span,
+    },
+    TooManyGlobals {
+        span:       token::Span,
+    },
+    TooManyLocals {
+        span:       token::Span,
+    },
+    TooManyFreeVariables {
+        span:       token::Span,
+    },
+    TooManyArguments {
+        span:       token::Span,
+    },
+    TooManyElements {                   // an array literal is too long to build in one instruction
+        span:       token::Span,
+    },
+    TooManyPairs {                      // a hash literal is too long to build in one instruction
+        span:       token::Span,
+    },
+    JumpTooFar {                        // the jump target lies past the reach of a jump operand
+        span:       token::Span,
+    },
+}
+
+impl CompileError {
+    pub fn span(&self) -> token::Span {
+        return match *self {
+            CompileError::UndefinedVariable{ span, .. } => span,
+            CompileError::UnknownOperator{ span, .. } => span,
+            CompileError::TooManyConstants{ span } => span,
+            CompileError::TooManyGlobals{ span } => span,
+            CompileError::TooManyLocals{ span } => span,
+            CompileError::TooManyFreeVariables{ span } => span,
+            CompileError::TooManyArguments{ span } => span,
+            CompileError::TooManyElements{ span } => span,
+            CompileError::TooManyPairs{ span } => span,
+            CompileError::JumpTooFar{ span } => span,
+        };
+    }
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match *self {
+            CompileError::UndefinedVariable{ ref name, span } =>
+                write!(f, "{}: undefined variable {}", span, name),
+            CompileError::UnknownOperator{ ref operator, span } =>
+                write!(f, "{}: unknown operator {}", span, operator),
+            CompileError::TooManyConstants{ span } =>
+                write!(f, "{}: too many constants, the limit is 65536", span),
+            CompileError::TooManyGlobals{ span } =>
+                write!(f, "{}: too many global bindings, the limit is 65536", span),
+            CompileError::TooManyLocals{ span } =>
+                write!(f, "{}: too many local bindings in a function, the limit is 256", span),
+            CompileError::TooManyFreeVariables{ span } =>
+                write!(f, "{}: too many captured variables in a function, the limit is 256", span),
+            CompileError::TooManyArguments{ span } =>
+                write!(f, "{}: too many arguments, the limit is 255", span),
+            CompileError::TooManyElements{ span } =>
+                write!(f, "{}: too many elements in an array literal, the limit is 65535", span),
+            CompileError::TooManyPairs{ span } =>
+                write!(f, "{}: too many pairs in a hash literal, the limit is 32767", span),
+            CompileError::JumpTooFar{ span } =>
+                write!(f, "{}: jump too far, the code of a function is limited to 65536 bytes", span),
+        };
+    }
+}
+
+impl Error for CompileError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let err = CompileError::UndefinedVariable{ name: String::from("x"), span: token::Span::new(4, 5, 2, 3) };
+        assert_eq!(err.to_string(), "2:3: undefined variable x");
+        assert_eq!(err.span(), token::Span::new(4, 5, 2, 3));
+
+        let err = CompileError::UnknownOperator{ operator: String::from("^"), span: token::Span::new(2, 3, 1, 3) };
+        assert_eq!(err.to_string(), "1:3: unknown operator ^");
+
+        let err = CompileError::TooManyConstants{ span: token::Span::new(0, 1, 7, 1) };
+        assert_eq!(err.to_string(), "7:1: too many constants, the limit is 65536");
+
+        let err = CompileError::TooManyPairs{ span: token::Span::new(0, 1, 1, 1) };
+        assert_eq!(err.to_string(), "1:1: too many pairs in a hash literal, the limit is 32767");
+    }
+}
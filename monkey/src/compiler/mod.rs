@@ -0,0 +1,5 @@
+pub mod compiler;
+
+pub mod error;
+
+pub mod symbol_table;
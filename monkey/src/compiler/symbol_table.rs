@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SymbolScope {
+    GLOBAL,         // top-level bindings, stored in the VM's globals
+    LOCAL,          // parameters and let bindings of a function, stored on the stack
+    BUILTIN,        // host functions, indexing builtins::BUILTINS
+    FREE,           // bindings of enclosing functions, captured by the closure
+    FUNCTION,       // the name a function literal is bound to, so that it can call itself
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Symbol {
+    pub name:       String,
+    pub scope:      SymbolScope,
+    pub index:      usize,
+}
+
+pub struct SymbolTable {
+    pub outer:              Option<Box<SymbolTable>>,   // the table of the enclosing function, if any
+    store:                  HashMap<String, Symbol>,
+    pub num_definitions:    usize,
+    pub free_symbols:       Vec<Symbol>,                // captured symbols as resolved in the enclosing table
+}
+
+impl SymbolTable {
+    pub fn new() -> SymbolTable {
+        return SymbolTable{
+            outer:              None,
+            store:              HashMap::new(),
+            num_definitions:    0,
+            free_symbols:       Vec::new(),
+        };
+    }
+
+    pub fn new_enclosed(outer: SymbolTable) -> SymbolTable {
+        let mut table: SymbolTable = SymbolTable::new();
+        table.outer = Some(Box::new(outer));
+        return table;
+    }
+
+    pub fn define(&mut self, name: &str) -> Symbol {
+        let scope: SymbolScope = match self.outer {
+            Some(_) => SymbolScope::LOCAL,
+            None => SymbolScope::GLOBAL,
+        };
+
+        // redefining a name in the same scope reuses its slot
+        match self.store.get(name) {
+            Some(symbol) if symbol.scope == scope => return symbol.clone(),
+            _ => (),
+        }
+
+        let symbol: Symbol = Symbol{ name: String::from(name), scope: scope, index: self.num_definitions };
+        self.store.insert(String::from(name), symbol.clone());
+        self.num_definitions += 1;
+        return symbol;
+    }
+
+    pub fn define_builtin(&mut self, index: usize, name: &str) -> Symbol {
+        let symbol: Symbol = Symbol{ name: String::from(name), scope: SymbolScope::BUILTIN, index: index };
+        self.store.insert(String::from(name), symbol.clone());
+        return symbol;
+    }
+
+    pub fn define_function_name(&mut self, name: &str) -> Symbol {
+        let symbol: Symbol = Symbol{ name: String::from(name), scope: SymbolScope::FUNCTION, index: 0 };
+        self.store.insert(String::from(name), symbol.clone());
+        return symbol;
+    }
+
+    // Resolves a name, capturing it as a free variable when it is bound by an
+    // enclosing function rather than globally or by this table.
+    pub fn resolve(&mut self, name: &str) -> Option<Symbol> {
+        match self.store.get(name) {
+            Some(symbol) => return Some(symbol.clone()),
+            None => (),
+        }
+
+        let symbol: Symbol = self.outer.as_mut()?.resolve(name)?;
+
+        return match symbol.scope {
+            SymbolScope::GLOBAL | SymbolScope::BUILTIN => Some(symbol),
+            _ => Some(self.define_free(symbol)),
+        };
+    }
+
+    fn define_free(&mut self, original: Symbol) -> Symbol {
+        let symbol: Symbol = Symbol{ name: original.name.clone(), scope: SymbolScope::FREE, index: self.free_symbols.len() };
+        self.free_symbols.push(original);
+        self.store.insert(symbol.name.clone(), symbol.clone());
+        return symbol;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn symbol(name: &str, scope: SymbolScope, index: usize) -> Symbol {
+        return Symbol{ name: String::from(name), scope: scope, index: index };
+    }
+
+    #[test]
+    fn test_define_and_resolve() {
+        let mut global = SymbolTable::new();
+        assert_eq!(global.define("a"), symbol("a", SymbolScope::GLOBAL, 0));
+        assert_eq!(global.define("b"), symbol("b", SymbolScope::GLOBAL, 1));
+        assert_eq!(global.define("a"), symbol("a", SymbolScope::GLOBAL, 0));
+
+        let mut local = SymbolTable::new_enclosed(global);
+        assert_eq!(local.define("c"), symbol("c", SymbolScope::LOCAL, 0));
+        assert_eq!(local.define("a"), symbol("a", SymbolScope::LOCAL, 1));
+
+        assert_eq!(local.resolve("a"), Some(symbol("a", SymbolScope::LOCAL, 1)));
+        assert_eq!(local.resolve("b"), Some(symbol("b", SymbolScope::GLOBAL, 1)));
+        assert_eq!(local.resolve("c"), Some(symbol("c", SymbolScope::LOCAL, 0)));
+        assert_eq!(local.resolve("d"), None);
+
+        let mut global = *local.outer.take().unwrap();
+        assert_eq!(global.resolve("c"), None);
+        assert_eq!(global.num_definitions, 2);
+    }
+
+    #[test]
+    fn test_resolve_free() {
+        let mut global = SymbolTable::new();
+        global.define("a");
+        global.define_builtin(3, "len");
+
+        let mut first = SymbolTable::new_enclosed(global);
+        first.define("b");
+        first.define("c");
+
+        let mut second = SymbolTable::new_enclosed(first);
+        second.define("d");
+
+        assert_eq!(second.resolve("a"), Some(symbol("a", SymbolScope::GLOBAL, 0)));
+        assert_eq!(second.resolve("len"), Some(symbol("len", SymbolScope::BUILTIN, 3)));
+        assert_eq!(second.resolve("c"), Some(symbol("c", SymbolScope::FREE, 0)));
+        assert_eq!(second.resolve("b"), Some(symbol("b", SymbolScope::FREE, 1)));
+        assert_eq!(second.resolve("c"), Some(symbol("c", SymbolScope::FREE, 0)));
+        assert_eq!(second.resolve("d"), Some(symbol("d", SymbolScope::LOCAL, 0)));
+        assert_eq!(second.resolve("e"), None);
+        assert_eq!(second.free_symbols, vec![symbol("c", SymbolScope::LOCAL, 1), symbol("b", SymbolScope::LOCAL, 0)]);
+
+        let first = second.outer.take().unwrap();
+        assert!(first.free_symbols.is_empty());
+    }
+
+    #[test]
+    fn test_resolve_nested_free() {
+        let mut first = SymbolTable::new_enclosed(SymbolTable::new());
+        first.define("a");
+        let second = SymbolTable::new_enclosed(first);
+        let mut third = SymbolTable::new_enclosed(second);
+
+        // captured by the middle function first, then from it
+        assert_eq!(third.resolve("a"), Some(symbol("a", SymbolScope::FREE, 0)));
+        assert_eq!(third.free_symbols, vec![symbol("a", SymbolScope::FREE, 0)]);
+
+        let second = third.outer.take().unwrap();
+        assert_eq!(second.free_symbols, vec![symbol("a", SymbolScope::LOCAL, 0)]);
+    }
+
+    #[test]
+    fn test_function_name() {
+        let mut global = SymbolTable::new();
+        global.define("fib");
+
+        let mut local = SymbolTable::new_enclosed(global);
+        local.define_function_name("fib");
+        assert_eq!(local.resolve("fib"), Some(symbol("fib", SymbolScope::FUNCTION, 0)));
+
+        // a parameter of the same name shadows the function name
+        local.define("fib");
+        assert_eq!(local.resolve("fib"), Some(symbol("fib", SymbolScope::LOCAL, 0)));
+    }
+}
@@ -0,0 +1,961 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+use ast::*;
+use ast::ast::Node;
+use object::*;
+
+// The most calls that can be active at once, counting the program itself as the
+// VM does. The evaluator recurses on the native stack, so without a limit deep
+// recursion would overflow it and abort the process.
+pub const MAX_FRAMES: usize = 1024;
+
+// Native stack that a thread needs to evaluate MAX_FRAMES nested calls, with
+// room for the expressions between them.
+pub const NATIVE_STACK_SIZE: usize = 64 * 1024 * 1024;
+
+thread_local! {
+    static CALL_DEPTH: Cell<usize> = const { Cell::new(0) };    // function calls being evaluated on this thread
+}
+
+pub fn eval_program(program: &ast::Program, env: &Rc<RefCell<environment::Environment>>) -> Option<object::Object> {
+    let mut result: Option<object::Object> = None;
+
+    for stmt in program.statements.iter() {
+        result = eval_statement(stmt, env);
+
+        match result {
+            Some(object::Object::ReturnValue(value)) => return Some(*value),
+            Some(object::Object::Error(mut err)) => {
+                err.unwind("<main>");
+                return Some(object::Object::Error(err));
+            },
+            _ => (),
+        }
+    }
+
+    return result;
+}
+
+pub fn eval_statement(stmt: &ast::Statement, env: &Rc<RefCell<environment::Environment>>) -> Option<object::Object> {
+    return match *stmt {
+        ast::Statement::Expression(ref stmt) => Some(eval_expression(&stmt.expression, env)),
+        ast::Statement::Let(ref stmt) => eval_let_statement(stmt, env),
+        ast::Statement::Return(ref stmt) => {
+            let value: object::Object = eval_expression(&stmt.return_value, env);
+            if is_error(&value) {
+                return Some(value);
+            }
+            Some(object::Object::ReturnValue(Box::new(value)))
+        },
+    };
+}
+
+// Errors raised below exp that have no position yet get the position of exp,
+// so that they point at the innermost node that failed.
+pub fn eval_expression(exp: &ast::Expression, env: &Rc<RefCell<environment::Environment>>) -> object::Object {
+    let mut result: object::Object = eval_expression_node(exp, env);
+    match result {
+        object::Object::Error(ref mut err) => err.locate(exp.span().line, exp.span().column),
+        _ => (),
+    }
+    return result;
+}
+
+fn eval_expression_node(exp: &ast::Expression, env: &Rc<RefCell<environment::Environment>>) -> object::Object {
+    return match *exp {
+        ast::Expression::IntegerLiteral(ref il) => object::Object::Integer(il.value),
+        ast::Expression::Boolean(ref b) => object::Object::Boolean(b.value),
+        ast::Expression::StringLiteral(ref sl) => object::Object::String(sl.value.clone()),
+        ast::Expression::Identifier(ref ident) => eval_identifier(ident, env),
+        ast::Expression::Prefix(ref pe) => {
+            let right: object::Object = eval_expression(&pe.right, env);
+            if is_error(&right) {
+                return right;
+            }
+            eval_prefix_expression(&pe.operator, right)
+        },
+        ast::Expression::Infix(ref ie) => {
+            let left: object::Object = eval_expression(&ie.left, env);
+            if is_error(&left) {
+                return left;
+            }
+            if ie.operator == "&&" || ie.operator == "||" {
+                return eval_logical_expression(&ie.operator, left, &ie.right, env);
+            }
+            let right: object::Object = eval_expression(&ie.right, env);
+            if is_error(&right) {
+                return right;
+            }
+            eval_infix_expression(&ie.operator, left, right)
+        },
+        ast::Expression::If(ref ife) => eval_if_expression(ife, env),
+        ast::Expression::FunctionLiteral(ref fl) => {
+            object::Object::Function(object::Function::new(Rc::clone(&fl.parameters), Rc::clone(&fl.body), Rc::clone(env)))
+        },
+        ast::Expression::Call(ref ce) => {
+            let function: object::Object = eval_expression(&ce.function, env);
+            if is_error(&function) {
+                return function;
+            }
+            let args: Vec<object::Object> = match eval_expressions(&ce.arguments, env) {
+                Ok(args) => args,
+                Err(err) => return err,
+            };
+            apply_function(function, args)
+        },
+        ast::Expression::Array(ref al) => match eval_expressions(&al.elements, env) {
+            Ok(elements) => object::Object::Array(elements),
+            Err(err) => err,
+        },
+        ast::Expression::Index(ref ie) => {
+            let left: object::Object = eval_expression(&ie.left, env);
+            if is_error(&left) {
+                return left;
+            }
+            let index: object::Object = eval_expression(&ie.index, env);
+            if is_error(&index) {
+                return index;
+            }
+            eval_index_expression(left, index)
+        },
+        ast::Expression::Hash(ref hl) => eval_hash_literal(hl, env),
+    };
+}
+
+fn eval_expressions(exps: &[ast::Expression], env: &Rc<RefCell<environment::Environment>>) -> Result<Vec<object::Object>, object::Object> {
+    let mut result: Vec<object::Object> = Vec::new();
+
+    for exp in exps.iter() {
+        let evaluated: object::Object = eval_expression(exp, env);
+        if is_error(&evaluated) {
+            return Err(evaluated);
+        }
+        result.push(evaluated);
+    }
+
+    return Ok(result);
+}
+
+fn eval_let_statement(stmt: &ast::LetStatement, env: &Rc<RefCell<environment::Environment>>) -> Option<object::Object> {
+    let value: object::Object = match eval_expression(&stmt.value, env) {
+        object::Object::Error(err) => return Some(object::Object::Error(err)),
+        // a function literal is named after its binding, for stack traces, as the compiler does
+        object::Object::Function(mut func) => {
+            match stmt.value {
+                ast::Expression::FunctionLiteral(_) => func.name = Some(stmt.name.value.clone()),
+                _ => (),
+            }
+            object::Object::Function(func)
+        },
+        value => value,
+    };
+    env.borrow_mut().set(stmt.name.value.clone(), value);
+    return None;
+}
+
+fn eval_block_statement(block: &ast::BlockStatement, env: &Rc<RefCell<environment::Environment>>) -> Option<object::Object> {
+    let mut result: Option<object::Object> = None;
+
+    for stmt in block.statements.iter() {
+        result = eval_statement(stmt, env);
+
+        // leave return values wrapped so the enclosing function or program can unwrap them
+        match result {
+            Some(object::Object::ReturnValue(_)) => return result,
+            Some(object::Object::Error(_)) => return result,
+            _ => (),
+        }
+    }
+
+    return result;
+}
+
+fn eval_identifier(ident: &ast::Identifier, env: &Rc<RefCell<environment::Environment>>) -> object::Object {
+    // bindings in the environment shadow builtins of the same name
+    let value: Option<object::Object> = env.borrow().get(&ident.value);
+    return match value.or_else(|| builtins::lookup(&ident.value)) {
+        Some(val) => val,
+        None => new_error(error::ErrorKind::UnknownIdentifier, format!("identifier not found: {}", ident.value)),
+    };
+}
+
+fn eval_prefix_expression(operator: &str, right: object::Object) -> object::Object {
+    return match operator {
+        "!" => eval_bang_operator_expression(right),
+        "-" => eval_minus_prefix_operator_expression(right),
+        _ => new_error(error::ErrorKind::UnknownOperator, format!("unknown operator: {}{}", operator, right.object_type())),
+    };
+}
+
+fn eval_bang_operator_expression(right: object::Object) -> object::Object {
+    return match right {
+        object::Object::Boolean(value) => object::Object::Boolean(! value),
+        object::Object::Null => object::Object::Boolean(true),
+        _ => object::Object::Boolean(false),
+    };
+}
+
+fn eval_minus_prefix_operator_expression(right: object::Object) -> object::Object {
+    return match right {
+        object::Object::Integer(value) => object::Object::Integer(value.wrapping_neg()),
+        _ => new_error(error::ErrorKind::UnknownOperator, format!("unknown operator: -{}", right.object_type())),
+    };
+}
+
+fn eval_infix_expression(operator: &str, left: object::Object, right: object::Object) -> object::Object {
+    return match (&left, &right) {
+        (&object::Object::Integer(l), &object::Object::Integer(r)) => eval_integer_infix_expression(operator, l, r),
+        (&object::Object::Boolean(l), &object::Object::Boolean(r)) => match operator {
+            "==" => object::Object::Boolean(l == r),
+            "!=" => object::Object::Boolean(l != r),
+            _ => new_error(error::ErrorKind::UnknownOperator, format!("unknown operator: {} {} {}", left.object_type(), operator, right.object_type())),
+        },
+        (object::Object::String(l), object::Object::String(r)) => eval_string_infix_expression(operator, l, r),
+        (&object::Object::Null, &object::Object::Null) => match operator {
+            "==" => object::Object::Boolean(true),
+            "!=" => object::Object::Boolean(false),
+            _ => new_error(error::ErrorKind::UnknownOperator, format!("unknown operator: {} {} {}", left.object_type(), operator, right.object_type())),
+        },
+        _ => {
+            if left.object_type() != right.object_type() {
+                new_error(error::ErrorKind::TypeMismatch, format!("type mismatch: {} {} {}", left.object_type(), operator, right.object_type()))
+            } else {
+                new_error(error::ErrorKind::UnknownOperator, format!("unknown operator: {} {} {}", left.object_type(), operator, right.object_type()))
+            }
+        },
+    };
+}
+
+// && and || only evaluate their right operand when the left one does not
+// already decide the result.
+fn eval_logical_expression(operator: &str, left: object::Object, right: &ast::Expression, env: &Rc<RefCell<environment::Environment>>) -> object::Object {
+    let left_truthy: bool = is_truthy(&left);
+    if (operator == "&&" && ! left_truthy) || (operator == "||" && left_truthy) {
+        return object::Object::Boolean(left_truthy);
+    }
+
+    let right: object::Object = eval_expression(right, env);
+    if is_error(&right) {
+        return right;
+    }
+    return object::Object::Boolean(is_truthy(&right));
+}
+
+fn eval_string_infix_expression(operator: &str, left: &str, right: &str) -> object::Object {
+    return match operator {
+        "+" => object::Object::String(format!("{}{}", left, right)),
+        "==" => object::Object::Boolean(left == right),
+        "!=" => object::Object::Boolean(left != right),
+        _ => new_error(error::ErrorKind::UnknownOperator, format!("unknown operator: {} {} {}", object::STRING_OBJ, operator, object::STRING_OBJ)),
+    };
+}
+
+fn eval_integer_infix_expression(operator: &str, left: i64, right: i64) -> object::Object {
+    return match operator {
+        "+" => object::Object::Integer(left.wrapping_add(right)),
+        "-" => object::Object::Integer(left.wrapping_sub(right)),
+        "*" => object::Object::Integer(left.wrapping_mul(right)),
+        "/" => {
+            if right == 0 {
+                new_error(error::ErrorKind::DivisionByZero, String::from("division by zero"))
+            } else {
+                object::Object::Integer(left.wrapping_div(right))
+            }
+        },
+        "%" => {
+            if right == 0 {
+                new_error(error::ErrorKind::DivisionByZero, String::from("division by zero"))
+            } else {
+                object::Object::Integer(left.wrapping_rem(right))
+            }
+        },
+        "<" => object::Object::Boolean(left < right),
+        ">" => object::Object::Boolean(left > right),
+        "<=" => object::Object::Boolean(left <= right),
+        ">=" => object::Object::Boolean(left >= right),
+        "==" => object::Object::Boolean(left == right),
+        "!=" => object::Object::Boolean(left != right),
+        _ => new_error(error::ErrorKind::UnknownOperator, format!("unknown operator: {} {} {}", object::INTEGER_OBJ, operator, object::INTEGER_OBJ)),
+    };
+}
+
+fn eval_index_expression(left: object::Object, index: object::Object) -> object::Object {
+    return match (&left, &index) {
+        (object::Object::Array(elements), object::Object::Integer(i)) => {
+            if *i < 0 || *i >= elements.len() as i64 {
+                object::Object::Null
+            } else {
+                elements[*i as usize].clone()
+            }
+        },
+        (object::Object::Hash(pairs), _) => match index.hash_key() {
+            Some(key) => match pairs.get(&key) {
+                Some(pair) => pair.value.clone(),
+                None => object::Object::Null,
+            },
+            None => new_error(error::ErrorKind::UnusableHashKey, format!("unusable as hash key: {}", index.object_type())),
+        },
+        _ => new_error(error::ErrorKind::IndexNotSupported, format!("index operator not supported: {}", left.object_type())),
+    };
+}
+
+fn eval_hash_literal(hl: &ast::HashLiteral, env: &Rc<RefCell<environment::Environment>>) -> object::Object {
+    let mut pairs: HashMap<object::HashKey, object::HashPair> = HashMap::new();
+
+    for (key_node, value_node) in hl.pairs.iter() {
+        let key: object::Object = eval_expression(key_node, env);
+        if is_error(&key) {
+            return key;
+        }
+
+        let hash_key: object::HashKey = match key.hash_key() {
+            Some(hash_key) => hash_key,
+            None => return new_error(error::ErrorKind::UnusableHashKey, format!("unusable as hash key: {}", key.object_type())),
+        };
+
+        let value: object::Object = eval_expression(value_node, env);
+        if is_error(&value) {
+            return value;
+        }
+
+        pairs.insert(hash_key, object::HashPair{ key: key, value: value });
+    }
+
+    return object::Object::Hash(pairs);
+}
+
+fn eval_if_expression(ife: &ast::IfExpression, env: &Rc<RefCell<environment::Environment>>) -> object::Object {
+    let condition: object::Object = eval_expression(&ife.condition, env);
+    if is_error(&condition) {
+        return condition;
+    }
+
+    let result: Option<object::Object>;
+    if is_truthy(&condition) {
+        result = eval_block_statement(&ife.consequence, env);
+    } else {
+        match ife.alternative.as_ref() {
+            Some(alt) => result = eval_block_statement(alt, env),
+            None => result = None,
+        }
+    }
+
+    return result.unwrap_or(object::Object::Null);
+}
+
+fn apply_function(function: object::Object, args: Vec<object::Object>) -> object::Object {
+    let func: object::Function = match function {
+        object::Object::Function(func) => func,
+        object::Object::Builtin(builtin) => return (builtin.func)(args),
+        _ => return new_error(error::ErrorKind::NotAFunction, format!("not a function: {}", function.object_type())),
+    };
+
+    if func.parameters.len() != args.len() {
+        return new_error(error::ErrorKind::WrongArgumentCount, format!("wrong number of arguments: want={}, got={}", func.parameters.len(), args.len()));
+    }
+
+    let depth: usize = CALL_DEPTH.with(|depth| depth.get());
+    if depth + 1 >= MAX_FRAMES {
+        return new_error(error::ErrorKind::StackOverflow, String::from("stack overflow"));
+    }
+
+    let extended_env: Rc<RefCell<environment::Environment>> = extend_function_env(&func, args);
+    CALL_DEPTH.with(|call_depth| call_depth.set(depth + 1));
+    let evaluated: Option<object::Object> = eval_block_statement(&func.body, &extended_env);
+    CALL_DEPTH.with(|call_depth| call_depth.set(depth));
+
+    return match unwrap_return_value(evaluated.unwrap_or(object::Object::Null)) {
+        object::Object::Error(mut err) => {
+            err.unwind(func.name.as_ref().map_or("<anonymous>", |name| name.as_str()));
+            object::Object::Error(err)
+        },
+        value => value,
+    };
+}
+
+fn extend_function_env(func: &object::Function, args: Vec<object::Object>) -> Rc<RefCell<environment::Environment>> {
+    let mut env: environment::Environment = environment::Environment::new_enclosed(Rc::clone(&func.env));
+
+    for (param, arg) in func.parameters.iter().zip(args) {
+        env.set(param.value.clone(), arg);
+    }
+
+    return Rc::new(RefCell::new(env));
+}
+
+fn unwrap_return_value(obj: object::Object) -> object::Object {
+    return match obj {
+        object::Object::ReturnValue(value) => *value,
+        _ => obj,
+    };
+}
+
+fn is_truthy(obj: &object::Object) -> bool {
+    return match *obj {
+        object::Object::Null => false,
+        object::Object::Boolean(value) => value,
+        _ => true,
+    };
+}
+
+fn is_error(obj: &object::Object) -> bool {
+    return matches!(*obj, object::Object::Error(_));
+}
+
+fn new_error(kind: error::ErrorKind, message: String) -> object::Object {
+    return object::Object::Error(error::RuntimeError::new(kind, message));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lexer::*;
+    use object::error;
+    use parser::*;
+    use std::thread;
+
+    fn test_eval(input: &str) -> Option<object::Object> {
+        let mut l = lexer::Lexer::new(input);
+        let mut p = parser::Parser::new(&mut l);
+        let env = Rc::new(RefCell::new(environment::Environment::new()));
+
+        match p.parse_program() {
+            Ok(prog) => return eval_program(&prog, &env),
+            Err(errors) => {
+                assert!(false, "parser has {} errors: {:?}", errors.len(), errors);
+                return None;
+            },
+        }
+    }
+
+    fn check_integer_object(obj: Option<object::Object>, expected: i64, i: usize) {
+        match obj {
+            Some(object::Object::Integer(value)) => assert_eq!(value, expected, "tests[{}]", i),
+            Some(other) => assert!(false, "tests[{}]: object is not Integer, got={}", i, other.inspect()),
+            None => assert!(false, "tests[{}]: no object returned", i),
+        }
+    }
+
+    fn check_boolean_object(obj: Option<object::Object>, expected: bool, i: usize) {
+        match obj {
+            Some(object::Object::Boolean(value)) => assert_eq!(value, expected, "tests[{}]", i),
+            Some(other) => assert!(false, "tests[{}]: object is not Boolean, got={}", i, other.inspect()),
+            None => assert!(false, "tests[{}]: no object returned", i),
+        }
+    }
+
+    fn check_null_object(obj: Option<object::Object>, i: usize) {
+        match obj {
+            Some(object::Object::Null) => (),
+            Some(other) => assert!(false, "tests[{}]: object is not Null, got={}", i, other.inspect()),
+            None => assert!(false, "tests[{}]: no object returned", i),
+        }
+    }
+
+    #[test]
+    fn test_eval_integer_expression() {
+        let tests: [(&str, i64); 18] = [
+            ("5",                                   5),
+            ("10",                                  10),
+            ("-5",                                  -5),
+            ("-10",                                 -10),
+            ("5 + 5 + 5 + 5 - 10",                  10),
+            ("2 * 2 * 2 * 2 * 2",                   32),
+            ("-50 + 100 + -50",                     0),
+            ("5 * 2 + 10",                          20),
+            ("5 + 2 * 10",                          25),
+            ("20 + 2 * -10",                        0),
+            ("50 / 2 * 2 + 10",                     60),
+            ("2 * (5 + 10)",                        30),
+            ("3 * 3 * 3 + 10",                      37),
+            ("3 * (3 * 3) + 10",                    37),
+            ("(5 + 10 * 2 + 15 / 3) * 2 + -10",     50),
+            ("17 % 5",                              2),
+            ("-17 % 5",                             -2),
+            ("2 + 17 % 5 * 3",                      8),
+        ];
+
+        for (i, &(input, expected)) in tests.iter().enumerate() {
+            check_integer_object(test_eval(input), expected, i);
+        }
+    }
+
+    #[test]
+    fn test_eval_boolean_expression() {
+        let tests: [(&str, bool); 27] = [
+            ("true",                true),
+            ("false",               false),
+            ("1 < 2",               true),
+            ("1 > 2",               false),
+            ("1 < 1",               false),
+            ("1 > 1",               false),
+            ("1 == 1",              true),
+            ("1 != 1",              false),
+            ("1 == 2",              false),
+            ("1 != 2",              true),
+            ("true == true",        true),
+            ("false == false",      true),
+            ("true == false",       false),
+            ("true != false",       true),
+            ("false != true",       true),
+            ("(1 < 2) == true",     true),
+            ("(1 < 2) == false",    false),
+            ("(1 > 2) == true",     false),
+            ("(1 > 2) == false",    true),
+            ("1 <= 1",              true),
+            ("2 <= 1",              false),
+            ("1 >= 1",              true),
+            ("1 >= 2",              false),
+            ("true && false",       false),
+            ("true && 1 < 2",       true),
+            ("false || 1 > 2",      false),
+            ("1 > 2 || 2 > 1",      true),
+        ];
+
+        for (i, &(input, expected)) in tests.iter().enumerate() {
+            check_boolean_object(test_eval(input), expected, i);
+        }
+    }
+
+    #[test]
+    fn test_bang_operator() {
+        let tests: [(&str, bool); 6] = [
+            ("!true",       false),
+            ("!false",      true),
+            ("!5",          false),
+            ("!!true",      true),
+            ("!!false",     false),
+            ("!!5",         true),
+        ];
+
+        for (i, &(input, expected)) in tests.iter().enumerate() {
+            check_boolean_object(test_eval(input), expected, i);
+        }
+    }
+
+    #[test]
+    fn test_if_else_expressions() {
+        let tests: [(&str, Option<i64>); 7] = [
+            ("if (true) { 10 }",                Some(10)),
+            ("if (false) { 10 }",               None),
+            ("if (1) { 10 }",                   Some(10)),
+            ("if (1 < 2) { 10 }",               Some(10)),
+            ("if (1 > 2) { 10 }",               None),
+            ("if (1 > 2) { 10 } else { 20 }",   Some(20)),
+            ("if (1 < 2) { 10 } else { 20 }",   Some(10)),
+        ];
+
+        for (i, &(input, expected)) in tests.iter().enumerate() {
+            match expected {
+                Some(value) => check_integer_object(test_eval(input), value, i),
+                None => check_null_object(test_eval(input), i),
+            }
+        }
+    }
+
+    #[test]
+    fn test_return_statements() {
+        let tests: [(&str, i64); 5] = [
+            ("return 10;",                  10),
+            ("return 10; 9;",               10),
+            ("return 2 * 5; 9;",            10),
+            ("9; return 2 * 5; 9;",         10),
+            ("
+if (10 > 1) {
+    if (10 > 1) {
+        return 10;
+    }
+
+    return 1;
+}
+",                                          10),
+        ];
+
+        for (i, &(input, expected)) in tests.iter().enumerate() {
+            check_integer_object(test_eval(input), expected, i);
+        }
+    }
+
+    #[test]
+    fn test_error_handling() {
+        let tests: [(&str, error::ErrorKind, &str); 20] = [
+            ("5 + true;",                           error::ErrorKind::TypeMismatch,       "type mismatch: INTEGER + BOOLEAN"),
+            ("5 + true; 5;",                        error::ErrorKind::TypeMismatch,       "type mismatch: INTEGER + BOOLEAN"),
+            ("-true",                               error::ErrorKind::UnknownOperator,    "unknown operator: -BOOLEAN"),
+            ("true + false;",                       error::ErrorKind::UnknownOperator,    "unknown operator: BOOLEAN + BOOLEAN"),
+            ("5; true + false; 5",                  error::ErrorKind::UnknownOperator,    "unknown operator: BOOLEAN + BOOLEAN"),
+            ("if (10 > 1) { true + false; }",       error::ErrorKind::UnknownOperator,    "unknown operator: BOOLEAN + BOOLEAN"),
+            ("
+if (10 > 1) {
+    if (10 > 1) {
+        return true + false;
+    }
+
+    return 1;
+}
+",                                                  error::ErrorKind::UnknownOperator,    "unknown operator: BOOLEAN + BOOLEAN"),
+            ("foobar",                              error::ErrorKind::UnknownIdentifier,  "identifier not found: foobar"),
+            ("10 / (5 - 5)",                        error::ErrorKind::DivisionByZero,     "division by zero"),
+            ("let x = 5; x(1)",                     error::ErrorKind::NotAFunction,       "not a function: INTEGER"),
+            ("let f = fn(a, b) { a }; f(1)",        error::ErrorKind::WrongArgumentCount, "wrong number of arguments: want=2, got=1"),
+            ("\"Hello\" - \"World\"",                error::ErrorKind::UnknownOperator,    "unknown operator: STRING - STRING"),
+            ("5 % 0",                               error::ErrorKind::DivisionByZero,     "division by zero"),
+            ("true && (1 + true)",                  error::ErrorKind::TypeMismatch,       "type mismatch: INTEGER + BOOLEAN"),
+            ("1[0]",                                error::ErrorKind::IndexNotSupported,  "index operator not supported: INTEGER"),
+            ("[1, 2][true]",                        error::ErrorKind::IndexNotSupported,  "index operator not supported: ARRAY"),
+            ("{\"name\": \"Monkey\"}[fn(x) { x }];",    error::ErrorKind::UnusableHashKey,    "unusable as hash key: FUNCTION"),
+            ("{fn(x) { x }: 1}",                    error::ErrorKind::UnusableHashKey,    "unusable as hash key: FUNCTION"),
+            ("{[1]: 1}",                            error::ErrorKind::UnusableHashKey,    "unusable as hash key: ARRAY"),
+            ("\"a\" + 1",                           error::ErrorKind::TypeMismatch,       "type mismatch: STRING + INTEGER"),
+        ];
+
+        for (i, &(input, kind, message)) in tests.iter().enumerate() {
+            match test_eval(input) {
+                Some(object::Object::Error(err)) => {
+                    assert_eq!(err.kind, kind, "tests[{}]", i);
+                    assert_eq!(err.message, message, "tests[{}]", i);
+                },
+                Some(other) => assert!(false, "tests[{}]: no error object returned, got={}", i, other.inspect()),
+                None => assert!(false, "tests[{}]: no object returned", i),
+            }
+        }
+    }
+
+    #[test]
+    fn test_string_literal() {
+        match test_eval("\"Hello World!\"") {
+            Some(object::Object::String(value)) => assert_eq!(value, "Hello World!"),
+            Some(other) => assert!(false, "object is not String, got={}", other.inspect()),
+            None => assert!(false, "no object returned"),
+        }
+    }
+
+    #[test]
+    fn test_string_concatenation() {
+        match test_eval("let name = \"Monkey\"; \"Hello\" + \" \" + name + \"!\\n\"") {
+            Some(object::Object::String(value)) => assert_eq!(value, "Hello Monkey!\n"),
+            Some(other) => assert!(false, "object is not String, got={}", other.inspect()),
+            None => assert!(false, "no object returned"),
+        }
+
+        check_boolean_object(test_eval("\"a\" == \"a\""), true, 0);
+        check_boolean_object(test_eval("\"a\" != \"a\""), false, 1);
+        check_boolean_object(test_eval("\"a\" == \"b\""), false, 2);
+    }
+
+    #[test]
+    fn test_logical_operators_short_circuit() {
+        let tests: [(&str, bool); 4] = [
+            ("false && missing",        false),
+            ("true || missing",         true),
+            ("false && (1 / 0)",        false),
+            ("let f = fn() { 1 / 0 }; 1 < 2 || f()", true),
+        ];
+
+        for (i, &(input, expected)) in tests.iter().enumerate() {
+            check_boolean_object(test_eval(input), expected, i);
+        }
+
+        match test_eval("true && missing") {
+            Some(object::Object::Error(err)) => assert_eq!(err.message, "identifier not found: missing"),
+            Some(other) => assert!(false, "no error object returned, got={}", other.inspect()),
+            None => assert!(false, "no object returned"),
+        }
+    }
+
+    #[test]
+    fn test_array_literals() {
+        match test_eval("[1, 2 * 2, 3 + 3]") {
+            Some(object::Object::Array(elements)) => {
+                assert_eq!(elements.len(), 3);
+                check_integer_object(Some(elements[0].clone()), 1, 0);
+                check_integer_object(Some(elements[1].clone()), 4, 1);
+                check_integer_object(Some(elements[2].clone()), 6, 2);
+            },
+            Some(other) => assert!(false, "object is not Array, got={}", other.inspect()),
+            None => assert!(false, "no object returned"),
+        }
+    }
+
+    #[test]
+    fn test_array_index_expressions() {
+        let tests: [(&str, i64); 8] = [
+            ("[1, 2, 3][0]",                                        1),
+            ("[1, 2, 3][1]",                                        2),
+            ("[1, 2, 3][2]",                                        3),
+            ("let i = 0; [1][i];",                                  1),
+            ("[1, 2, 3][1 + 1];",                                   3),
+            ("let myArray = [1, 2, 3]; myArray[2];",                3),
+            ("let myArray = [1, 2, 3]; myArray[0] + myArray[1] + myArray[2];", 6),
+            ("let f = fn(x) { x * 2 }; [f][0](4)",                  8),
+        ];
+
+        for (i, &(input, expected)) in tests.iter().enumerate() {
+            check_integer_object(test_eval(input), expected, i);
+        }
+
+        check_null_object(test_eval("[1, 2, 3][3]"), 0);
+        check_null_object(test_eval("[1, 2, 3][-1]"), 1);
+    }
+
+    #[test]
+    fn test_hash_literals() {
+        let input = "
+let two = \"two\";
+{
+    \"one\": 10 - 9,
+    two: 1 + 1,
+    \"thr\" + \"ee\": 6 / 2,
+    4: 4,
+    true: 5,
+    false: 6
+}";
+
+        match test_eval(input) {
+            Some(object::Object::Hash(pairs)) => {
+                let expected: [(object::HashKey, i64); 6] = [
+                    (object::HashKey::String(String::from("one")),      1),
+                    (object::HashKey::String(String::from("two")),      2),
+                    (object::HashKey::String(String::from("three")),    3),
+                    (object::HashKey::Integer(4),                       4),
+                    (object::HashKey::Boolean(true),                    5),
+                    (object::HashKey::Boolean(false),                   6),
+                ];
+                assert_eq!(pairs.len(), expected.len());
+
+                for (i, &(ref key, value)) in expected.iter().enumerate() {
+                    match pairs.get(key) {
+                        Some(pair) => check_integer_object(Some(pair.value.clone()), value, i),
+                        None => assert!(false, "tests[{}]: no pair for given key in pairs", i),
+                    }
+                }
+            },
+            Some(other) => assert!(false, "object is not Hash, got={}", other.inspect()),
+            None => assert!(false, "no object returned"),
+        }
+    }
+
+    #[test]
+    fn test_hash_index_expressions() {
+        let tests: [(&str, i64); 5] = [
+            ("{\"foo\": 5}[\"foo\"]",                  5),
+            ("let key = \"foo\"; {\"foo\": 5}[key]",    5),
+            ("{5: 5}[5]",                             5),
+            ("{true: 5}[true]",                       5),
+            ("{false: 5}[false]",                     5),
+        ];
+
+        for (i, &(input, expected)) in tests.iter().enumerate() {
+            check_integer_object(test_eval(input), expected, i);
+        }
+
+        check_null_object(test_eval("{\"foo\": 5}[\"bar\"]"), 0);
+        check_null_object(test_eval("{}[\"foo\"]"), 1);
+    }
+
+    #[test]
+    fn test_builtin_functions() {
+        let tests: [(&str, i64); 9] = [
+            ("len(\"\")",                                 0),
+            ("len(\"four\")",                             4),
+            ("len(\"hello world\")",                      11),
+            ("len(\"h\u{e9}llo\")",                       5),
+            ("len([1, 2, 3])",                            3),
+            ("len({1: 2})",                               1),
+            ("first([1, 2, 3])",                          1),
+            ("last(rest([1, 2, 3]))",                     3),
+            ("let len = fn(x) { 42 }; len([1])",          42),
+        ];
+
+        for (i, &(input, expected)) in tests.iter().enumerate() {
+            check_integer_object(test_eval(input), expected, i);
+        }
+
+        check_null_object(test_eval("first([])"), 0);
+        check_null_object(test_eval("puts(\"hello\")"), 1);
+
+        match test_eval("let a = [1]; let b = push(a, 2); [a, b]") {
+            Some(object::Object::Array(elements)) => {
+                assert_eq!(elements[0].inspect(), "[1]");
+                assert_eq!(elements[1].inspect(), "[1, 2]");
+            },
+            Some(other) => assert!(false, "object is not Array, got={}", other.inspect()),
+            None => assert!(false, "no object returned"),
+        }
+    }
+
+    #[test]
+    fn test_unicode_source() {
+        let tests: [(&str, i64); 3] = [
+            ("let 名前 = \"モンキー\"; len(名前)",                       4),
+            ("let λ = fn(χ) { χ * 2 }; /* ✓ */ λ(21)",                 42),
+            ("let счёт = [1, 2, 3]; // сумма\nсчёт[0] + счёт[2]",     4),
+        ];
+
+        for (i, &(input, expected)) in tests.iter().enumerate() {
+            check_integer_object(test_eval(input), expected, i);
+        }
+    }
+
+    #[test]
+    fn test_builtin_function_errors() {
+        let tests: [(&str, &str); 6] = [
+            ("len(1)",                      "argument to `len` not supported, got INTEGER"),
+            ("len(\"one\", \"two\")",       "wrong number of arguments: want=1, got=2"),
+            ("first(1)",                    "argument to `first` must be ARRAY, got INTEGER"),
+            ("last(\"abc\")",               "argument to `last` must be ARRAY, got STRING"),
+            ("rest()",                      "wrong number of arguments: want=1, got=0"),
+            ("push(1, 1)",                  "argument to `push` must be ARRAY, got INTEGER"),
+        ];
+
+        for (i, &(input, expected)) in tests.iter().enumerate() {
+            match test_eval(input) {
+                Some(object::Object::Error(err)) => assert_eq!(err.message, expected, "tests[{}]", i),
+                Some(other) => assert!(false, "tests[{}]: no error object returned, got={}", i, other.inspect()),
+                None => assert!(false, "tests[{}]: no object returned", i),
+            }
+        }
+    }
+
+    #[test]
+    fn test_let_statements() {
+        let tests: [(&str, i64); 4] = [
+            ("let a = 5; a;",                               5),
+            ("let a = 5 * 5; a;",                           25),
+            ("let a = 5; let b = a; b;",                    5),
+            ("let a = 5; let b = a; let c = a + b + 5; c;", 15),
+        ];
+
+        for (i, &(input, expected)) in tests.iter().enumerate() {
+            check_integer_object(test_eval(input), expected, i);
+        }
+
+        assert!(test_eval("let a = 5;").is_none(), "let statement produced a value");
+    }
+
+    #[test]
+    fn test_function_object() {
+        match test_eval("fn(x) { x + 2; };") {
+            Some(object::Object::Function(func)) => {
+                assert_eq!(func.parameters.len(), 1);
+                assert_eq!(func.parameters[0].value, "x");
+                assert_eq!(func.inspect(), "fn(x) { (x + 2); }");
+            },
+            Some(other) => assert!(false, "object is not Function, got={}", other.inspect()),
+            None => assert!(false, "no object returned"),
+        }
+    }
+
+    #[test]
+    fn test_function_application() {
+        let tests: [(&str, i64); 7] = [
+            ("let identity = fn(x) { x; }; identity(5);",               5),
+            ("let identity = fn(x) { return x; }; identity(5);",        5),
+            ("let double = fn(x) { x * 2; }; double(5);",               10),
+            ("let add = fn(x, y) { x + y; }; add(5, 5);",               10),
+            ("let add = fn(x, y) { x + y; }; add(5 + 5, add(5, 5));",   20),
+            ("fn(x) { x; }(5)",                                         5),
+            ("let f = fn() { return 1; 2; }; f() + 1",                  2),
+        ];
+
+        for (i, &(input, expected)) in tests.iter().enumerate() {
+            check_integer_object(test_eval(input), expected, i);
+        }
+
+        check_null_object(test_eval("fn() { }()"), 0);
+    }
+
+    #[test]
+    fn test_closures() {
+        let input = "
+let newAdder = fn(x) {
+    fn(y) { x + y };
+};
+
+let addTwo = newAdder(2);
+addTwo(2);";
+
+        check_integer_object(test_eval(input), 4, 0);
+    }
+
+    #[test]
+    fn test_recursive_function() {
+        let input = "
+let fibonacci = fn(x) {
+    if (x < 2) {
+        return x;
+    }
+    fibonacci(x - 1) + fibonacci(x - 2);
+};
+
+fibonacci(15);";
+
+        check_integer_object(test_eval(input), 610, 0);
+    }
+
+    #[test]
+    fn test_closure_sees_defining_scope() {
+        let input = "
+let x = 10;
+let f = fn() { x };
+let g = fn(x) { f() };
+g(20);";
+
+        check_integer_object(test_eval(input), 10, 0);
+    }
+
+    fn check_stack(input: &str, expected: &[(&str, usize, usize)]) {
+        match test_eval(input) {
+            Some(object::Object::Error(err)) => {
+                let stack: Vec<(&str, usize, usize)> = err.stack.iter()
+                    .map(|frame| (frame.function.as_str(), frame.position.unwrap().0, frame.position.unwrap().1))
+                    .collect();
+                assert_eq!(stack, expected, "input: {}", input);
+            },
+            Some(other) => assert!(false, "no error object returned, got={}", other.inspect()),
+            None => assert!(false, "no object returned"),
+        }
+    }
+
+    #[test]
+    fn test_error_stack_traces() {
+        check_stack("5 + true", &[("<main>", 1, 3)]);
+        check_stack("let x = 1;\nlet y = [x, missing];", &[("<main>", 2, 13)]);
+        check_stack("\
+let add = fn(a, b) { a + b };
+let apply = fn(f) { f(1, true) };
+apply(add)", &[("add", 1, 24), ("apply", 2, 22), ("<main>", 3, 6)]);
+        check_stack("let f = fn() { len(1) };\nlet g = f;\ng()", &[("f", 1, 19), ("<main>", 3, 2)]);
+        check_stack("fn() { 1 / 0 }()", &[("<anonymous>", 1, 10), ("<main>", 1, 15)]);
+    }
+
+    #[test]
+    fn test_deep_recursion() {
+        // a test thread has too little native stack for MAX_FRAMES nested calls
+        let evaluator = thread::Builder::new().stack_size(NATIVE_STACK_SIZE).spawn(|| {
+            check_integer_object(test_eval("let f = fn(x) { if (x == 0) { 0 } else { 1 + f(x - 1) } }; f(1022)"), 1022, 0);
+
+            match test_eval("let f = fn(x) { f(x + 1) }; f(0);") {
+                Some(object::Object::Error(err)) => {
+                    assert_eq!(err.kind, error::ErrorKind::StackOverflow);
+                    assert_eq!(err.message, "stack overflow");
+                    assert_eq!(err.stack.len(), MAX_FRAMES);
+                    assert_eq!(err.stack[0], error::StackFrame{ function: String::from("f"), position: Some((1, 18)) });
+                    assert_eq!(err.traceback(None), "\
+Traceback (most recent call last):
+  line 1, column 30, in <main>
+  line 1, column 18, in f
+  [previous frame repeated 1022 more times]
+StackOverflow: stack overflow");
+                },
+                Some(other) => assert!(false, "no error object returned, got={}", other.inspect()),
+                None => assert!(false, "no object returned"),
+            }
+
+            // the calls that failed are no longer counted
+            check_integer_object(test_eval("let f = fn(x) { if (x == 0) { 0 } else { 1 + f(x - 1) } }; f(1022)"), 1022, 1);
+        }).unwrap();
+        evaluator.join().unwrap();
+    }
+}
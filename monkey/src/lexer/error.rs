@@ -0,0 +1,67 @@
+use std::error::Error;
+use std::fmt;
+use token::*;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LexError {
+    IllegalCharacter {                  // a character that cannot start any token
+        ch:         char,
+        span:       token::Span,
+    },
+    UnterminatedString {                // the line ended before the closing quote
+        span:       token::Span,        // from the opening quote to the end of its line
+    },
+    UnterminatedBlockComment {          // the input ended inside a /* */ comment
+        span:       token::Span,        // location of the outermost /*
+    },
+    InvalidEscape {                     // an unknown or malformed escape sequence inside a string
+        sequence:   String,
+        span:       token::Span,
+    },
+}
+
+impl LexError {
+    pub fn span(&self) -> token::Span {
+        return match *self {
+            LexError::IllegalCharacter{ span, .. } => span,
+            LexError::UnterminatedString{ span } => span,
+            LexError::UnterminatedBlockComment{ span } => span,
+            LexError::InvalidEscape{ span, .. } => span,
+        };
+    }
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match *self {
+            LexError::IllegalCharacter{ ch, span } =>
+                write!(f, "{}: illegal character {:?}", span, ch),
+            LexError::UnterminatedString{ span } =>
+                write!(f, "{}: unterminated string literal", span),
+            LexError::UnterminatedBlockComment{ span } =>
+                write!(f, "{}: unterminated block comment", span),
+            LexError::InvalidEscape{ ref sequence, span } =>
+                write!(f, "{}: invalid escape sequence {}", span, sequence),
+        };
+    }
+}
+
+impl Error for LexError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let err = LexError::IllegalCharacter{ ch: '@', span: token::Span::new(4, 5, 1, 5) };
+        assert_eq!(err.to_string(), "1:5: illegal character '@'");
+
+        let err = LexError::UnterminatedString{ span: token::Span::new(8, 14, 2, 3) };
+        assert_eq!(err.to_string(), "2:3: unterminated string literal");
+        assert_eq!(err.span(), token::Span::new(8, 14, 2, 3));
+
+        let err = LexError::InvalidEscape{ sequence: String::from("\\q"), span: token::Span::new(3, 5, 1, 4) };
+        assert_eq!(err.to_string(), "1:4: invalid escape sequence \\q");
+    }
+}
@@ -1,13 +1,19 @@
+use std::char;
 use std::str::Chars;
 use std::iter::Peekable;
+use unicode_xid::UnicodeXID;
+use lexer::error::*;
 use token::*;
 
 pub struct Lexer<'a> {
     pub input:          &'a str,
     pub chars:          Peekable<Chars<'a>>,
-    pub position:       i32,            // current position in input (points to current char)
-    pub read_position:  i32,            // current reading position in input (after current char)
-    pub ch:             Option<char>,   // current char under examination
+    pub ch:             Option<char>,   // current char under examination, None past the end of input
+    pub offset:         usize,          // byte offset of the current char
+    pub line:           usize,          // line of the current char, starting at 1
+    pub column:         usize,          // column of the current char, starting at 1
+    pub errors:         Vec<LexError>,  // problems found so far, drained by the parser
+    finished:           bool,           // whether the iterator has already yielded EOF
 }
 
 impl<'a> Lexer<'a> {
@@ -15,103 +21,257 @@ impl<'a> Lexer<'a> {
         let mut l = Lexer{
             input:          input,
             chars:          input.chars().peekable(),
-            position:       0,
-            read_position:  0,
             ch:             None,
+            offset:         0,
+            line:           1,
+            column:         1,
+            errors:         Vec::new(),
+            finished:       false,
         };
         l.read_char();
         return l;
     }
 
     pub fn read_char(&mut self) {
-        if self.read_position > self.input.len() as i32 {
-            self.ch = None;
-        } else if self.read_position == self.input.len() as i32 {
-            self.ch = Some('\0');
-        } else {
-            self.ch = self.chars.next();
+        self.advance_location();
+        self.ch = self.chars.next();
+    }
+
+    fn advance_location(&mut self) {
+        match self.ch {
+            Some(ch) => {
+                self.offset += ch.len_utf8();
+                if ch == '\n' {
+                    self.line += 1;
+                    self.column = 1;
+                } else {
+                    self.column += 1;
+                }
+            },
+            None => (),
         }
-        self.position = self.read_position;
-        self.read_position += 1;
+    }
+
+    fn at_eof(&self) -> bool {
+        return self.ch.is_none();
+    }
+
+    fn current_span(&self, start: usize, line: usize, column: usize) -> token::Span {
+        let end: usize = match self.ch {
+            Some(ch) => self.offset + ch.len_utf8(),
+            None => self.offset,
+        };
+        return token::Span::new(start, end, line, column);
     }
 
     pub fn peek_char(&mut self) -> Option<char> {
-        if self.read_position > self.input.len() as i32 {
-            return None;
-        } else if self.read_position == self.input.len() as i32 {
-            return Some('\0');
-        } else {
-            match self.chars.peek() {
-                Some(&ch) => return Some(ch),
-                None => return None,
-            }
-        }
+        return self.chars.peek().cloned();
     }
 
-    pub fn next_token(&mut self) -> Option<token::Token> {
-        let tok: Option<token::Token>;
+    // Returns the next token of the input. Once the input is exhausted every
+    // call returns an EOF token.
+    pub fn next_token(&mut self) -> token::Token {
+        let tok: token::Token;
+        let mut consumed: bool = false;    // whether the current char already lies past the token
 
         self.skip_whitespace();
 
+        let start: usize = self.offset;
+        let line: usize = self.line;
+        let column: usize = self.column;
+
         match self.ch {
             Some('=') => match self.peek_char() {
                 Some('=') => {
-                    tok = Some(token::Token::new(token::EQ, String::from("==")));
+                    tok = token::Token::new(token::EQ, String::from("=="));
                     self.read_char();
                 },
-                Some(_) =>  tok = Some(token::Token::new(token::ASSIGN,         '='.to_string())),
-                None =>     tok = Some(token::Token::new(token::ASSIGN,         '='.to_string())),
+                Some(_) =>  tok = token::Token::new(token::ASSIGN,         '='.to_string()),
+                None =>     tok = token::Token::new(token::ASSIGN,         '='.to_string()),
             },
-            Some('+') =>    tok = Some(token::Token::new(token::PLUS,           '+'.to_string())),
+            Some('+') =>    tok = token::Token::new(token::PLUS,           '+'.to_string()),
             Some('!') => match self.peek_char() {
                 Some('=') => {
-                    tok = Some(token::Token::new(token::NOT_EQ, String::from("!=")));
+                    tok = token::Token::new(token::NOT_EQ, String::from("!="));
+                    self.read_char();
+                },
+                Some(_) =>  tok = token::Token::new(token::BANG,           '!'.to_string()),
+                None =>     tok = token::Token::new(token::BANG,           '!'.to_string()),
+            },
+            Some('-') =>    tok = token::Token::new(token::MINUS,          '-'.to_string()),
+            Some('<') => match self.peek_char() {
+                Some('=') => {
+                    tok = token::Token::new(token::LT_EQ, String::from("<="));
+                    self.read_char();
+                },
+                Some(_) =>  tok = token::Token::new(token::LT,             '<'.to_string()),
+                None =>     tok = token::Token::new(token::LT,             '<'.to_string()),
+            },
+            Some('>') => match self.peek_char() {
+                Some('=') => {
+                    tok = token::Token::new(token::GT_EQ, String::from(">="));
+                    self.read_char();
+                },
+                Some(_) =>  tok = token::Token::new(token::GT,             '>'.to_string()),
+                None =>     tok = token::Token::new(token::GT,             '>'.to_string()),
+            },
+            Some('&') => match self.peek_char() {
+                Some('&') => {
+                    tok = token::Token::new(token::AND, String::from("&&"));
+                    self.read_char();
+                },
+                _ =>        tok = self.illegal_character('&', start, line, column),
+            },
+            Some('|') => match self.peek_char() {
+                Some('|') => {
+                    tok = token::Token::new(token::OR, String::from("||"));
                     self.read_char();
                 },
-                Some(_) =>  tok = Some(token::Token::new(token::BANG,           '!'.to_string())),
-                None =>     tok = Some(token::Token::new(token::BANG,           '!'.to_string())),
+                _ =>        tok = self.illegal_character('|', start, line, column),
             },
-            Some('-') =>    tok = Some(token::Token::new(token::MINUS,          '-'.to_string())),
-            Some('<') =>    tok = Some(token::Token::new(token::LT,             '<'.to_string())),
-            Some('>') =>    tok = Some(token::Token::new(token::GT,             '>'.to_string())),
-            Some('/') =>    tok = Some(token::Token::new(token::SLASH,          '/'.to_string())),
-            Some('*') =>    tok = Some(token::Token::new(token::ASTERISK,       '*'.to_string())),
-            Some(',') =>    tok = Some(token::Token::new(token::COMMA,          ','.to_string())),
-            Some(';') =>    tok = Some(token::Token::new(token::SEMICOLON,      ';'.to_string())),
-            Some('(') =>    tok = Some(token::Token::new(token::LPAREN,         '('.to_string())),
-            Some(')') =>    tok = Some(token::Token::new(token::RPAREN,         ')'.to_string())),
-            Some('{') =>    tok = Some(token::Token::new(token::LBRACE,         '{'.to_string())),
-            Some('}') =>    tok = Some(token::Token::new(token::RBRACE,         '}'.to_string())),
-            Some('\0') =>   tok = Some(token::Token::new(token::EOF,            '\0'.to_string())),
-            Some(_) => {
+            Some('/') =>    tok = token::Token::new(token::SLASH,          '/'.to_string()),
+            Some('*') =>    tok = token::Token::new(token::ASTERISK,       '*'.to_string()),
+            Some('%') =>    tok = token::Token::new(token::PERCENT,        '%'.to_string()),
+            Some(',') =>    tok = token::Token::new(token::COMMA,          ','.to_string()),
+            Some(';') =>    tok = token::Token::new(token::SEMICOLON,      ';'.to_string()),
+            Some(':') =>    tok = token::Token::new(token::COLON,          ':'.to_string()),
+            Some('(') =>    tok = token::Token::new(token::LPAREN,         '('.to_string()),
+            Some(')') =>    tok = token::Token::new(token::RPAREN,         ')'.to_string()),
+            Some('{') =>    tok = token::Token::new(token::LBRACE,         '{'.to_string()),
+            Some('}') =>    tok = token::Token::new(token::RBRACE,         '}'.to_string()),
+            Some('[') =>    tok = token::Token::new(token::LBRACKET,       '['.to_string()),
+            Some(']') =>    tok = token::Token::new(token::RBRACKET,       ']'.to_string()),
+            Some('"') => match self.read_string() {
+                Some(value) => tok = token::Token::new(token::STRING, value),
+                None => {
+                    let span: token::Span = token::Span::new(start, self.offset, line, column);
+                    self.errors.push(LexError::UnterminatedString{ span: span });
+                    tok = token::Token::new(token::ILLEGAL, String::from(&self.input[start..self.offset]));
+                    consumed = true;
+                },
+            },
+            Some(ch) => {
                 if is_letter(self.ch) {
                     let ident: String = self.read_identifier();
                     let ttype: token::TokenType = token::lookup_ident(&ident);
-                    tok = Some(token::Token{ ttype: ttype, literal: ident });
-                    return tok;
+                    tok = token::Token::new(ttype, ident);
+                    consumed = true;
                 } else if is_digit(self.ch) {
                     let ident: String = self.read_number();
                     let ttype: token::TokenType = token::INT;
-                    tok = Some(token::Token{ ttype: ttype, literal: ident });
-                    return tok;
+                    tok = token::Token::new(ttype, ident);
+                    consumed = true;
                 } else {
-                    match self.ch {
-                        Some(ch) => tok = Some(token::Token{ ttype: token::ILLEGAL, literal: ch.to_string() }),
-                        None => tok = None,
-                    }
+                    tok = self.illegal_character(ch, start, line, column);
                 }
             },
-            None => tok = None,
+            None => tok = token::Token::new(token::EOF, String::new()),
         }
 
-        self.read_char();
+        if ! consumed {
+            self.read_char();
+        }
 
+        let mut tok: token::Token = tok;
+        tok.span = token::Span::new(start, self.offset, line, column);
         return tok;
     }
 
+    fn illegal_character(&mut self, ch: char, start: usize, line: usize, column: usize) -> token::Token {
+        let span: token::Span = self.current_span(start, line, column);
+        self.errors.push(LexError::IllegalCharacter{ ch: ch, span: span });
+        return token::Token::new(token::ILLEGAL, ch.to_string());
+    }
+
+    // Reads a string literal, with the current char on the opening quote. Gives
+    // up at the end of the line, leaving the newline unread, so that a missing
+    // closing quote does not swallow the rest of the input.
+    pub fn read_string(&mut self) -> Option<String> {
+        let mut buffer: String = String::new();
+
+        loop {
+            self.read_char();
+            if self.at_eof() {
+                return None;
+            }
+
+            match self.ch {
+                Some('"') => return Some(buffer),
+                Some('\n') => return None,
+                Some('\\') => {
+                    let start: usize = self.offset;
+                    let line: usize = self.line;
+                    let column: usize = self.column;
+
+                    self.read_char();
+                    if self.at_eof() {
+                        return None;
+                    }
+
+                    match self.ch {
+                        Some('\n') => return None,
+                        Some('n') => buffer.push('\n'),
+                        Some('t') => buffer.push('\t'),
+                        Some('"') => buffer.push('"'),
+                        Some('\\') => buffer.push('\\'),
+                        Some('u') => match self.read_unicode_escape() {
+                            Ok(ch) => buffer.push(ch),
+                            Err(sequence) => {
+                                let span: token::Span = self.current_span(start, line, column);
+                                self.errors.push(LexError::InvalidEscape{ sequence: sequence, span: span });
+                            },
+                        },
+                        Some(ch) => {
+                            let span: token::Span = self.current_span(start, line, column);
+                            self.errors.push(LexError::InvalidEscape{ sequence: format!("\\{}", ch), span: span });
+                        },
+                        None => return None,
+                    }
+                },
+                Some(ch) => buffer.push(ch),
+                None => return None,
+            }
+        }
+    }
+
+    // Reads the {XXXX} part of a \u{XXXX} escape, with the current char on the u.
+    // Never consumes a closing quote, so a malformed escape cannot swallow the
+    // end of the string.
+    fn read_unicode_escape(&mut self) -> Result<char, String> {
+        let mut sequence: String = String::from("\\u");
+        let mut digits: String = String::new();
+
+        if self.peek_char() != Some('{') {
+            return Err(sequence);
+        }
+        self.read_char();
+        sequence.push('{');
+
+        loop {
+            match self.peek_char() {
+                Some('}') => {
+                    self.read_char();
+                    sequence.push('}');
+                    break;
+                },
+                Some(ch) if ch.is_ascii_hexdigit() && digits.len() < 6 => {
+                    self.read_char();
+                    digits.push(ch);
+                    sequence.push(ch);
+                },
+                _ => return Err(sequence),
+            }
+        }
+
+        return match u32::from_str_radix(&digits, 16).ok().and_then(char::from_u32) {
+            Some(ch) => Ok(ch),
+            None => Err(sequence),
+        };
+    }
+
     pub fn read_identifier(&mut self) -> String {
         let mut buffer: String = String::new();
-        while is_letter(self.ch) {
+        while is_identifier_part(self.ch) {
             match self.ch {
                 Some(ch) => buffer.push(ch),
                 None => return buffer,
@@ -133,16 +293,86 @@ impl<'a> Lexer<'a> {
         return buffer;
     }
 
+    // Skips whitespace as well as // line comments and /* */ block comments.
     pub fn skip_whitespace(&mut self) {
-        while is_whitespace(self.ch) {
+        loop {
+            if is_whitespace(self.ch) {
+                self.read_char();
+            } else if self.ch == Some('/') && self.peek_char() == Some('/') {
+                self.skip_line_comment();
+            } else if self.ch == Some('/') && self.peek_char() == Some('*') {
+                self.skip_block_comment();
+            } else {
+                return;
+            }
+        }
+    }
+
+    fn skip_line_comment(&mut self) {
+        while ! self.at_eof() && self.ch != Some('\n') {
+            self.read_char();
+        }
+    }
+
+    // Block comments nest, so /* a /* b */ c */ is a single comment.
+    fn skip_block_comment(&mut self) {
+        let start: usize = self.offset;
+        let line: usize = self.line;
+        let column: usize = self.column;
+        let mut depth: usize = 0;
+
+        loop {
+            if self.at_eof() {
+                let span: token::Span = token::Span::new(start, self.offset, line, column);
+                self.errors.push(LexError::UnterminatedBlockComment{ span: span });
+                return;
+            }
+
+            if self.ch == Some('/') && self.peek_char() == Some('*') {
+                self.read_char();
+                depth += 1;
+            } else if self.ch == Some('*') && self.peek_char() == Some('/') {
+                self.read_char();
+                depth -= 1;
+                if depth == 0 {
+                    self.read_char();
+                    return;
+                }
+            }
             self.read_char();
         }
     }
 }
 
+// Yields every token of the input followed by exactly one EOF token.
+impl<'a> Iterator for Lexer<'a> {
+    type Item = token::Token;
+
+    fn next(&mut self) -> Option<token::Token> {
+        if self.finished {
+            return None;
+        }
+
+        let tok: token::Token = self.next_token();
+        if tok.ttype == token::EOF {
+            self.finished = true;
+        }
+        return Some(tok);
+    }
+}
+
+// Identifiers follow Unicode's XID_Start/XID_Continue properties, with the
+// underscore allowed at the start as well.
 pub fn is_letter(ch: Option<char>) -> bool {
     match ch {
-        Some(ord) => return 'a' <= ord && ord <= 'z' || 'A' <= ord && ord <= 'Z' || ord == '_',
+        Some(ord) => return ord == '_' || UnicodeXID::is_xid_start(ord),
+        None => return false,
+    }
+}
+
+pub fn is_identifier_part(ch: Option<char>) -> bool {
+    match ch {
+        Some(ord) => return UnicodeXID::is_xid_continue(ord),
         None => return false,
     }
 }
@@ -175,58 +405,444 @@ let add = fn(x, y) {
 };
 
 let result = add(five, ten);
+[1, 2];
+{\"foo\": \"bar\"}
 ";
 
         let tests = [
-            token::Token{ ttype:    token::LET,         literal:    String::from("let")         },
-            token::Token{ ttype:    token::IDENT,       literal:    String::from("five")        },
-            token::Token{ ttype:    token::ASSIGN,      literal:    String::from("=")           },
-            token::Token{ ttype:    token::INT,         literal:    String::from("5")           },
-            token::Token{ ttype:    token::SEMICOLON,   literal:    String::from(";")           },
-            token::Token{ ttype:    token::LET,         literal:    String::from("let")         },
-            token::Token{ ttype:    token::IDENT,       literal:    String::from("ten")         },
-            token::Token{ ttype:    token::ASSIGN,      literal:    String::from("=")           },
-            token::Token{ ttype:    token::INT,         literal:    String::from("10")          },
-            token::Token{ ttype:    token::SEMICOLON,   literal:    String::from(";")           },
-            token::Token{ ttype:    token::LET,         literal:    String::from("let")         },
-            token::Token{ ttype:    token::IDENT,       literal:    String::from("add")         },
-            token::Token{ ttype:    token::ASSIGN,      literal:    String::from("=")           },
-            token::Token{ ttype:    token::FUNCTION,    literal:    String::from("fn")          },
-            token::Token{ ttype:    token::LPAREN,      literal:    String::from("(")           },
-            token::Token{ ttype:    token::IDENT,       literal:    String::from("x")           },
-            token::Token{ ttype:    token::COMMA,       literal:    String::from(",")           },
-            token::Token{ ttype:    token::IDENT,       literal:    String::from("y")           },
-            token::Token{ ttype:    token::RPAREN,      literal:    String::from(")")           },
-            token::Token{ ttype:    token::LBRACE,      literal:    String::from("{")           },
-            token::Token{ ttype:    token::IDENT,       literal:    String::from("x")           },
-            token::Token{ ttype:    token::PLUS,        literal:    String::from("+")           },
-            token::Token{ ttype:    token::IDENT,       literal:    String::from("y")           },
-            token::Token{ ttype:    token::SEMICOLON,   literal:    String::from(";")           },
-            token::Token{ ttype:    token::RBRACE,      literal:    String::from("}")           },
-            token::Token{ ttype:    token::SEMICOLON,   literal:    String::from(";")           },
-            token::Token{ ttype:    token::LET,         literal:    String::from("let")         },
-            token::Token{ ttype:    token::IDENT,       literal:    String::from("result")      },
-            token::Token{ ttype:    token::ASSIGN,      literal:    String::from("=")           },
-            token::Token{ ttype:    token::IDENT,       literal:    String::from("add")         },
-            token::Token{ ttype:    token::LPAREN,      literal:    String::from("(")           },
-            token::Token{ ttype:    token::IDENT,       literal:    String::from("five")        },
-            token::Token{ ttype:    token::COMMA,       literal:    String::from(",")           },
-            token::Token{ ttype:    token::IDENT,       literal:    String::from("ten")         },
-            token::Token{ ttype:    token::RPAREN,      literal:    String::from(")")           },
-            token::Token{ ttype:    token::SEMICOLON,   literal:    String::from(";")           },
-            token::Token{ ttype:    token::EOF,         literal:    String::from("\0")          },
+            token::Token::new(token::LET, String::from("let")),
+            token::Token::new(token::IDENT, String::from("five")),
+            token::Token::new(token::ASSIGN, String::from("=")),
+            token::Token::new(token::INT, String::from("5")),
+            token::Token::new(token::SEMICOLON, String::from(";")),
+            token::Token::new(token::LET, String::from("let")),
+            token::Token::new(token::IDENT, String::from("ten")),
+            token::Token::new(token::ASSIGN, String::from("=")),
+            token::Token::new(token::INT, String::from("10")),
+            token::Token::new(token::SEMICOLON, String::from(";")),
+            token::Token::new(token::LET, String::from("let")),
+            token::Token::new(token::IDENT, String::from("add")),
+            token::Token::new(token::ASSIGN, String::from("=")),
+            token::Token::new(token::FUNCTION, String::from("fn")),
+            token::Token::new(token::LPAREN, String::from("(")),
+            token::Token::new(token::IDENT, String::from("x")),
+            token::Token::new(token::COMMA, String::from(",")),
+            token::Token::new(token::IDENT, String::from("y")),
+            token::Token::new(token::RPAREN, String::from(")")),
+            token::Token::new(token::LBRACE, String::from("{")),
+            token::Token::new(token::IDENT, String::from("x")),
+            token::Token::new(token::PLUS, String::from("+")),
+            token::Token::new(token::IDENT, String::from("y")),
+            token::Token::new(token::SEMICOLON, String::from(";")),
+            token::Token::new(token::RBRACE, String::from("}")),
+            token::Token::new(token::SEMICOLON, String::from(";")),
+            token::Token::new(token::LET, String::from("let")),
+            token::Token::new(token::IDENT, String::from("result")),
+            token::Token::new(token::ASSIGN, String::from("=")),
+            token::Token::new(token::IDENT, String::from("add")),
+            token::Token::new(token::LPAREN, String::from("(")),
+            token::Token::new(token::IDENT, String::from("five")),
+            token::Token::new(token::COMMA, String::from(",")),
+            token::Token::new(token::IDENT, String::from("ten")),
+            token::Token::new(token::RPAREN, String::from(")")),
+            token::Token::new(token::SEMICOLON, String::from(";")),
+            token::Token::new(token::LBRACKET, String::from("[")),
+            token::Token::new(token::INT, String::from("1")),
+            token::Token::new(token::COMMA, String::from(",")),
+            token::Token::new(token::INT, String::from("2")),
+            token::Token::new(token::RBRACKET, String::from("]")),
+            token::Token::new(token::SEMICOLON, String::from(";")),
+            token::Token::new(token::LBRACE, String::from("{")),
+            token::Token::new(token::STRING, String::from("foo")),
+            token::Token::new(token::COLON, String::from(":")),
+            token::Token::new(token::STRING, String::from("bar")),
+            token::Token::new(token::RBRACE, String::from("}")),
+            token::Token::new(token::EOF, String::new()),
         ];
 
-        let mut l = Lexer::new(&input);
+        let mut l = Lexer::new(input);
 
         let mut i = 0;
         for test in tests.iter() {
-            match l.next_token() {
-                Some(tok) => assert_eq!(&tok, test, "tests[{}]", i),
+            match l.next() {
+                Some(tok) => {
+                    assert_eq!(tok.ttype, test.ttype, "tests[{}] - tokentype wrong", i);
+                    assert_eq!(tok.literal, test.literal, "tests[{}] - literal wrong", i);
+                },
                 None => assert!(false),
             }
             i += 1;
         }
     }
-}
 
+    #[test]
+    fn test_token_spans() {
+        let input = "let x = 10;
+  x != y;
+";
+
+        let tests = [
+            (token::LET,        token::Span::new(0, 3, 1, 1)),
+            (token::IDENT,      token::Span::new(4, 5, 1, 5)),
+            (token::ASSIGN,     token::Span::new(6, 7, 1, 7)),
+            (token::INT,        token::Span::new(8, 10, 1, 9)),
+            (token::SEMICOLON,  token::Span::new(10, 11, 1, 11)),
+            (token::IDENT,      token::Span::new(14, 15, 2, 3)),
+            (token::NOT_EQ,     token::Span::new(16, 18, 2, 5)),
+            (token::IDENT,      token::Span::new(19, 20, 2, 8)),
+            (token::SEMICOLON,  token::Span::new(20, 21, 2, 9)),
+            (token::EOF,        token::Span::new(22, 22, 3, 1)),
+        ];
+
+        let mut l = Lexer::new(input);
+
+        let mut i = 0;
+        for &(ttype, span) in tests.iter() {
+            match l.next() {
+                Some(tok) => {
+                    assert_eq!(tok.ttype, ttype, "tests[{}] - tokentype wrong", i);
+                    assert_eq!(tok.span, span, "tests[{}] - span wrong", i);
+                },
+                None => assert!(false),
+            }
+            i += 1;
+        }
+    }
+
+    #[test]
+    fn test_string_literals() {
+        let input = r#""foobar" "foo bar" "a\nb\t\"c\"\\" "\u{48}\u{e9}\u{1F600}" """#;
+
+        let tests = [
+            token::Token::new(token::STRING,    String::from("foobar")),
+            token::Token::new(token::STRING,    String::from("foo bar")),
+            token::Token::new(token::STRING,    String::from("a\nb\t\"c\"\\")),
+            token::Token::new(token::STRING,    String::from("H\u{e9}\u{1F600}")),
+            token::Token::new(token::STRING,    String::new()),
+            token::Token::new(token::EOF,       String::new()),
+        ];
+
+        let mut l = Lexer::new(input);
+
+        let mut i = 0;
+        for test in tests.iter() {
+            match l.next() {
+                Some(tok) => {
+                    assert_eq!(tok.ttype, test.ttype, "tests[{}] - tokentype wrong", i);
+                    assert_eq!(tok.literal, test.literal, "tests[{}] - literal wrong", i);
+                },
+                None => assert!(false),
+            }
+            i += 1;
+        }
+        assert_eq!(l.errors, vec![]);
+    }
+
+    #[test]
+    fn test_string_span() {
+        let mut l = Lexer::new("let s = \"hi\";");
+
+        let tokens: Vec<token::Token> = l.by_ref().collect();
+
+        assert_eq!(tokens[3].ttype, token::STRING);
+        assert_eq!(tokens[3].span, token::Span::new(8, 12, 1, 9));
+        assert_eq!(tokens[4].ttype, token::SEMICOLON);
+    }
+
+    #[test]
+    fn test_unterminated_string() {
+        let mut l = Lexer::new("let s = 1;\nlet t = \"abc;\nlet u = 2;\n");
+
+        let tokens: Vec<token::Token> = l.by_ref().collect();
+
+        assert_eq!(tokens[8].ttype, token::ILLEGAL);
+        assert_eq!(tokens[8].literal, "\"abc;");
+        assert_eq!(tokens[8].span, token::Span::new(19, 24, 2, 9));
+        assert_eq!(tokens[9].ttype, token::LET);
+        assert_eq!(tokens[9].span, token::Span::new(25, 28, 3, 1));
+        assert_eq!(tokens[13].ttype, token::SEMICOLON);
+        assert_eq!(tokens[14].ttype, token::EOF);
+        assert_eq!(l.errors, vec![LexError::UnterminatedString{ span: token::Span::new(19, 24, 2, 9) }]);
+        assert_eq!(l.errors[0].to_string(), "2:9: unterminated string literal");
+
+        let mut l = Lexer::new("\"abc\\\n@");
+
+        let tokens: Vec<token::Token> = l.by_ref().collect();
+
+        assert_eq!(tokens[0].ttype, token::ILLEGAL);
+        assert_eq!(tokens[0].literal, "\"abc\\");
+        assert_eq!(tokens[1].ttype, token::ILLEGAL);
+        assert_eq!(tokens[1].literal, "@");
+        assert_eq!(l.errors, vec![
+            LexError::UnterminatedString{ span: token::Span::new(0, 5, 1, 1) },
+            LexError::IllegalCharacter{ ch: '@', span: token::Span::new(6, 7, 2, 1) },
+        ]);
+    }
+
+    #[test]
+    fn test_invalid_escapes() {
+        let tests: [(&str, &str, &str); 5] = [
+            (r#""a\qb""#,           "ab",   "1:3: invalid escape sequence \\q"),
+            (r#""\u{110000}""#,     "",     "1:2: invalid escape sequence \\u{110000}"),
+            (r#""\u{zz}""#,         "zz}",  "1:2: invalid escape sequence \\u{"),
+            (r#""\u41""#,           "41",   "1:2: invalid escape sequence \\u"),
+            (r#""\u{1234567}""#,    "7}",   "1:2: invalid escape sequence \\u{123456"),
+        ];
+
+        let mut i = 0;
+        for &(input, literal, message) in tests.iter() {
+            let mut l = Lexer::new(input);
+
+            match l.next() {
+                Some(tok) => {
+                    assert_eq!(tok.ttype, token::STRING, "tests[{}] - tokentype wrong", i);
+                    assert_eq!(tok.literal, literal, "tests[{}] - literal wrong", i);
+                },
+                None => assert!(false),
+            }
+            assert_eq!(l.errors.len(), 1, "tests[{}] - wrong number of errors", i);
+            assert_eq!(l.errors[0].to_string(), message, "tests[{}]", i);
+
+            i += 1;
+        }
+    }
+
+    #[test]
+    fn test_illegal_character() {
+        let mut l = Lexer::new("a @ b");
+
+        l.next_token();
+        match l.next() {
+            Some(tok) => {
+                assert_eq!(tok.ttype, token::ILLEGAL);
+                assert_eq!(tok.literal, "@");
+            },
+            None => assert!(false),
+        }
+        assert_eq!(l.errors, vec![LexError::IllegalCharacter{ ch: '@', span: token::Span::new(2, 3, 1, 3) }]);
+    }
+
+    #[test]
+    fn test_comments() {
+        let input = "// leading comment
+let five = 5; // trailing comment
+/* block
+   comment */ let ten = 10;
+/* outer /* nested */ still outer */ five / ten; /**/ //
+// final comment";
+
+        let tests = [
+            token::Token::new(token::LET,           String::from("let")),
+            token::Token::new(token::IDENT,         String::from("five")),
+            token::Token::new(token::ASSIGN,        String::from("=")),
+            token::Token::new(token::INT,           String::from("5")),
+            token::Token::new(token::SEMICOLON,     String::from(";")),
+            token::Token::new(token::LET,           String::from("let")),
+            token::Token::new(token::IDENT,         String::from("ten")),
+            token::Token::new(token::ASSIGN,        String::from("=")),
+            token::Token::new(token::INT,           String::from("10")),
+            token::Token::new(token::SEMICOLON,     String::from(";")),
+            token::Token::new(token::IDENT,         String::from("five")),
+            token::Token::new(token::SLASH,         String::from("/")),
+            token::Token::new(token::IDENT,         String::from("ten")),
+            token::Token::new(token::SEMICOLON,     String::from(";")),
+            token::Token::new(token::EOF,           String::new()),
+        ];
+
+        let mut l = Lexer::new(input);
+
+        let mut i = 0;
+        for test in tests.iter() {
+            match l.next() {
+                Some(tok) => {
+                    assert_eq!(tok.ttype, test.ttype, "tests[{}] - tokentype wrong", i);
+                    assert_eq!(tok.literal, test.literal, "tests[{}] - literal wrong", i);
+                },
+                None => assert!(false),
+            }
+            i += 1;
+        }
+        assert_eq!(l.errors, vec![]);
+    }
+
+    #[test]
+    fn test_comment_spans() {
+        let mut l = Lexer::new("/* a\n b */ x // y\nz");
+
+        let x = l.next_token();
+        assert_eq!(x.span, token::Span::new(11, 12, 2, 7));
+        let z = l.next_token();
+        assert_eq!(z.span, token::Span::new(18, 19, 3, 1));
+    }
+
+    #[test]
+    fn test_unterminated_block_comment() {
+        let mut l = Lexer::new("let x = 1;\n  /* outer /* inner */\nlet y = 2;");
+
+        let tokens: Vec<token::Token> = l.by_ref().collect();
+
+        assert_eq!(tokens.len(), 6);
+        assert_eq!(tokens[5].ttype, token::EOF);
+        assert_eq!(l.errors, vec![LexError::UnterminatedBlockComment{ span: token::Span::new(13, 44, 2, 3) }]);
+        assert_eq!(l.errors[0].to_string(), "2:3: unterminated block comment");
+    }
+
+    #[test]
+    fn test_two_character_operators() {
+        let input = "a <= b >= c < d > e && f || g % h & | =";
+
+        let tests = [
+            token::Token::new(token::IDENT,         String::from("a")),
+            token::Token::new(token::LT_EQ,         String::from("<=")),
+            token::Token::new(token::IDENT,         String::from("b")),
+            token::Token::new(token::GT_EQ,         String::from(">=")),
+            token::Token::new(token::IDENT,         String::from("c")),
+            token::Token::new(token::LT,            String::from("<")),
+            token::Token::new(token::IDENT,         String::from("d")),
+            token::Token::new(token::GT,            String::from(">")),
+            token::Token::new(token::IDENT,         String::from("e")),
+            token::Token::new(token::AND,           String::from("&&")),
+            token::Token::new(token::IDENT,         String::from("f")),
+            token::Token::new(token::OR,            String::from("||")),
+            token::Token::new(token::IDENT,         String::from("g")),
+            token::Token::new(token::PERCENT,       String::from("%")),
+            token::Token::new(token::IDENT,         String::from("h")),
+            token::Token::new(token::ILLEGAL,       String::from("&")),
+            token::Token::new(token::ILLEGAL,       String::from("|")),
+            token::Token::new(token::ASSIGN,        String::from("=")),
+            token::Token::new(token::EOF,           String::new()),
+        ];
+
+        let mut l = Lexer::new(input);
+
+        let mut i = 0;
+        for test in tests.iter() {
+            match l.next() {
+                Some(tok) => {
+                    assert_eq!(tok.ttype, test.ttype, "tests[{}] - tokentype wrong", i);
+                    assert_eq!(tok.literal, test.literal, "tests[{}] - literal wrong", i);
+                },
+                None => assert!(false),
+            }
+            i += 1;
+        }
+        assert_eq!(l.errors.len(), 2);
+    }
+
+    #[test]
+    fn test_iterator_ends_after_single_eof() {
+        let mut l = Lexer::new("let x = 5;");
+
+        let types: Vec<token::TokenType> = l.by_ref().map(|tok| tok.ttype).collect();
+        assert_eq!(types, vec![token::LET, token::IDENT, token::ASSIGN, token::INT, token::SEMICOLON, token::EOF]);
+        assert!(l.next().is_none());
+        assert!(l.next().is_none());
+
+        let tokens: Vec<token::Token> = Lexer::new("").collect();
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].ttype, token::EOF);
+        assert_eq!(tokens[0].span, token::Span::new(0, 0, 1, 1));
+    }
+
+    #[test]
+    fn test_next_token_repeats_eof() {
+        let mut l = Lexer::new("x");
+
+        assert_eq!(l.next_token().ttype, token::IDENT);
+        for _ in 0..3 {
+            let tok = l.next_token();
+            assert_eq!(tok.ttype, token::EOF);
+            assert_eq!(tok.literal, "");
+            assert_eq!(tok.span, token::Span::new(1, 1, 1, 2));
+        }
+    }
+
+    #[test]
+    fn test_iterator_adapters() {
+        let idents: Vec<String> = Lexer::new("let add = fn(x, y) { x + y; };")
+            .filter(|tok| tok.ttype == token::IDENT)
+            .map(|tok| tok.literal)
+            .collect();
+        assert_eq!(idents, vec!["add", "x", "y", "x", "y"]);
+
+        let mut tokens = Lexer::new("a == b").peekable();
+        assert_eq!(tokens.peek().map(|tok| tok.ttype), Some(token::IDENT));
+        assert_eq!(tokens.count(), 4);
+    }
+
+    #[test]
+    fn test_nul_characters_in_source() {
+        let mut l = Lexer::new("a \0 b \"c\0d\"");
+
+        let tokens: Vec<token::Token> = l.by_ref().collect();
+        let types: Vec<token::TokenType> = tokens.iter().map(|tok| tok.ttype).collect();
+        assert_eq!(types, vec![token::IDENT, token::ILLEGAL, token::IDENT, token::STRING, token::EOF]);
+        assert_eq!(tokens[3].literal, "c\0d");
+        assert_eq!(l.errors, vec![LexError::IllegalCharacter{ ch: '\0', span: token::Span::new(2, 3, 1, 3) }]);
+    }
+
+    #[test]
+    fn test_unicode_identifiers() {
+        let tests: [(&str, &str); 12] = [
+            ("café",            "Latin"),
+            ("straße_2",        "Latin"),
+            ("λ",               "Greek"),
+            ("αβγ",             "Greek"),
+            ("привет",          "Cyrillic"),
+            ("שלום",            "Hebrew"),
+            ("مرحبا",           "Arabic"),
+            ("नमस्ते",            "Devanagari"),
+            ("变量",            "Han"),
+            ("名前ですか",      "Japanese"),
+            ("변수",            "Hangul"),
+            ("_x1",             "ASCII"),
+        ];
+
+        for (i, &(ident, script)) in tests.iter().enumerate() {
+            let input = format!("let {} = {};", ident, ident);
+            let tokens: Vec<token::Token> = Lexer::new(&input).collect();
+
+            let types: Vec<token::TokenType> = tokens.iter().map(|tok| tok.ttype).collect();
+            assert_eq!(types, vec![token::LET, token::IDENT, token::ASSIGN, token::IDENT, token::SEMICOLON, token::EOF], "tests[{}] - {}", i, script);
+            assert_eq!(tokens[1].literal, ident, "tests[{}] - {}", i, script);
+            assert_eq!(tokens[3].literal, ident, "tests[{}] - {}", i, script);
+
+            // spans are byte offsets into the input, so they slice out the identifier
+            assert_eq!(&input[tokens[1].span.start..tokens[1].span.end], ident, "tests[{}] - {}", i, script);
+            assert_eq!(&input[tokens[3].span.start..tokens[3].span.end], ident, "tests[{}] - {}", i, script);
+            assert_eq!(tokens[5].span.start, input.len(), "tests[{}] - {}", i, script);
+        }
+    }
+
+    #[test]
+    fn test_non_ascii_strings_and_comments() {
+        let input = "// コメント ✓\nlet s = \"héllo, мир 🌍\"; /* 注释 */ s + \"!\"";
+
+        let mut l = Lexer::new(input);
+        let tokens: Vec<token::Token> = l.by_ref().collect();
+
+        let types: Vec<token::TokenType> = tokens.iter().map(|tok| tok.ttype).collect();
+        assert_eq!(types, vec![
+            token::LET, token::IDENT, token::ASSIGN, token::STRING, token::SEMICOLON,
+            token::IDENT, token::PLUS, token::STRING, token::EOF,
+        ]);
+        assert_eq!(tokens[3].literal, "héllo, мир 🌍");
+        assert_eq!(&input[tokens[3].span.start..tokens[3].span.end], "\"héllo, мир 🌍\"");
+        assert_eq!(tokens[3].span.line, 2);
+        assert_eq!(tokens[3].span.column, 9);
+        assert_eq!(tokens[5].span.column, 34);
+        assert_eq!(tokens[8].span.start, input.len());
+        assert_eq!(l.errors, vec![]);
+    }
+
+    #[test]
+    fn test_non_identifier_symbols_are_illegal() {
+        let mut l = Lexer::new("a 🌍 1x");
+
+        let tokens: Vec<token::Token> = l.by_ref().collect();
+        let types: Vec<token::TokenType> = tokens.iter().map(|tok| tok.ttype).collect();
+        assert_eq!(types, vec![token::IDENT, token::ILLEGAL, token::INT, token::IDENT, token::EOF]);
+        assert_eq!(tokens[1].literal, "🌍");
+        assert_eq!(l.errors, vec![LexError::IllegalCharacter{ ch: '🌍', span: token::Span::new(2, 6, 1, 3) }]);
+    }
+}
@@ -0,0 +1,34 @@
+// The interpreter follows the structure of the Go reference implementation closely:
+// package-style `x/x.rs` modules, upper-case token and opcode names, explicit
+// returns, typed late-initialized bindings, `new` constructors, `match` where
+// `if let` would do and `assert!(false, ...)` in place of t.Fatalf. Silence the
+// clippy lints that would fight that style.
+#![allow(
+    clippy::assertions_on_constants,
+    clippy::collapsible_match,
+    clippy::explicit_counter_loop,
+    clippy::len_zero,
+    clippy::manual_range_contains,
+    clippy::match_like_matches_macro,
+    clippy::module_inception,
+    clippy::needless_late_init,
+    clippy::needless_return,
+    clippy::new_without_default,
+    clippy::redundant_field_names,
+    clippy::single_match,
+    clippy::upper_case_acronyms,
+)]
+
+extern crate unicode_xid;
+
+pub mod token;
+pub mod lexer;
+pub mod ast;
+pub mod parser;
+pub mod object;
+pub mod evaluator;
+pub mod code;
+pub mod compiler;
+pub mod vm;
+pub mod artifact;
+pub mod repl;
@@ -1,5 +1,162 @@
-mod token;
-mod lexer;
-mod ast;
-mod parser;
+// The REPL and script runner on top of the monkey library. Like the library it
+// keeps the explicit returns of the Go reference implementation.
+#![allow(clippy::needless_return)]
 
+extern crate monkey;
+
+use monkey::{artifact, ast, code, compiler, evaluator, lexer, parser, repl};
+
+use std::env;
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::process;
+use std::thread;
+
+static USAGE: &str = "usage: monkey [FILE | --vm FILE | --compile FILE -o OUT | --disassemble FILE]";
+
+// The evaluator recurses on the native stack, which on the main thread is too
+// small for as many calls as it allows.
+fn main() {
+    let interpreter = thread::Builder::new()
+        .stack_size(evaluator::evaluator::NATIVE_STACK_SIZE)
+        .spawn(run)
+        .expect("could not start the interpreter thread");
+    match interpreter.join() {
+        Ok(()) => (),
+        Err(_) => process::exit(101),
+    }
+}
+
+fn run() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(|arg| arg.as_str()).collect();
+
+    let result: Result<bool, String> = match args.as_slice() {
+        [] => run_repl().map(|()| true),
+        ["--disassemble", path] => disassemble_file(path).and_then(|listing| {
+            let stdout = io::stdout();
+            let result: io::Result<()> = stdout.lock().write_all(listing.as_bytes());
+            return result.map(|()| true).map_err(|err| err.to_string());
+        }),
+        ["--compile", path, "-o", out] => compile_file(path, out).map(|()| true),
+        ["--vm", path] => run_file_on_vm(path),
+        [path] if ! path.starts_with('-') => run_file(path),
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        },
+    };
+
+    match result {
+        Ok(true) => (),
+        Ok(false) => process::exit(1),
+        Err(message) => {
+            eprintln!("monkey: {}", message);
+            process::exit(1);
+        },
+    }
+}
+
+fn run_repl() -> Result<(), String> {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let mut output = stdout.lock();
+
+    let result: io::Result<()> = writeln!(output, "This is the Monkey programming language!")
+        .and_then(|()| writeln!(output, "Feel free to type in commands"))
+        .and_then(|()| repl::repl::start(&mut stdin.lock(), &mut output));
+    return result.map_err(|err| err.to_string());
+}
+
+// Runs a Monkey script with the evaluator, or a precompiled file on the VM.
+// Runtime errors print a traceback to stderr; returns whether the program ran
+// to completion.
+fn run_file(path: &str) -> Result<bool, String> {
+    let data: Vec<u8> = read_file(path)?;
+    if data.starts_with(artifact::artifact::MAGIC) {
+        return run_on_vm(path, data);
+    }
+
+    let source: String = source_text(path, data)?;
+    let stderr = io::stderr();
+    return repl::repl::run_script(&source, &mut stderr.lock()).map_err(|err| err.to_string());
+}
+
+// Runs a Monkey script or a precompiled file on the VM.
+fn run_file_on_vm(path: &str) -> Result<bool, String> {
+    let data: Vec<u8> = read_file(path)?;
+    return run_on_vm(path, data);
+}
+
+fn run_on_vm(path: &str, data: Vec<u8>) -> Result<bool, String> {
+    let (bytecode, source) = bytecode_from(path, data)?;
+    let stderr = io::stderr();
+    return repl::repl::run_bytecode(bytecode, source.as_deref(), &mut stderr.lock()).map_err(|err| err.to_string());
+}
+
+// Compiles a Monkey script into a precompiled file, with debug info so that
+// tracebacks and listings of it still show source positions.
+fn compile_file(path: &str, out: &str) -> Result<(), String> {
+    let source: String = source_text(path, read_file(path)?)?;
+    let bytecode: compiler::compiler::Bytecode = compile_source(path, &source)?;
+    return match artifact::artifact::save(out, &bytecode, true) {
+        Ok(()) => Ok(()),
+        Err(err) => Err(format!("{}: {}", out, err)),
+    };
+}
+
+// Lists the bytecode of a precompiled file, or compiles Monkey source and
+// lists it alongside the source lines.
+fn disassemble_file(path: &str) -> Result<String, String> {
+    let (bytecode, source) = bytecode_from(path, read_file(path)?)?;
+    return Ok(code::disassembler::disassemble(&bytecode, source.as_deref()));
+}
+
+fn read_file(path: &str) -> Result<Vec<u8>, String> {
+    return match fs::read(path) {
+        Ok(data) => Ok(data),
+        Err(err) => Err(format!("{}: {}", path, err)),
+    };
+}
+
+fn source_text(path: &str, data: Vec<u8>) -> Result<String, String> {
+    return match String::from_utf8(data) {
+        Ok(source) => Ok(source),
+        Err(_) => Err(format!("{}: not valid UTF-8", path)),
+    };
+}
+
+// Decodes a precompiled file, or compiles Monkey source, which is returned as
+// well so that it can be quoted.
+fn bytecode_from(path: &str, data: Vec<u8>) -> Result<(compiler::compiler::Bytecode, Option<String>), String> {
+    if data.starts_with(artifact::artifact::MAGIC) {
+        return match artifact::artifact::decode(&data) {
+            Ok(bytecode) => Ok((bytecode, None)),
+            Err(err) => Err(format!("{}: {}", path, err)),
+        };
+    }
+
+    let source: String = source_text(path, data)?;
+    let bytecode: compiler::compiler::Bytecode = compile_source(path, &source)?;
+    return Ok((bytecode, Some(source)));
+}
+
+fn compile_source(path: &str, source: &str) -> Result<compiler::compiler::Bytecode, String> {
+    let mut l = lexer::lexer::Lexer::new(source);
+    let mut p = parser::parser::Parser::new(&mut l);
+    let program: ast::ast::Program = match p.parse_program() {
+        Ok(program) => program,
+        Err(errors) => {
+            let messages: Vec<String> = errors.iter().map(|err| format!("{}:{}", path, err)).collect();
+            return Err(messages.join("\n"));
+        },
+    };
+
+    let mut c = compiler::compiler::Compiler::new();
+    match c.compile_program(&program) {
+        Ok(()) => (),
+        Err(err) => return Err(format!("{}:{}", path, err)),
+    }
+    return Ok(c.bytecode());
+}
@@ -0,0 +1,155 @@
+use std::io;
+use std::io::Write;
+use object::*;
+
+// Host functions available to every program. They are looked up by name once
+// an identifier is not found in the environment, so a script can still shadow
+// them with its own bindings. Compiled code refers to them by their index in
+// this table, so new builtins go at the end.
+pub static BUILTINS: &[object::Builtin] = &[
+    object::Builtin{ name: "len",   func: len },
+    object::Builtin{ name: "first", func: first },
+    object::Builtin{ name: "last",  func: last },
+    object::Builtin{ name: "rest",  func: rest },
+    object::Builtin{ name: "push",  func: push },
+    object::Builtin{ name: "puts",  func: puts },
+];
+
+pub fn lookup(name: &str) -> Option<object::Object> {
+    return BUILTINS.iter().find(|b| b.name == name).map(|b| object::Object::Builtin(*b));
+}
+
+fn new_error(kind: error::ErrorKind, message: String) -> object::Object {
+    return object::Object::Error(error::RuntimeError::new(kind, message));
+}
+
+fn check_arg_count(args: &[object::Object], want: usize) -> Option<object::Object> {
+    if args.len() != want {
+        return Some(new_error(error::ErrorKind::WrongArgumentCount, format!("wrong number of arguments: want={}, got={}", want, args.len())));
+    }
+    return None;
+}
+
+fn len(args: Vec<object::Object>) -> object::Object {
+    match check_arg_count(&args, 1) {
+        Some(err) => return err,
+        None => (),
+    }
+
+    return match args[0] {
+        object::Object::String(ref value) => object::Object::Integer(value.chars().count() as i64),
+        object::Object::Array(ref elements) => object::Object::Integer(elements.len() as i64),
+        object::Object::Hash(ref pairs) => object::Object::Integer(pairs.len() as i64),
+        ref other => new_error(error::ErrorKind::InvalidArgument, format!("argument to `len` not supported, got {}", other.object_type())),
+    };
+}
+
+fn first(args: Vec<object::Object>) -> object::Object {
+    match check_arg_count(&args, 1) {
+        Some(err) => return err,
+        None => (),
+    }
+
+    return match args[0] {
+        object::Object::Array(ref elements) => elements.first().cloned().unwrap_or(object::Object::Null),
+        ref other => new_error(error::ErrorKind::InvalidArgument, format!("argument to `first` must be {}, got {}", object::ARRAY_OBJ, other.object_type())),
+    };
+}
+
+fn last(args: Vec<object::Object>) -> object::Object {
+    match check_arg_count(&args, 1) {
+        Some(err) => return err,
+        None => (),
+    }
+
+    return match args[0] {
+        object::Object::Array(ref elements) => elements.last().cloned().unwrap_or(object::Object::Null),
+        ref other => new_error(error::ErrorKind::InvalidArgument, format!("argument to `last` must be {}, got {}", object::ARRAY_OBJ, other.object_type())),
+    };
+}
+
+fn rest(args: Vec<object::Object>) -> object::Object {
+    match check_arg_count(&args, 1) {
+        Some(err) => return err,
+        None => (),
+    }
+
+    return match args[0] {
+        object::Object::Array(ref elements) => {
+            if elements.is_empty() {
+                object::Object::Null
+            } else {
+                object::Object::Array(elements[1..].to_vec())
+            }
+        },
+        ref other => new_error(error::ErrorKind::InvalidArgument, format!("argument to `rest` must be {}, got {}", object::ARRAY_OBJ, other.object_type())),
+    };
+}
+
+fn push(args: Vec<object::Object>) -> object::Object {
+    match check_arg_count(&args, 2) {
+        Some(err) => return err,
+        None => (),
+    }
+
+    return match args[0] {
+        object::Object::Array(ref elements) => {
+            let mut elements: Vec<object::Object> = elements.clone();
+            elements.push(args[1].clone());
+            object::Object::Array(elements)
+        },
+        ref other => new_error(error::ErrorKind::InvalidArgument, format!("argument to `push` must be {}, got {}", object::ARRAY_OBJ, other.object_type())),
+    };
+}
+
+fn puts(args: Vec<object::Object>) -> object::Object {
+    let stdout = io::stdout();
+    let mut output = stdout.lock();
+    for arg in args.iter() {
+        match writeln!(output, "{}", arg.inspect()) {
+            Ok(()) => (),
+            Err(err) => return new_error(error::ErrorKind::WriteFailed, format!("could not write output of `puts`: {}", err)),
+        }
+    }
+    return object::Object::Null;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(name: &str, args: Vec<object::Object>) -> object::Object {
+        return match lookup(name) {
+            Some(object::Object::Builtin(builtin)) => (builtin.func)(args),
+            _ => panic!("no builtin named {}", name),
+        };
+    }
+
+    fn array(values: &[i64]) -> object::Object {
+        return object::Object::Array(values.iter().map(|v| object::Object::Integer(*v)).collect());
+    }
+
+    #[test]
+    fn test_lookup() {
+        for builtin in BUILTINS.iter() {
+            match lookup(builtin.name) {
+                Some(object::Object::Builtin(found)) => assert_eq!(found.name, builtin.name),
+                _ => assert!(false, "builtin {} not found", builtin.name),
+            }
+        }
+        assert!(lookup("missing").is_none());
+    }
+
+    #[test]
+    fn test_array_builtins() {
+        assert_eq!(call("first", vec![array(&[1, 2, 3])]).inspect(), "1");
+        assert_eq!(call("first", vec![array(&[])]).inspect(), "null");
+        assert_eq!(call("last", vec![array(&[1, 2, 3])]).inspect(), "3");
+        assert_eq!(call("last", vec![array(&[])]).inspect(), "null");
+        assert_eq!(call("rest", vec![array(&[1, 2, 3])]).inspect(), "[2, 3]");
+        assert_eq!(call("rest", vec![array(&[1])]).inspect(), "[]");
+        assert_eq!(call("rest", vec![array(&[])]).inspect(), "null");
+        assert_eq!(call("push", vec![array(&[1]), object::Object::Integer(2)]).inspect(), "[1, 2]");
+        assert_eq!(call("puts", vec![]).inspect(), "null");
+    }
+}
@@ -0,0 +1,60 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use object::*;
+
+pub struct Environment {
+    pub store:      HashMap<String, object::Object>,
+    pub outer:      Option<Rc<RefCell<Environment>>>,   // enclosing scope, None for the global environment
+}
+
+impl Environment {
+    pub fn new() -> Environment {
+        return Environment{
+            store:      HashMap::new(),
+            outer:      None,
+        };
+    }
+
+    pub fn new_enclosed(outer: Rc<RefCell<Environment>>) -> Environment {
+        let mut env: Environment = Environment::new();
+        env.outer = Some(outer);
+        return env;
+    }
+
+    pub fn get(&self, name: &str) -> Option<object::Object> {
+        match self.store.get(name) {
+            Some(obj) => return Some(obj.clone()),
+            None => (),
+        }
+        return match self.outer.as_ref() {
+            Some(outer) => outer.borrow().get(name),
+            None => None,
+        };
+    }
+
+    pub fn set(&mut self, name: String, val: object::Object) -> object::Object {
+        self.store.insert(name, val.clone());
+        return val;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_enclosed_environment() {
+        let outer = Rc::new(RefCell::new(Environment::new()));
+        outer.borrow_mut().set(String::from("a"), object::Object::Integer(1));
+        outer.borrow_mut().set(String::from("b"), object::Object::Integer(2));
+
+        let mut inner = Environment::new_enclosed(Rc::clone(&outer));
+        inner.set(String::from("b"), object::Object::Integer(3));
+
+        assert_eq!(inner.get("a").map(|obj| obj.inspect()), Some(String::from("1")));
+        assert_eq!(inner.get("b").map(|obj| obj.inspect()), Some(String::from("3")));
+        assert_eq!(outer.borrow().get("b").map(|obj| obj.inspect()), Some(String::from("2")));
+        assert!(inner.get("c").is_none());
+    }
+}
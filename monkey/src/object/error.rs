@@ -0,0 +1,238 @@
+use std::fmt;
+
+// How many runs of frames a traceback shows at either end of a deep call stack.
+const TRACEBACK_EDGE: usize = 10;
+
+// What went wrong, independent of the wording of the message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    UnknownIdentifier,
+    UnknownOperator,
+    TypeMismatch,
+    DivisionByZero,
+    NotAFunction,
+    WrongArgumentCount,
+    InvalidArgument,        // a builtin does not accept the type of an argument
+    IndexNotSupported,
+    UnusableHashKey,
+    StackOverflow,          // too many calls were active at once, or the VM ran out of stack
+    InvalidBytecode,        // the VM was given instructions no compiler produces
+    WriteFailed,            // a builtin could not write its output, e.g. to a closed pipe
+}
+
+impl ErrorKind {
+    pub fn as_str(&self) -> &'static str {
+        return match *self {
+            ErrorKind::UnknownIdentifier    => "UnknownIdentifier",
+            ErrorKind::UnknownOperator      => "UnknownOperator",
+            ErrorKind::TypeMismatch         => "TypeMismatch",
+            ErrorKind::DivisionByZero       => "DivisionByZero",
+            ErrorKind::NotAFunction         => "NotAFunction",
+            ErrorKind::WrongArgumentCount   => "WrongArgumentCount",
+            ErrorKind::InvalidArgument      => "InvalidArgument",
+            ErrorKind::IndexNotSupported    => "IndexNotSupported",
+            ErrorKind::UnusableHashKey      => "UnusableHashKey",
+            ErrorKind::StackOverflow        => "StackOverflow",
+            ErrorKind::InvalidBytecode      => "InvalidBytecode",
+            ErrorKind::WriteFailed          => "WriteFailed",
+        };
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "{}", self.as_str());
+    }
+}
+
+// A call that was active when an error was raised, and the line and column in
+// it that were being evaluated.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StackFrame {
+    pub function:   String,
+    pub position:   Option<(usize, usize)>,
+}
+
+// The error value of a failed evaluation. Its call stack is filled in while the
+// error unwinds: every node it passes records its position until one has, and
+// every function call it leaves turns that position into a frame.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RuntimeError {
+    pub kind:       ErrorKind,
+    pub message:    String,
+    pub stack:      Vec<StackFrame>,            // innermost call first
+    position:       Option<(usize, usize)>,     // innermost position in the call being unwound
+}
+
+impl RuntimeError {
+    pub fn new(kind: ErrorKind, message: String) -> RuntimeError {
+        return RuntimeError{
+            kind:       kind,
+            message:    message,
+            stack:      Vec::new(),
+            position:   None,
+        };
+    }
+
+    // Records where the error happened in the call being unwound, unless a more
+    // deeply nested node already did.
+    pub fn locate(&mut self, line: usize, column: usize) {
+        if self.position.is_none() {
+            self.position = Some((line, column));
+        }
+    }
+
+    // Leaves the call of function, after which the position of its call site is
+    // recorded next.
+    pub fn unwind(&mut self, function: &str) {
+        self.stack.push(StackFrame{ function: String::from(function), position: self.position.take() });
+    }
+
+    // Renders the error with its call stack, outermost call first. With the
+    // source text, each frame also shows the line it was at. A run of identical
+    // frames, as deep recursion leaves, is shown once with its length, and of a
+    // stack with many runs only those at either end are shown.
+    pub fn traceback(&self, source: Option<&str>) -> String {
+        let source_lines: Vec<&str> = match source {
+            Some(source) => source.lines().collect(),
+            None => Vec::new(),
+        };
+
+        let mut runs: Vec<(&StackFrame, usize)> = Vec::new();
+        for frame in self.stack.iter().rev() {
+            let repeated: bool = match runs.last() {
+                Some(&(last, _)) => last == frame,
+                None => false,
+            };
+            if repeated {
+                runs.last_mut().unwrap().1 += 1;
+            } else {
+                runs.push((frame, 1));
+            }
+        }
+
+        let omitted: usize = runs.len().saturating_sub(2 * TRACEBACK_EDGE);
+        let mut builder: String = String::from("Traceback (most recent call last):\n");
+        for (i, &(frame, count)) in runs.iter().enumerate() {
+            if omitted > 0 && i >= TRACEBACK_EDGE && i < TRACEBACK_EDGE + omitted {
+                if i == TRACEBACK_EDGE {
+                    let frames: usize = runs[i..i + omitted].iter().map(|&(_, count)| count).sum();
+                    builder.push_str(&format!("  [{} more frames]\n", frames));
+                }
+                continue;
+            }
+
+            push_frame(&mut builder, frame, &source_lines);
+            if count > 1 {
+                builder.push_str(&format!("  [previous frame repeated {} more times]\n", count - 1));
+            }
+        }
+        builder.push_str(&format!("{}: {}", self.kind, self.message));
+
+        return builder;
+    }
+}
+
+fn push_frame(builder: &mut String, frame: &StackFrame, source_lines: &[&str]) {
+    match frame.position {
+        Some((line, column)) => {
+            builder.push_str(&format!("  line {}, column {}, in {}\n", line, column, frame.function));
+            if line >= 1 && line <= source_lines.len() {
+                builder.push_str(&format!("    {}\n", source_lines[line - 1].trim()));
+            }
+        },
+        None => builder.push_str(&format!("  in {}\n", frame.function)),
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "{}", self.message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_traceback() {
+        let mut err = RuntimeError::new(ErrorKind::TypeMismatch, String::from("type mismatch: INTEGER + BOOLEAN"));
+        err.locate(1, 24);
+        err.locate(1, 22);
+        err.unwind("add");
+        err.locate(2, 4);
+        err.unwind("<main>");
+
+        assert_eq!(err.stack, vec![
+            StackFrame{ function: String::from("add"), position: Some((1, 24)) },
+            StackFrame{ function: String::from("<main>"), position: Some((2, 4)) },
+        ]);
+        assert_eq!(err.to_string(), "type mismatch: INTEGER + BOOLEAN");
+
+        assert_eq!(err.traceback(None), "\
+Traceback (most recent call last):
+  line 2, column 4, in <main>
+  line 1, column 24, in add
+TypeMismatch: type mismatch: INTEGER + BOOLEAN");
+
+        assert_eq!(err.traceback(Some("let add = fn(a, b) { a + b };\nadd(1, true)\n")), "\
+Traceback (most recent call last):
+  line 2, column 4, in <main>
+    add(1, true)
+  line 1, column 24, in add
+    let add = fn(a, b) { a + b };
+TypeMismatch: type mismatch: INTEGER + BOOLEAN");
+    }
+
+    #[test]
+    fn test_traceback_of_recursion() {
+        let mut err = RuntimeError::new(ErrorKind::StackOverflow, String::from("stack overflow"));
+        for _ in 0..1000 {
+            err.locate(1, 18);
+            err.unwind("f");
+        }
+        err.locate(1, 30);
+        err.unwind("<main>");
+
+        assert_eq!(err.traceback(Some("let f = fn(x) { f(x + 1) }; f(0);")), "\
+Traceback (most recent call last):
+  line 1, column 30, in <main>
+    let f = fn(x) { f(x + 1) }; f(0);
+  line 1, column 18, in f
+    let f = fn(x) { f(x + 1) }; f(0);
+  [previous frame repeated 999 more times]
+StackOverflow: stack overflow");
+    }
+
+    #[test]
+    fn test_traceback_of_mutual_recursion() {
+        let mut err = RuntimeError::new(ErrorKind::StackOverflow, String::from("stack overflow"));
+        for _ in 0..30 {
+            err.locate(2, 5);
+            err.unwind("odd");
+            err.locate(1, 5);
+            err.unwind("even");
+        }
+        err.locate(3, 1);
+        err.unwind("<main>");
+
+        let traceback: String = err.traceback(None);
+        let lines: Vec<&str> = traceback.lines().collect();
+        assert_eq!(lines.len(), 1 + 10 + 1 + 10 + 1);
+        assert_eq!(lines[1], "  line 3, column 1, in <main>");
+        assert_eq!(lines[2], "  line 1, column 5, in even");
+        assert_eq!(lines[10], "  line 1, column 5, in even");
+        assert_eq!(lines[11], "  [41 more frames]");
+        assert_eq!(lines[12], "  line 1, column 5, in even");
+        assert_eq!(lines[21], "  line 2, column 5, in odd");
+        assert_eq!(lines[22], "StackOverflow: stack overflow");
+    }
+
+    #[test]
+    fn test_traceback_without_position() {
+        let mut err = RuntimeError::new(ErrorKind::DivisionByZero, String::from("division by zero"));
+        err.unwind("<main>");
+        assert_eq!(err.traceback(None), "Traceback (most recent call last):\n  in <main>\nDivisionByZero: division by zero");
+    }
+}
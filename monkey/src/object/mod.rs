@@ -0,0 +1,7 @@
+pub mod builtins;
+
+pub mod environment;
+
+pub mod error;
+
+pub mod object;
@@ -0,0 +1,233 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use ast::*;
+use ast::ast::Node;
+use code::*;
+use object::*;
+
+pub type ObjectType = &'static str;
+
+pub static INTEGER_OBJ:         ObjectType      = "INTEGER";
+pub static BOOLEAN_OBJ:         ObjectType      = "BOOLEAN";
+pub static STRING_OBJ:          ObjectType      = "STRING";
+pub static NULL_OBJ:            ObjectType      = "NULL";
+pub static RETURN_VALUE_OBJ:    ObjectType      = "RETURN_VALUE";
+pub static ERROR_OBJ:           ObjectType      = "ERROR";
+pub static FUNCTION_OBJ:        ObjectType      = "FUNCTION";
+pub static ARRAY_OBJ:           ObjectType      = "ARRAY";
+pub static HASH_OBJ:            ObjectType      = "HASH";
+pub static BUILTIN_OBJ:         ObjectType      = "BUILTIN";
+pub static COMPILED_FUNCTION_OBJ: ObjectType    = "COMPILED_FUNCTION";
+pub static CLOSURE_OBJ:         ObjectType      = "CLOSURE";
+
+#[derive(Clone)]
+pub enum Object {
+    Integer(i64),
+    Boolean(bool),
+    String(String),
+    Null,
+    ReturnValue(Box<Object>),   // wraps the value of a return statement while it unwinds
+    Error(error::RuntimeError),
+    Function(Function),
+    Array(Vec<Object>),
+    Hash(HashMap<HashKey, HashPair>),
+    Builtin(Builtin),
+    CompiledFunction(Rc<CompiledFunction>),
+    Closure(Rc<Closure>),
+}
+
+impl Object {
+    pub fn object_type(&self) -> ObjectType {
+        return match *self {
+            Object::Integer(_) => INTEGER_OBJ,
+            Object::Boolean(_) => BOOLEAN_OBJ,
+            Object::String(_) => STRING_OBJ,
+            Object::Null => NULL_OBJ,
+            Object::ReturnValue(_) => RETURN_VALUE_OBJ,
+            Object::Error(_) => ERROR_OBJ,
+            Object::Function(_) => FUNCTION_OBJ,
+            Object::Array(_) => ARRAY_OBJ,
+            Object::Hash(_) => HASH_OBJ,
+            Object::Builtin(_) => BUILTIN_OBJ,
+            Object::CompiledFunction(_) => COMPILED_FUNCTION_OBJ,
+            Object::Closure(_) => CLOSURE_OBJ,
+        };
+    }
+
+    pub fn inspect(&self) -> String {
+        return match *self {
+            Object::Integer(value) => value.to_string(),
+            Object::Boolean(value) => value.to_string(),
+            Object::String(ref value) => value.clone(),
+            Object::Null => String::from("null"),
+            Object::ReturnValue(ref value) => value.inspect(),
+            Object::Error(ref err) => format!("ERROR: {}", err.message),
+            Object::Function(ref func) => func.inspect(),
+            Object::Array(ref elements) => {
+                let elements: Vec<String> = elements.iter().map(|e| e.inspect()).collect();
+                format!("[{}]", elements.join(", "))
+            },
+            Object::Hash(ref pairs) => {
+                // sorted so that the output does not depend on the map's iteration order
+                let mut pairs: Vec<String> = pairs.values().map(|p| format!("{}: {}", p.key.inspect(), p.value.inspect())).collect();
+                pairs.sort();
+                format!("{{{}}}", pairs.join(", "))
+            },
+            Object::Builtin(ref builtin) => format!("builtin function {}", builtin.name),
+            Object::CompiledFunction(ref func) => format!("CompiledFunction[{:p}]", Rc::as_ptr(func)),
+            Object::Closure(ref closure) => format!("Closure[{:p}]", Rc::as_ptr(closure)),
+        };
+    }
+}
+
+// Only integers, booleans and strings can be used as hash keys.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum HashKey {
+    Integer(i64),
+    Boolean(bool),
+    String(String),
+}
+
+impl Object {
+    pub fn hash_key(&self) -> Option<HashKey> {
+        return match *self {
+            Object::Integer(value) => Some(HashKey::Integer(value)),
+            Object::Boolean(value) => Some(HashKey::Boolean(value)),
+            Object::String(ref value) => Some(HashKey::String(value.clone())),
+            _ => None,
+        };
+    }
+}
+
+#[derive(Clone)]
+pub struct HashPair {
+    pub key:            Object,         // the original key, kept for inspect
+    pub value:          Object,
+}
+
+pub type BuiltinFunction = fn(Vec<Object>) -> Object;
+
+#[derive(Clone, Copy)]
+pub struct Builtin {
+    pub name:           &'static str,
+    pub func:           BuiltinFunction,
+}
+
+// A function body lowered to bytecode by the compiler.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CompiledFunction {
+    pub name:           Option<String>,     // the name of the let binding of the function literal
+    pub instructions:   code::Instructions,
+    pub lines:          code::LineTable,    // source positions of the instructions, empty when unknown
+    pub num_locals:     usize,          // stack slots reserved for parameters and let bindings
+    pub num_parameters: usize,
+}
+
+impl CompiledFunction {
+    pub fn new(instructions: code::Instructions, lines: code::LineTable, num_locals: usize, num_parameters: usize) -> CompiledFunction {
+        return CompiledFunction{
+            name:           None,
+            instructions:   instructions,
+            lines:          lines,
+            num_locals:     num_locals,
+            num_parameters: num_parameters,
+        };
+    }
+}
+
+// A compiled function together with the values it captured from the functions
+// enclosing it when it was created.
+#[derive(Clone)]
+pub struct Closure {
+    pub func:           Rc<CompiledFunction>,
+    pub free:           Vec<Object>,
+}
+
+impl Closure {
+    pub fn new(func: Rc<CompiledFunction>, free: Vec<Object>) -> Closure {
+        return Closure{
+            func:           func,
+            free:           free,
+        };
+    }
+}
+
+#[derive(Clone)]
+pub struct Function {
+    pub name:           Option<String>,     // the name of the let binding of the function literal
+    pub parameters:     Rc<Vec<ast::Identifier>>,
+    pub body:           Rc<ast::BlockStatement>,
+    pub env:            Rc<RefCell<environment::Environment>>,    // the scope the function was defined in
+}
+
+impl Function {
+    pub fn new(params: Rc<Vec<ast::Identifier>>, body: Rc<ast::BlockStatement>, env: Rc<RefCell<environment::Environment>>) -> Function {
+        return Function{
+            name:           None,
+            parameters:     params,
+            body:           body,
+            env:            env,
+        };
+    }
+
+    pub fn inspect(&self) -> String {
+        let mut builder: String = String::new();
+
+        let params: Vec<String> = self.parameters.iter().map(|p| p.to_string()).collect();
+
+        builder.push_str("fn");
+        builder.push('(');
+        builder.push_str(&params.join(", "));
+        builder.push(')');
+        builder.push(' ');
+        builder.push_str(&self.body.to_string());
+
+        return builder;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use token::token;
+
+    #[test]
+    fn test_inspect() {
+        assert_eq!(Object::Integer(5).inspect(), "5");
+        assert_eq!(Object::Boolean(true).inspect(), "true");
+        assert_eq!(Object::Null.inspect(), "null");
+        assert_eq!(Object::ReturnValue(Box::new(Object::Integer(10))).inspect(), "10");
+        assert_eq!(Object::Error(error::RuntimeError::new(error::ErrorKind::UnknownIdentifier, String::from("identifier not found: x"))).inspect(), "ERROR: identifier not found: x");
+
+        let params = vec![ast::Identifier::new(token::Token::new(token::IDENT, String::from("x")), String::from("x"))];
+        let body = ast::BlockStatement::new(token::Token::new(token::LBRACE, String::from("{")));
+        let func = Function::new(Rc::new(params), Rc::new(body), Rc::new(RefCell::new(environment::Environment::new())));
+        assert_eq!(Object::Function(func).inspect(), "fn(x) { }");
+        assert_eq!(Object::Array(vec![Object::Integer(1), Object::Boolean(true)]).inspect(), "[1, true]");
+        assert_eq!(Object::Array(Vec::new()).inspect(), "[]");
+
+        let mut pairs = HashMap::new();
+        pairs.insert(HashKey::Integer(2), HashPair{ key: Object::Integer(2), value: Object::Boolean(false) });
+        pairs.insert(HashKey::Integer(1), HashPair{ key: Object::Integer(1), value: Object::Boolean(true) });
+        assert_eq!(Object::Hash(pairs).inspect(), "{1: true, 2: false}");
+    }
+
+    #[test]
+    fn test_hash_key() {
+        assert_eq!(Object::String(String::from("Hello World")).hash_key(), Object::String(String::from("Hello World")).hash_key());
+        assert_ne!(Object::String(String::from("Hello World")).hash_key(), Object::String(String::from("My name is johnny")).hash_key());
+        assert_ne!(Object::Integer(1).hash_key(), Object::Boolean(true).hash_key());
+        assert_eq!(Object::Null.hash_key(), None);
+        assert_eq!(Object::Array(Vec::new()).hash_key(), None);
+    }
+
+    #[test]
+    fn test_object_type() {
+        assert_eq!(Object::Integer(5).object_type(), INTEGER_OBJ);
+        assert_eq!(Object::Boolean(false).object_type(), BOOLEAN_OBJ);
+        assert_eq!(Object::Null.object_type(), NULL_OBJ);
+        assert_eq!(Object::ReturnValue(Box::new(Object::Null)).object_type(), RETURN_VALUE_OBJ);
+        assert_eq!(Object::Error(error::RuntimeError::new(error::ErrorKind::TypeMismatch, String::new())).object_type(), ERROR_OBJ);
+    }
+}
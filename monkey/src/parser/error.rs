@@ -0,0 +1,95 @@
+use std::error::Error;
+use std::fmt;
+use std::num::ParseIntError;
+use lexer::error::LexError;
+use token::*;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParseError {
+    Lex(LexError),                      // the lexer could not make a token out of the input
+    UnexpectedToken {                   // the next token is not the one the grammar requires
+        expected:   token::TokenType,
+        found:      token::Token,
+    },
+    UnexpectedEof {                     // the input ended in the middle of a construct
+        expected:   Option<token::TokenType>,   // None when any expression would do
+        span:       token::Span,
+    },
+    NoPrefixParseFn {                   // the token cannot start an expression
+        found:      token::Token,
+    },
+    InvalidIntegerLiteral {             // the INT token does not fit into an i64
+        found:      token::Token,
+        cause:      ParseIntError,
+    },
+}
+
+impl ParseError {
+    pub fn span(&self) -> token::Span {
+        return match *self {
+            ParseError::Lex(ref err) => err.span(),
+            ParseError::UnexpectedToken{ ref found, .. } => found.span,
+            ParseError::UnexpectedEof{ span, .. } => span,
+            ParseError::NoPrefixParseFn{ ref found } => found.span,
+            ParseError::InvalidIntegerLiteral{ ref found, .. } => found.span,
+        };
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match *self {
+            ParseError::Lex(ref err) => write!(f, "{}", err),
+            ParseError::UnexpectedToken{ expected, ref found } =>
+                write!(f, "{}: expected next token to be {}, got {} instead", found.span, expected, found.ttype),
+            ParseError::UnexpectedEof{ expected: Some(expected), span } =>
+                write!(f, "{}: unexpected end of input, expected {}", span, expected),
+            ParseError::UnexpectedEof{ expected: None, span } =>
+                write!(f, "{}: unexpected end of input, expected an expression", span),
+            ParseError::NoPrefixParseFn{ ref found } =>
+                write!(f, "{}: no prefix parse function for {} found", found.span, found.ttype),
+            ParseError::InvalidIntegerLiteral{ ref found, ref cause } =>
+                write!(f, "{}: could not parse {} as integer: {}", found.span, found.literal, cause),
+        };
+    }
+}
+
+impl Error for ParseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        return match *self {
+            ParseError::Lex(ref err) => Some(err),
+            ParseError::InvalidIntegerLiteral{ ref cause, .. } => Some(cause),
+            _ => None,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let found = token::Token{ ttype: token::INT, literal: String::from("5"), span: token::Span::new(6, 7, 1, 7) };
+        let err = ParseError::UnexpectedToken{ expected: token::ASSIGN, found: found };
+        assert_eq!(err.to_string(), "1:7: expected next token to be =, got INT instead");
+        assert_eq!(err.span(), token::Span::new(6, 7, 1, 7));
+
+        let err = ParseError::UnexpectedEof{ expected: Some(token::RBRACE), span: token::Span::new(10, 10, 2, 1) };
+        assert_eq!(err.to_string(), "2:1: unexpected end of input, expected }");
+
+        let err = ParseError::UnexpectedEof{ expected: None, span: token::Span::new(8, 8, 1, 9) };
+        assert_eq!(err.to_string(), "1:9: unexpected end of input, expected an expression");
+    }
+
+    #[test]
+    fn test_source() {
+        let found = token::Token{ ttype: token::INT, literal: String::from("99999999999999999999"), span: token::Span::new(0, 20, 1, 1) };
+        let cause = found.literal.parse::<i64>().unwrap_err();
+        let err = ParseError::InvalidIntegerLiteral{ found: found, cause: cause };
+        assert!(err.source().is_some());
+
+        let found = token::Token{ ttype: token::SEMICOLON, literal: String::from(";"), span: token::Span::new(0, 1, 1, 1) };
+        assert!(ParseError::NoPrefixParseFn{ found: found }.source().is_none());
+    }
+}
@@ -1,21 +1,26 @@
+use std::mem;
 use ast::*;
 use lexer::*;
+use parser::error::*;
 use token::*;
 
 #[derive(PartialEq)]
 enum Precedence {
     LOWEST,
+    LOGICALOR,      // ||
+    LOGICALAND,     // &&
     EQUALS,         // ==
     LESSGREATER,    // > or <
     SUM,            // +
-    PRODUCT,        // *
+    PRODUCT,        // * or %
     PREFIX,         // -X or !X
     CALL,           // myFunction(X)
+    INDEX,          // array[index]
 }
 
 pub struct Parser<'a> {
     pub l:              &'a mut lexer::Lexer<'a>,
-    pub errors:         Vec<String>,
+    errors:             Vec<ParseError>,
     pub cur_token:      Option<token::Token>,
     pub peek_token:     Option<token::Token>,
 }
@@ -38,15 +43,21 @@ impl<'a> Parser<'a> {
 
     pub fn precedences(&mut self, ttype: token::TokenType) -> i32 {
         return match ttype {
-            "==" => Precedence::EQUALS as i32,
-            "!=" => Precedence::EQUALS as i32,
-            "<" => Precedence::LESSGREATER as i32,
-            ">" => Precedence::LESSGREATER as i32,
-            "+" => Precedence::SUM as i32,
-            "-" => Precedence::SUM as i32,
-            "/" => Precedence::PRODUCT as i32,
-            "*" => Precedence::PRODUCT as i32,
-            "(" => Precedence::CALL as i32,
+            token::OR => Precedence::LOGICALOR as i32,
+            token::AND => Precedence::LOGICALAND as i32,
+            token::EQ => Precedence::EQUALS as i32,
+            token::NOT_EQ => Precedence::EQUALS as i32,
+            token::LT => Precedence::LESSGREATER as i32,
+            token::GT => Precedence::LESSGREATER as i32,
+            token::LT_EQ => Precedence::LESSGREATER as i32,
+            token::GT_EQ => Precedence::LESSGREATER as i32,
+            token::PLUS => Precedence::SUM as i32,
+            token::MINUS => Precedence::SUM as i32,
+            token::SLASH => Precedence::PRODUCT as i32,
+            token::ASTERISK => Precedence::PRODUCT as i32,
+            token::PERCENT => Precedence::PRODUCT as i32,
+            token::LPAREN => Precedence::CALL as i32,
+            token::LBRACKET => Precedence::INDEX as i32,
             _ => Precedence::LOWEST as i32,
         };
     }
@@ -65,43 +76,65 @@ impl<'a> Parser<'a> {
         };
     }
 
-    pub fn prefix_parse_fns(&mut self, ttype: token::TokenType) -> Option<Box<ast::Expression>> {
+    pub fn prefix_parse_fns(&mut self, ttype: token::TokenType) -> Option<ast::Expression> {
         return match ttype {
-            "IDENT" => self.parse_identifier(),
-            "INT" => self.parse_integer_literal(),
-            "!" => self.parse_prefix_expression(),
-            "-" => self.parse_prefix_expression(),
-            _ => None,
+            token::IDENT => self.parse_identifier(),
+            token::INT => self.parse_integer_literal(),
+            token::STRING => self.parse_string_literal(),
+            token::TRUE => self.parse_boolean(),
+            token::FALSE => self.parse_boolean(),
+            token::LPAREN => self.parse_grouped_expression(),
+            token::IF => self.parse_if_expression(),
+            token::FUNCTION => self.parse_function_literal(),
+            token::LBRACKET => self.parse_array_literal(),
+            // blocks are only parsed where the grammar requires one (after if,
+            // else and fn), so a brace in expression position opens a hash
+            token::LBRACE => self.parse_hash_literal(),
+            token::BANG => self.parse_prefix_expression(),
+            token::MINUS => self.parse_prefix_expression(),
+            // the lexer has already reported why this token is illegal
+            token::ILLEGAL => None,
+            _ => {
+                self.no_prefix_parse_fn_error();
+                None
+            },
         };
     }
 
     pub fn infix_parse_fns(&mut self, ttype: token::TokenType) -> bool {
         return match ttype {
-            "+" =>  true,
-            "-" =>  true,
-            "/" =>  true,
-            "*" =>  true,
-            "==" => true,
-            "!=" => true,
-            "<" =>  true,
-            ">" =>  true,
-            _ =>    false,
+            token::PLUS =>      true,
+            token::MINUS =>     true,
+            token::SLASH =>     true,
+            token::ASTERISK =>  true,
+            token::PERCENT =>   true,
+            token::EQ =>        true,
+            token::NOT_EQ =>    true,
+            token::LT =>        true,
+            token::GT =>        true,
+            token::LT_EQ =>     true,
+            token::GT_EQ =>     true,
+            token::AND =>       true,
+            token::OR =>        true,
+            token::LPAREN =>    true,
+            token::LBRACKET =>  true,
+            _ =>                false,
         };
     }
 
-    pub fn parse_identifier(&mut self) -> Option<Box<ast::Expression>> {
+    pub fn parse_identifier(&mut self) -> Option<ast::Expression> {
         return match self.cur_token.as_ref().cloned() {
-            Some(tok) => Some(Box::new(ast::Identifier::new(tok.clone(), tok.literal.clone()))),
+            Some(tok) => Some(ast::Expression::Identifier(ast::Identifier::new(tok.clone(), tok.literal.clone()))),
             None => None,
         };
     }
 
-    pub fn parse_integer_literal(&mut self) -> Option<Box<ast::Expression>> {
+    pub fn parse_integer_literal(&mut self) -> Option<ast::Expression> {
         return match self.cur_token.as_ref().cloned() {
             Some(tok) => match tok.literal.parse::<i64>() {
-                Ok(val) => Some(Box::new(ast::IntegerLiteral::new(tok, val))),
+                Ok(val) => Some(ast::Expression::IntegerLiteral(ast::IntegerLiteral::new(tok, val))),
                 Err(err) => {
-                    self.errors.push(err.to_string());
+                    self.errors.push(ParseError::InvalidIntegerLiteral{ found: tok, cause: err });
                     None
                 },
             },
@@ -109,18 +142,47 @@ impl<'a> Parser<'a> {
         };
     }
 
+    pub fn parse_string_literal(&mut self) -> Option<ast::Expression> {
+        return match self.cur_token.as_ref().cloned() {
+            Some(tok) => {
+                let value: String = tok.literal.clone();
+                Some(ast::Expression::StringLiteral(ast::StringLiteral::new(tok, value)))
+            },
+            None => None,
+        };
+    }
+
+    pub fn parse_boolean(&mut self) -> Option<ast::Expression> {
+        return match self.cur_token.as_ref().cloned() {
+            Some(tok) => {
+                let value: bool = tok.ttype == token::TRUE;
+                Some(ast::Expression::Boolean(ast::Boolean::new(tok, value)))
+            },
+            None => None,
+        };
+    }
+
     pub fn peek_error(&mut self, ttype: token::TokenType) {
-        match self.peek_token.as_ref().cloned() {
-            Some(tok) => self.errors.push(format!("expected next token to be {}, got {} instead", ttype, tok.ttype)),
-            None => self.errors.push(format!("expected next token to be {}, got None instead", ttype)),
-        }
+        let err: ParseError = match self.peek_token.as_ref().cloned() {
+            Some(ref tok) if tok.ttype == token::EOF => ParseError::UnexpectedEof{ expected: Some(ttype), span: tok.span },
+            Some(tok) => ParseError::UnexpectedToken{ expected: ttype, found: tok },
+            None => ParseError::UnexpectedEof{ expected: Some(ttype), span: self.eof_span() },
+        };
+        self.errors.push(err);
     }
 
     pub fn no_prefix_parse_fn_error(&mut self) {
-        match self.cur_token.as_ref().cloned() {
-            Some(tok) => self.errors.push(format!("no prefix parse function for {} found", tok.ttype)),
-            None => self.errors.push(format!("no prefix parse function for None found")),
-        }
+        let err: ParseError = match self.cur_token.as_ref().cloned() {
+            Some(ref tok) if tok.ttype == token::EOF => ParseError::UnexpectedEof{ expected: None, span: tok.span },
+            Some(tok) => ParseError::NoPrefixParseFn{ found: tok },
+            None => ParseError::UnexpectedEof{ expected: None, span: self.eof_span() },
+        };
+        self.errors.push(err);
+    }
+
+    fn eof_span(&self) -> token::Span {
+        let end: usize = self.l.input.len();
+        return token::Span::new(end, end, self.l.line, self.l.column);
     }
 
     pub fn next_token(&mut self) {
@@ -129,10 +191,11 @@ impl<'a> Parser<'a> {
         } else {
             self.cur_token = None;
         }
-        self.peek_token = self.l.next_token();
+        self.peek_token = self.l.next();
+        self.errors.extend(self.l.errors.drain(..).map(ParseError::Lex));
     }
 
-    pub fn parse_program(&mut self) -> Option<ast::Program> {
+    pub fn parse_program(&mut self) -> Result<ast::Program, Vec<ParseError>> {
         let mut program: ast::Program = ast::Program::new();
 
         while self.cur_token.is_some() {
@@ -150,10 +213,14 @@ impl<'a> Parser<'a> {
             self.next_token();
         }
 
-        return Some(program);
+        if self.errors.len() > 0 {
+            return Err(mem::take(&mut self.errors));
+        }
+
+        return Ok(program);
     }
 
-    pub fn parse_statement(&mut self) -> Option<Box<ast::Statement>> {
+    pub fn parse_statement(&mut self) -> Option<ast::Statement> {
         match self.cur_token.as_ref().cloned() {
             Some(tok) => {
                 if tok.ttype == token::LET {
@@ -168,7 +235,7 @@ impl<'a> Parser<'a> {
         }
     }
 
-    pub fn parse_let_statement(&mut self) -> Option<Box<ast::Statement>> {
+    pub fn parse_let_statement(&mut self) -> Option<ast::Statement> {
         let token: token::Token;
         match self.cur_token.as_ref().cloned(){
             Some(tok) => token = tok,
@@ -189,23 +256,22 @@ impl<'a> Parser<'a> {
             return None;
         }
 
-        // TODO: return LetStatement with expression
-        while self.cur_token.is_some() {
-            match self.cur_token.as_ref().cloned() {
-                Some(tok) => {
-                    if tok.ttype == token::SEMICOLON {
-                        return Some(Box::new(ast::LetStatement::new(token, name, None)));
-                    }
-                },
-                None => (),
-            }
+        self.next_token();
+
+        let value: ast::Expression;
+        match self.parse_expression(Precedence::LOWEST as i32) {
+            Some(exp) => value = exp,
+            None => return None,
+        }
+
+        if self.peek_token_is(token::SEMICOLON) {
             self.next_token();
         }
 
-        return None;
+        return Some(ast::Statement::Let(ast::LetStatement::new(token, name, value)));
     }
 
-    pub fn parse_return_statement(&mut self) -> Option<Box<ast::Statement>> {
+    pub fn parse_return_statement(&mut self) -> Option<ast::Statement> {
         let token: token::Token;
         match self.cur_token.as_ref().cloned() {
             Some(tok) => token = tok,
@@ -214,51 +280,54 @@ impl<'a> Parser<'a> {
 
         self.next_token();
 
-        // TODO: return ReturnStatement with expression
-        while self.cur_token.is_some() {
-            match self.cur_token.as_ref().cloned() {
-                Some(tok) => {
-                    if tok.ttype == token::SEMICOLON {
-                        return Some(Box::new(ast::ReturnStatement::new(token, None)));
-                    }
-                },
-                None => (),
-            }
+        let return_value: ast::Expression;
+        match self.parse_expression(Precedence::LOWEST as i32) {
+            Some(exp) => return_value = exp,
+            None => return None,
+        }
+
+        if self.peek_token_is(token::SEMICOLON) {
             self.next_token();
         }
 
-        return None;
+        return Some(ast::Statement::Return(ast::ReturnStatement::new(token, return_value)));
     }
 
-    pub fn parse_expression_statement(&mut self) -> Option<Box<ast::Statement>> {
+    pub fn parse_expression_statement(&mut self) -> Option<ast::Statement> {
         let token: token::Token;
         match self.cur_token.as_ref().cloned() {
             Some(tok) => token = tok,
             None => return None,
         }
 
-        let expression: Option<Box<ast::Expression>> = self.parse_expression(Precedence::LOWEST as i32);
+        let expression: ast::Expression;
+        match self.parse_expression(Precedence::LOWEST as i32) {
+            Some(exp) => expression = exp,
+            None => return None,
+        }
 
         if self.peek_token_is(token::SEMICOLON) {
             self.next_token();
         }
 
-        return Some(Box::new(ast::ExpressionStatement::new(token, expression)));
+        return Some(ast::Statement::Expression(ast::ExpressionStatement::new(token, expression)));
     }
 
-    pub fn parse_expression(&mut self, precedence: i32) -> Option<Box<ast::Expression>> {
-        let prefix: Option<Box<ast::Expression>> = match self.cur_token.as_ref().cloned() {
+    pub fn parse_expression(&mut self, precedence: i32) -> Option<ast::Expression> {
+        let prefix: Option<ast::Expression> = match self.cur_token.as_ref().cloned() {
             Some(tok) => self.prefix_parse_fns(tok.ttype),
-            None => None,
+            None => {
+                self.no_prefix_parse_fn_error();
+                None
+            },
         };
 
-        if prefix.is_none() {
-            self.no_prefix_parse_fn_error();
-            return None;
+        let mut left_exp: ast::Expression;
+        match prefix {
+            Some(exp) => left_exp = exp,
+            None => return None,
         }
 
-        let mut leftExp: Option<Box<ast::Expression>> = prefix;
-
         while ! self.peek_token_is(token::SEMICOLON) && precedence < self.peek_precedence() {
             let flag: bool = match self.peek_token.as_ref().cloned() {
                 Some(tok) => self.infix_parse_fns(tok.ttype),
@@ -266,18 +335,30 @@ impl<'a> Parser<'a> {
             };
 
             if ! flag {
-                return leftExp;
+                return Some(left_exp);
             }
 
             self.next_token();
 
-            leftExp = self.parse_infix_expression(leftExp);
+            let infix: Option<ast::Expression>;
+            if self.cur_token_is(token::LPAREN) {
+                infix = self.parse_call_expression(left_exp);
+            } else if self.cur_token_is(token::LBRACKET) {
+                infix = self.parse_index_expression(left_exp);
+            } else {
+                infix = self.parse_infix_expression(left_exp);
+            }
+
+            match infix {
+                Some(exp) => left_exp = exp,
+                None => return None,
+            }
         }
 
-        return leftExp;
+        return Some(left_exp);
     }
 
-    pub fn parse_prefix_expression(&mut self) -> Option<Box<ast::Expression>> {
+    pub fn parse_prefix_expression(&mut self) -> Option<ast::Expression> {
         let token: token::Token;
         match self.cur_token.as_ref().cloned() {
             Some(tok) => token = tok,
@@ -286,218 +367,1136 @@ impl<'a> Parser<'a> {
 
         self.next_token();
 
-        let right: Option<Box<ast::Expression>> = self.parse_expression(Precedence::PREFIX as i32);
+        let right: ast::Expression;
+        match self.parse_expression(Precedence::PREFIX as i32) {
+            Some(exp) => right = exp,
+            None => return None,
+        }
+
+        return Some(ast::Expression::Prefix(ast::PrefixExpression::new(token.clone(), token.literal.clone(), right)));
+    }
+
+    pub fn parse_grouped_expression(&mut self) -> Option<ast::Expression> {
+        self.next_token();
+
+        let exp: Option<ast::Expression> = self.parse_expression(Precedence::LOWEST as i32);
+
+        if ! self.expect_peek(token::RPAREN) {
+            return None;
+        }
 
-        return Some(Box::new(ast::PrefixExpression::new(token.clone(), token.literal.clone(), right)));
+        return exp;
     }
 
-    pub fn parse_infix_expression(&mut self, left: Option<Box<ast::Expression>>) -> Option<Box<ast::Expression>> {
+    pub fn parse_if_expression(&mut self) -> Option<ast::Expression> {
         let token: token::Token;
         match self.cur_token.as_ref().cloned() {
             Some(tok) => token = tok,
             None => return None,
         }
 
-        let precedence: i32 = self.cur_precedence();
+        if ! self.expect_peek(token::LPAREN) {
+            return None;
+        }
+
         self.next_token();
-        let right: Option<Box<ast::Expression>> = self.parse_expression(precedence);
+        let condition: ast::Expression;
+        match self.parse_expression(Precedence::LOWEST as i32) {
+            Some(exp) => condition = exp,
+            None => return None,
+        }
 
-        return Some(Box::new(ast::InfixExpression::new(token.clone(), left, token.literal.clone(), right)));
-    }
+        if ! self.expect_peek(token::RPAREN) {
+            return None;
+        }
+
+        if ! self.expect_peek(token::LBRACE) {
+            return None;
+        }
 
+        let consequence: ast::BlockStatement;
+        match self.parse_block_statement() {
+            Some(block) => consequence = block,
+            None => return None,
+        }
 
-    pub fn expect_peek(&mut self, ttype: token::TokenType) -> bool {
-        if self.peek_token_is(ttype) {
+        let mut alternative: Option<ast::BlockStatement> = None;
+
+        if self.peek_token_is(token::ELSE) {
             self.next_token();
-            return true;
-        } else {
-            self.peek_error(ttype);
-            return false;
+
+            if ! self.expect_peek(token::LBRACE) {
+                return None;
+            }
+
+            match self.parse_block_statement() {
+                Some(block) => alternative = Some(block),
+                None => return None,
+            }
         }
+
+        return Some(ast::Expression::If(ast::IfExpression::new(token, condition, consequence, alternative)));
     }
 
-    pub fn peek_token_is(&self, ttype: token::TokenType) -> bool {
-        match self.peek_token.as_ref().cloned() {
-            Some(tok) => return tok.ttype == ttype,
-            None => return false,
+    pub fn parse_block_statement(&mut self) -> Option<ast::BlockStatement> {
+        let mut block: ast::BlockStatement;
+        match self.cur_token.as_ref().cloned() {
+            Some(tok) => block = ast::BlockStatement::new(tok),
+            None => return None,
+        }
+
+        self.next_token();
+
+        while ! self.cur_token_is(token::RBRACE) {
+            match self.cur_token.as_ref().cloned() {
+                Some(tok) => {
+                    if tok.ttype == token::EOF {
+                        self.errors.push(ParseError::UnexpectedEof{ expected: Some(token::RBRACE), span: tok.span });
+                        return None;
+                    }
+                },
+                None => {
+                    let span: token::Span = self.eof_span();
+                    self.errors.push(ParseError::UnexpectedEof{ expected: Some(token::RBRACE), span: span });
+                    return None;
+                },
+            }
+
+            match self.parse_statement() {
+                Some(statement) => block.statements.push(statement),
+                None => (),
+            }
+            self.next_token();
         }
+
+        return Some(block);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    pub fn parse_function_literal(&mut self) -> Option<ast::Expression> {
+        let token: token::Token;
+        match self.cur_token.as_ref().cloned() {
+            Some(tok) => token = tok,
+            None => return None,
+        }
 
-    #[test]
-    fn test_let_statement() {
-        let input = "
-let x = 5;
-let y = 10;
-let foobar = 838383;
-";
+        if ! self.expect_peek(token::LPAREN) {
+            return None;
+        }
 
-        let mut l = lexer::Lexer::new(input);
-        let mut p = Parser::new(&mut l);
+        let parameters: Vec<ast::Identifier>;
+        match self.parse_function_parameters() {
+            Some(params) => parameters = params,
+            None => return None,
+        }
 
-        let program = p.parse_program();
-        check_parser_errors(&p);
+        if ! self.expect_peek(token::LBRACE) {
+            return None;
+        }
 
-        match program {
-            Some(prog) => {
-                if prog.statements.len() != 3 {
-                    assert!(false, "program.statements does not contain {} statements, got={}", 3, prog.statements.len());
-                }
-                let mut i = 0;
-                for stmt in prog.statements.iter() {
-                    assert_eq!((**stmt).token_literal(), token::LET, "tests[{}]", i);
-                    i += 1;
-                }
-            },
-            None => assert!(false, "parse_program() returns None"),
+        let body: ast::BlockStatement;
+        match self.parse_block_statement() {
+            Some(block) => body = block,
+            None => return None,
         }
+
+        return Some(ast::Expression::FunctionLiteral(ast::FunctionLiteral::new(token, parameters, body)));
     }
 
-    #[test]
-    fn test_return_statement() {
-        let input = "
-return 5;
-return 10;
-return 838383;
-";
+    pub fn parse_function_parameters(&mut self) -> Option<Vec<ast::Identifier>> {
+        let mut identifiers: Vec<ast::Identifier> = Vec::new();
 
-        let mut l = lexer::Lexer::new(input);
-        let mut p = Parser::new(&mut l);
+        if self.peek_token_is(token::RPAREN) {
+            self.next_token();
+            return Some(identifiers);
+        }
 
-        let program = p.parse_program();
-        check_parser_errors(&p);
+        if ! self.expect_peek(token::IDENT) {
+            return None;
+        }
+        match self.cur_token.as_ref().cloned() {
+            Some(tok) => identifiers.push(ast::Identifier::new(tok.clone(), tok.literal.clone())),
+            None => return None,
+        }
 
-        match program {
-            Some(prog) => {
-                if prog.statements.len() != 3 {
-                    assert!(false, "program.statements does not contain {} statements, got={}", 3, prog.statements.len());
-                }
-                let mut i = 0;
-                for stmt in prog.statements.iter() {
-                    assert_eq!((**stmt).token_literal(), token::RETURN, "tests[{}]", i);
-                    i += 1;
-                }
-            },
-            None => assert!(false, "parse_program() returns None"),
+        while self.peek_token_is(token::COMMA) {
+            self.next_token();
+            if ! self.expect_peek(token::IDENT) {
+                return None;
+            }
+            match self.cur_token.as_ref().cloned() {
+                Some(tok) => identifiers.push(ast::Identifier::new(tok.clone(), tok.literal.clone())),
+                None => return None,
+            }
         }
-    }
 
-    #[test]
-    fn test_identifier_expression() {
-        let input = "foobar;";
+        if ! self.expect_peek(token::RPAREN) {
+            return None;
+        }
 
-        let mut l = lexer::Lexer::new(input);
-        let mut p = Parser::new(&mut l);
+        return Some(identifiers);
+    }
 
-        let program = p.parse_program();
-        check_parser_errors(&p);
+    pub fn parse_call_expression(&mut self, function: ast::Expression) -> Option<ast::Expression> {
+        let token: token::Token;
+        match self.cur_token.as_ref().cloned() {
+            Some(tok) => token = tok,
+            None => return None,
+        }
 
-        match program {
-            Some(prog) => {
-                if prog.statements.len() != 1 {
-                    assert!(false, "program.statements does not contain {} statements, got={}", 1, prog.statements.len());
-                }
-                assert_eq!((*prog.statements[0]).token_literal(), "foobar", "tests[{}]", 0);
-            },
-            None => assert!(false, "parse_program() returns None"),
+        let arguments: Vec<ast::Expression>;
+        match self.parse_expression_list(token::RPAREN) {
+            Some(args) => arguments = args,
+            None => return None,
         }
+
+        return Some(ast::Expression::Call(ast::CallExpression::new(token, function, arguments)));
     }
 
-    #[test]
-    fn test_integer_literal_expression() {
-        let input = "5;";
+    // Parses comma-separated expressions up to and including the end token,
+    // as used by call arguments and array literals.
+    pub fn parse_expression_list(&mut self, end: token::TokenType) -> Option<Vec<ast::Expression>> {
+        let mut list: Vec<ast::Expression> = Vec::new();
 
-        let mut l = lexer::Lexer::new(input);
-        let mut p = Parser::new(&mut l);
+        if self.peek_token_is(end) {
+            self.next_token();
+            return Some(list);
+        }
 
-        let program = p.parse_program();
-        check_parser_errors(&p);
+        self.next_token();
+        match self.parse_expression(Precedence::LOWEST as i32) {
+            Some(exp) => list.push(exp),
+            None => return None,
+        }
 
-        match program {
-            Some(prog) => {
-                if prog.statements.len() != 1 {
-                    assert!(false, "program.statements does not contain {} statements, got={}", 1, prog.statements.len());
-                }
-                assert_eq!((*prog.statements[0]).token_literal(), "5", "tests[{}]", 0);
-            },
-            None => assert!(false, "parse_program() returns None"),
+        while self.peek_token_is(token::COMMA) {
+            self.next_token();
+            self.next_token();
+            match self.parse_expression(Precedence::LOWEST as i32) {
+                Some(exp) => list.push(exp),
+                None => return None,
+            }
         }
-    }
 
-    #[test]
-    fn test_parsing_prefix_expressions() {
-        let input = "
-!5;
--5";
+        if ! self.expect_peek(end) {
+            return None;
+        }
 
-        let mut l = lexer::Lexer::new(input);
-        let mut p = Parser::new(&mut l);
+        return Some(list);
+    }
 
-        let program = p.parse_program();
-        check_parser_errors(&p);
+    pub fn parse_array_literal(&mut self) -> Option<ast::Expression> {
+        let token: token::Token;
+        match self.cur_token.as_ref().cloned() {
+            Some(tok) => token = tok,
+            None => return None,
+        }
 
-        match program {
-            Some(prog) => {
-                if prog.statements.len() != 2 {
-                    assert!(false, "program.statements does not contain {} statements, got={}", 2, prog.statements.len());
-                }
-                assert_eq!((*prog.statements[0]).to_string(), "(!5);", "tests[{}]", 0);
-                assert_eq!((*prog.statements[1]).to_string(), "(-5);", "tests[{}]", 1);
-            },
-            None => assert!(false, "parse_program() returns None"),
+        let elements: Vec<ast::Expression>;
+        match self.parse_expression_list(token::RBRACKET) {
+            Some(list) => elements = list,
+            None => return None,
         }
+
+        return Some(ast::Expression::Array(ast::ArrayLiteral::new(token, elements)));
     }
 
-    #[test]
-    fn test_parsing_infix_expressions() {
-        let infix_tests: [String; 8] = [
-            String::from("5 + 5;"),
-            String::from("5 - 5;"),
-            String::from("5 * 5;"),
-            String::from("5 / 5;"),
-            String::from("5 > 5;"),
-            String::from("5 < 5;"),
-            String::from("5 == 5;"),
-            String::from("5 != 5;")
-        ];
+    pub fn parse_hash_literal(&mut self) -> Option<ast::Expression> {
+        let token: token::Token;
+        match self.cur_token.as_ref().cloned() {
+            Some(tok) => token = tok,
+            None => return None,
+        }
 
-        let mut i = 0;
-        for input in infix_tests.iter() {
-            let mut l = lexer::Lexer::new(input);
-            let mut p = Parser::new(&mut l);
+        let mut pairs: Vec<(ast::Expression, ast::Expression)> = Vec::new();
 
-            let program = p.parse_program();
-            check_parser_errors(&p);
+        // stopping at EOF lets the closing expect_peek report the missing brace
+        while ! self.peek_token_is(token::RBRACE) && ! self.peek_token_is(token::EOF) {
+            self.next_token();
+            let key: ast::Expression;
+            match self.parse_expression(Precedence::LOWEST as i32) {
+                Some(exp) => key = exp,
+                None => return None,
+            }
+
+            if ! self.expect_peek(token::COLON) {
+                return None;
+            }
+
+            self.next_token();
+            let value: ast::Expression;
+            match self.parse_expression(Precedence::LOWEST as i32) {
+                Some(exp) => value = exp,
+                None => return None,
+            }
+
+            pairs.push((key, value));
+
+            if ! self.peek_token_is(token::RBRACE) && ! self.peek_token_is(token::EOF) && ! self.expect_peek(token::COMMA) {
+                return None;
+            }
+        }
+
+        if ! self.expect_peek(token::RBRACE) {
+            return None;
+        }
+
+        return Some(ast::Expression::Hash(ast::HashLiteral::new(token, pairs)));
+    }
+
+    pub fn parse_index_expression(&mut self, left: ast::Expression) -> Option<ast::Expression> {
+        let token: token::Token;
+        match self.cur_token.as_ref().cloned() {
+            Some(tok) => token = tok,
+            None => return None,
+        }
+
+        self.next_token();
+        let index: ast::Expression;
+        match self.parse_expression(Precedence::LOWEST as i32) {
+            Some(exp) => index = exp,
+            None => return None,
+        }
+
+        if ! self.expect_peek(token::RBRACKET) {
+            return None;
+        }
+
+        return Some(ast::Expression::Index(ast::IndexExpression::new(token, left, index)));
+    }
+
+    pub fn parse_infix_expression(&mut self, left: ast::Expression) -> Option<ast::Expression> {
+        let token: token::Token;
+        match self.cur_token.as_ref().cloned() {
+            Some(tok) => token = tok,
+            None => return None,
+        }
+
+        let precedence: i32 = self.cur_precedence();
+        self.next_token();
+        let right: ast::Expression;
+        match self.parse_expression(precedence) {
+            Some(exp) => right = exp,
+            None => return None,
+        }
+
+        return Some(ast::Expression::Infix(ast::InfixExpression::new(token.clone(), left, token.literal.clone(), right)));
+    }
+
+
+    pub fn expect_peek(&mut self, ttype: token::TokenType) -> bool {
+        if self.peek_token_is(ttype) {
+            self.next_token();
+            return true;
+        } else {
+            self.peek_error(ttype);
+            return false;
+        }
+    }
+
+    pub fn cur_token_is(&self, ttype: token::TokenType) -> bool {
+        match self.cur_token.as_ref() {
+            Some(tok) => return tok.ttype == ttype,
+            None => return false,
+        }
+    }
+
+    pub fn peek_token_is(&self, ttype: token::TokenType) -> bool {
+        match self.peek_token.as_ref().cloned() {
+            Some(tok) => return tok.ttype == ttype,
+            None => return false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ast::ast::Node;
+
+    #[test]
+    fn test_let_statement() {
+        let tests: [(&str, &str); 4] = [
+            ("let x = 5;",          "let x = 5;"),
+            ("let y = 10;",         "let y = 10;"),
+            ("let foobar = y;",     "let foobar = y;"),
+            ("let z = 5 + 5 * 2",   "let z = (5 + (5 * 2));"),
+        ];
+
+        let mut i = 0;
+        for &(input, expected) in tests.iter() {
+            let mut l = lexer::Lexer::new(input);
+            let mut p = Parser::new(&mut l);
+
+            let program = p.parse_program();
+
+            match program {
+                Ok(prog) => {
+                    if prog.statements.len() != 1 {
+                        assert!(false, "program.statements does not contain {} statements, got={}", 1, prog.statements.len());
+                    }
+                    assert_eq!(prog.statements[0].token_literal(), token::LET.as_str(), "tests[{}]", i);
+                    assert_eq!(prog.statements[0].to_string(), expected, "tests[{}]", i);
+                },
+                Err(errors) => check_parser_errors(&errors),
+            }
+
+            i += 1;
+        }
+    }
+
+    #[test]
+    fn test_return_statement() {
+        let tests: [(&str, &str); 4] = [
+            ("return 5;",           "return 5;"),
+            ("return 10;",          "return 10;"),
+            ("return foobar;",      "return foobar;"),
+            ("return -5 + 10",      "return ((-5) + 10);"),
+        ];
+
+        let mut i = 0;
+        for &(input, expected) in tests.iter() {
+            let mut l = lexer::Lexer::new(input);
+            let mut p = Parser::new(&mut l);
+
+            let program = p.parse_program();
 
             match program {
-                Some(prog) => {
+                Ok(prog) => {
                     if prog.statements.len() != 1 {
                         assert!(false, "program.statements does not contain {} statements, got={}", 1, prog.statements.len());
                     }
-                    assert_eq!((*prog.statements[0]).to_string(), format!("({});", &(*input)[..((*input).len()-1)]), "tests[{}]", i);
+                    assert_eq!(prog.statements[0].token_literal(), token::RETURN.as_str(), "tests[{}]", i);
+                    assert_eq!(prog.statements[0].to_string(), expected, "tests[{}]", i);
                 },
-                None => assert!(false, "parse_program() returns None"),
+                Err(errors) => check_parser_errors(&errors),
             }
 
             i += 1;
         }
     }
 
-    fn check_parser_errors(p: &Parser) {
-        if p.errors.len() == 0 {
+    #[test]
+    fn test_let_and_return_program() {
+        let input = "
+let x = 5;
+let y = x * 10;
+return x + y;
+";
+
+        let mut l = lexer::Lexer::new(input);
+        let mut p = Parser::new(&mut l);
+
+        let program = p.parse_program();
+
+        match program {
+            Ok(prog) => {
+                if prog.statements.len() != 3 {
+                    assert!(false, "program.statements does not contain {} statements, got={}", 3, prog.statements.len());
+                }
+                assert_eq!(prog.to_string(), "let x = 5;\nlet y = (x * 10);\nreturn (x + y);\n");
+            },
+            Err(errors) => check_parser_errors(&errors),
+        }
+    }
+
+    #[test]
+    fn test_identifier_expression() {
+        let input = "foobar;";
+
+        let mut l = lexer::Lexer::new(input);
+        let mut p = Parser::new(&mut l);
+
+        let program = p.parse_program();
+
+        match program {
+            Ok(prog) => {
+                if prog.statements.len() != 1 {
+                    assert!(false, "program.statements does not contain {} statements, got={}", 1, prog.statements.len());
+                }
+                assert_eq!(prog.statements[0].token_literal(), "foobar", "tests[{}]", 0);
+                match prog.statements[0] {
+                    ast::Statement::Expression(ast::ExpressionStatement{ expression: ast::Expression::Identifier(ref ident), .. }) => {
+                        assert_eq!(ident.value, "foobar");
+                    },
+                    ref stmt => assert!(false, "statement is not an Identifier expression, got={:?}", stmt),
+                }
+            },
+            Err(errors) => check_parser_errors(&errors),
+        }
+    }
+
+    #[test]
+    fn test_integer_literal_expression() {
+        let input = "5;";
+
+        let mut l = lexer::Lexer::new(input);
+        let mut p = Parser::new(&mut l);
+
+        let program = p.parse_program();
+
+        match program {
+            Ok(prog) => {
+                if prog.statements.len() != 1 {
+                    assert!(false, "program.statements does not contain {} statements, got={}", 1, prog.statements.len());
+                }
+                assert_eq!(prog.statements[0].token_literal(), "5", "tests[{}]", 0);
+                match prog.statements[0] {
+                    ast::Statement::Expression(ast::ExpressionStatement{ expression: ast::Expression::IntegerLiteral(ref il), .. }) => {
+                        assert_eq!(il.value, 5);
+                    },
+                    ref stmt => assert!(false, "statement is not an IntegerLiteral expression, got={:?}", stmt),
+                }
+            },
+            Err(errors) => check_parser_errors(&errors),
+        }
+    }
+
+    #[test]
+    fn test_boolean_expression() {
+        let input = "
+true;
+false;
+let foobar = true;
+let barfoo = false;
+";
+
+        let mut l = lexer::Lexer::new(input);
+        let mut p = Parser::new(&mut l);
+
+        let program = p.parse_program();
+
+        match program {
+            Ok(prog) => {
+                if prog.statements.len() != 4 {
+                    assert!(false, "program.statements does not contain {} statements, got={}", 4, prog.statements.len());
+                }
+                assert_eq!(prog.statements[0].to_string(), "true;", "tests[{}]", 0);
+                assert_eq!(prog.statements[1].to_string(), "false;", "tests[{}]", 1);
+                assert_eq!(prog.statements[2].to_string(), "let foobar = true;", "tests[{}]", 2);
+                assert_eq!(prog.statements[3].to_string(), "let barfoo = false;", "tests[{}]", 3);
+            },
+            Err(errors) => check_parser_errors(&errors),
+        }
+    }
+
+    #[test]
+    fn test_string_literal_expression() {
+        let input = r#""hello world"; "tab\there"; "say \"hi\"\n";"#;
+
+        let mut l = lexer::Lexer::new(input);
+        let mut p = Parser::new(&mut l);
+
+        let program = p.parse_program();
+
+        match program {
+            Ok(prog) => {
+                let expected: [(&str, &str); 3] = [
+                    ("hello world",     r#""hello world";"#),
+                    ("tab\there",       r#""tab\there";"#),
+                    ("say \"hi\"\n",    r#""say \"hi\"\n";"#),
+                ];
+                assert_eq!(prog.statements.len(), expected.len());
+
+                for (i, &(value, output)) in expected.iter().enumerate() {
+                    match prog.statements[i] {
+                        ast::Statement::Expression(ast::ExpressionStatement{ expression: ast::Expression::StringLiteral(ref sl), .. }) => {
+                            assert_eq!(sl.value, value, "tests[{}]", i);
+                        },
+                        _ => assert!(false, "tests[{}]: statement is not a string literal", i),
+                    }
+                    assert_eq!(prog.statements[i].to_string(), output, "tests[{}]", i);
+                }
+            },
+            Err(errors) => check_parser_errors(&errors),
+        }
+    }
+
+    #[test]
+    fn test_lexer_errors_are_reported() {
+        let tests: [(&str, &str); 3] = [
+            ("let s = \"abc;\nlet t = 1;",        "1:9: unterminated string literal"),
+            ("let s = \"a\\qb\";",                "1:11: invalid escape sequence \\q"),
+            ("let x = 1 @ 2;",                      "1:11: illegal character '@'"),
+        ];
+
+        let mut i = 0;
+        for &(input, expected) in tests.iter() {
+            let mut l = lexer::Lexer::new(input);
+            let mut p = Parser::new(&mut l);
+
+            match p.parse_program() {
+                Ok(_) => assert!(false, "tests[{}]: parser has no errors", i),
+                Err(errors) => {
+                    assert_eq!(errors[0].to_string(), expected, "tests[{}]", i);
+                    match errors[0] {
+                        ParseError::Lex(_) => (),
+                        _ => assert!(false, "tests[{}]: error is not a lexer error", i),
+                    }
+                },
+            }
+
+            i += 1;
+        }
+    }
+
+    #[test]
+    fn test_errors_after_unterminated_string() {
+        let input = "let s = \"abc;\nlet t = 1 @ 2;\nlet = 3;";
+
+        let mut l = lexer::Lexer::new(input);
+        let mut p = Parser::new(&mut l);
+
+        match p.parse_program() {
+            Ok(_) => assert!(false, "parser has no errors"),
+            Err(errors) => {
+                let messages: Vec<String> = errors.iter().map(|err| err.to_string()).collect();
+                assert!(messages.contains(&String::from("1:9: unterminated string literal")), "{:?}", messages);
+                assert!(messages.contains(&String::from("2:11: illegal character '@'")), "{:?}", messages);
+                assert!(messages.iter().any(|msg| msg.starts_with("3:5: ")), "{:?}", messages);
+            },
+        }
+    }
+
+    #[test]
+    fn test_parsing_array_literals() {
+        let input = "[1, 2 * 2, 3 + 3]; []";
+
+        let mut l = lexer::Lexer::new(input);
+        let mut p = Parser::new(&mut l);
+
+        let program = p.parse_program();
+
+        match program {
+            Ok(prog) => {
+                assert_eq!(prog.statements.len(), 2);
+                match prog.statements[0] {
+                    ast::Statement::Expression(ast::ExpressionStatement{ expression: ast::Expression::Array(ref al), .. }) => {
+                        assert_eq!(al.elements.len(), 3);
+                        assert_eq!(al.elements[0].to_string(), "1");
+                        assert_eq!(al.elements[1].to_string(), "(2 * 2)");
+                        assert_eq!(al.elements[2].to_string(), "(3 + 3)");
+                    },
+                    _ => assert!(false, "statement is not an array literal"),
+                }
+                assert_eq!(prog.statements[1].to_string(), "[];");
+            },
+            Err(errors) => check_parser_errors(&errors),
+        }
+    }
+
+    #[test]
+    fn test_parsing_hash_literals() {
+        let tests: [(&str, &str); 6] = [
+            ("{\"one\": 1, \"two\": 2, \"three\": 3}",   "{\"one\": 1, \"two\": 2, \"three\": 3};"),
+            ("{}",                                          "{};"),
+            ("{1: true, false: \"no\"}",                    "{1: true, false: \"no\"};"),
+            ("{\"one\": 0 + 1, \"two\": 10 - 8}",          "{\"one\": (0 + 1), \"two\": (10 - 8)};"),
+            ("let h = {\"a\": {\"b\": [1]}}; h[\"a\"]",       "let h = {\"a\": {\"b\": [1]}};\n(h[\"a\"]);"),
+            ("if (x) { {\"a\": 1} } else { {} }",            "if (x) { {\"a\": 1}; } else { {}; };"),
+        ];
+
+        let mut i = 0;
+        for &(input, expected) in tests.iter() {
+            let mut l = lexer::Lexer::new(input);
+            let mut p = Parser::new(&mut l);
+
+            match p.parse_program() {
+                Ok(prog) => {
+                    let output: Vec<String> = prog.statements.iter().map(|s| s.to_string()).collect();
+                    assert_eq!(output.join("\n"), expected, "tests[{}]", i);
+                },
+                Err(errors) => check_parser_errors(&errors),
+            }
+
+            i += 1;
+        }
+    }
+
+    #[test]
+    fn test_hash_literal_pairs() {
+        let input = "{\"one\": 1, \"two\": 2}";
+
+        let mut l = lexer::Lexer::new(input);
+        let mut p = Parser::new(&mut l);
+
+        match p.parse_program() {
+            Ok(prog) => match prog.statements[0] {
+                ast::Statement::Expression(ast::ExpressionStatement{ expression: ast::Expression::Hash(ref hl), .. }) => {
+                    assert_eq!(hl.pairs.len(), 2);
+                    assert_eq!(hl.pairs[0].0.to_string(), "\"one\"");
+                    assert_eq!(hl.pairs[0].1.to_string(), "1");
+                    assert_eq!(hl.pairs[1].0.to_string(), "\"two\"");
+                    assert_eq!(hl.pairs[1].1.to_string(), "2");
+                },
+                _ => assert!(false, "statement is not a hash literal"),
+            },
+            Err(errors) => check_parser_errors(&errors),
+        }
+    }
+
+    #[test]
+    fn test_hash_literal_errors() {
+        let tests: [(&str, &str); 3] = [
+            ("{\"a\" 1}",                 "1:6: expected next token to be :, got INT instead"),
+            ("{\"a\": 1 \"b\": 2}",        "1:9: expected next token to be ,, got STRING instead"),
+            ("{\"a\": 1",                 "1:8: unexpected end of input, expected }"),
+        ];
+
+        let mut i = 0;
+        for &(input, expected) in tests.iter() {
+            let mut l = lexer::Lexer::new(input);
+            let mut p = Parser::new(&mut l);
+
+            match p.parse_program() {
+                Ok(_) => assert!(false, "tests[{}]: parser has no errors", i),
+                Err(errors) => assert_eq!(errors[0].to_string(), expected, "tests[{}]", i),
+            }
+
+            i += 1;
+        }
+    }
+
+    #[test]
+    fn test_parsing_index_expressions() {
+        let input = "myArray[1 + 1]";
+
+        let mut l = lexer::Lexer::new(input);
+        let mut p = Parser::new(&mut l);
+
+        let program = p.parse_program();
+
+        match program {
+            Ok(prog) => match prog.statements[0] {
+                ast::Statement::Expression(ast::ExpressionStatement{ expression: ast::Expression::Index(ref ie), .. }) => {
+                    assert_eq!(ie.left.to_string(), "myArray");
+                    assert_eq!(ie.index.to_string(), "(1 + 1)");
+                },
+                _ => assert!(false, "statement is not an index expression"),
+            },
+            Err(errors) => check_parser_errors(&errors),
+        }
+    }
+
+    #[test]
+    fn test_to_string_round_trips() {
+        let tests: [&str; 5] = [
+            "let a = [1, \"two\", [3]];",
+            "a[0][1] + f(b)[c];",
+            "a[0](x);",
+            "fn(x) { x[0] }([1, 2]);",
+            "[fn(x) { x }][0](1);",
+        ];
+
+        for (i, input) in tests.iter().enumerate() {
+            let mut l = lexer::Lexer::new(input);
+            let mut p = Parser::new(&mut l);
+            match p.parse_program() {
+                Ok(prog) => {
+                    let first: String = prog.to_string();
+
+                    let mut l = lexer::Lexer::new(&first);
+                    let mut p = Parser::new(&mut l);
+                    match p.parse_program() {
+                        Ok(prog) => assert_eq!(prog.to_string(), first, "tests[{}]", i),
+                        Err(errors) => check_parser_errors(&errors),
+                    }
+                },
+                Err(errors) => check_parser_errors(&errors),
+            }
+        }
+    }
+
+    #[test]
+    fn test_parsing_prefix_expressions() {
+        let input = "
+!5;
+-5;
+!true;
+!false";
+
+        let mut l = lexer::Lexer::new(input);
+        let mut p = Parser::new(&mut l);
+
+        let program = p.parse_program();
+
+        match program {
+            Ok(prog) => {
+                if prog.statements.len() != 4 {
+                    assert!(false, "program.statements does not contain {} statements, got={}", 4, prog.statements.len());
+                }
+                assert_eq!(prog.statements[0].to_string(), "(!5);", "tests[{}]", 0);
+                assert_eq!(prog.statements[1].to_string(), "(-5);", "tests[{}]", 1);
+                assert_eq!(prog.statements[2].to_string(), "(!true);", "tests[{}]", 2);
+                assert_eq!(prog.statements[3].to_string(), "(!false);", "tests[{}]", 3);
+            },
+            Err(errors) => check_parser_errors(&errors),
+        }
+    }
+
+    #[test]
+    fn test_parsing_infix_expressions() {
+        let infix_tests: [String; 16] = [
+            String::from("5 + 5;"),
+            String::from("5 - 5;"),
+            String::from("5 * 5;"),
+            String::from("5 / 5;"),
+            String::from("5 > 5;"),
+            String::from("5 < 5;"),
+            String::from("5 == 5;"),
+            String::from("5 != 5;"),
+            String::from("true == true;"),
+            String::from("true != false;"),
+            String::from("false == false;"),
+            String::from("5 % 5;"),
+            String::from("5 <= 5;"),
+            String::from("5 >= 5;"),
+            String::from("true && false;"),
+            String::from("true || false;"),
+        ];
+
+        let mut i = 0;
+        for input in infix_tests.iter() {
+            let mut l = lexer::Lexer::new(input);
+            let mut p = Parser::new(&mut l);
+
+            let program = p.parse_program();
+
+            match program {
+                Ok(prog) => {
+                    if prog.statements.len() != 1 {
+                        assert!(false, "program.statements does not contain {} statements, got={}", 1, prog.statements.len());
+                    }
+                    assert_eq!(prog.statements[0].to_string(), format!("({});", &(*input)[..((*input).len()-1)]), "tests[{}]", i);
+                },
+                Err(errors) => check_parser_errors(&errors),
+            }
+
+            i += 1;
+        }
+    }
+
+    #[test]
+    fn test_operator_precedence_parsing() {
+        let tests: [(&str, &str); 36] = [
+            ("-a * b",                      "((-a) * b);\n"),
+            ("!-a",                         "(!(-a));\n"),
+            ("a + b + c",                   "((a + b) + c);\n"),
+            ("a + b - c",                   "((a + b) - c);\n"),
+            ("a * b * c",                   "((a * b) * c);\n"),
+            ("a * b / c",                   "((a * b) / c);\n"),
+            ("a + b / c",                   "(a + (b / c));\n"),
+            ("a + b * c + d / e - f",       "(((a + (b * c)) + (d / e)) - f);\n"),
+            ("3 + 4; -5 * 5",               "(3 + 4);\n((-5) * 5);\n"),
+            ("5 > 4 == 3 < 4",              "((5 > 4) == (3 < 4));\n"),
+            ("5 < 4 != 3 > 4",              "((5 < 4) != (3 > 4));\n"),
+            ("3 + 4 * 5 == 3 * 1 + 4 * 5",  "((3 + (4 * 5)) == ((3 * 1) + (4 * 5)));\n"),
+            ("true",                        "true;\n"),
+            ("false",                       "false;\n"),
+            ("3 > 5 == false",              "((3 > 5) == false);\n"),
+            ("3 < 5 == true",               "((3 < 5) == true);\n"),
+            ("!true == false",              "((!true) == false);\n"),
+            ("1 + (2 + 3) + 4",             "((1 + (2 + 3)) + 4);\n"),
+            ("(5 + 5) * 2",                 "((5 + 5) * 2);\n"),
+            ("2 / (5 + 5)",                 "(2 / (5 + 5));\n"),
+            ("-(5 + 5)",                    "(-(5 + 5));\n"),
+            ("!(true == true)",             "(!(true == true));\n"),
+            ("((a))",                       "a;\n"),
+            ("a + add(b * c) + d",          "((a + add((b * c))) + d);\n"),
+            ("add(a, b, 1, 2 * 3, 4 + 5, add(6, 7 * 8))", "add(a, b, 1, (2 * 3), (4 + 5), add(6, (7 * 8)));\n"),
+            ("add(a + b + c * d / f + g)",  "add((((a + b) + ((c * d) / f)) + g));\n"),
+            ("a + b % c * d",               "(a + ((b % c) * d));\n"),
+            ("a <= b == c >= d",            "((a <= b) == (c >= d));\n"),
+            ("a == b && c != d",            "((a == b) && (c != d));\n"),
+            ("a || b && c",                 "(a || (b && c));\n"),
+            ("a && b || c && d",            "((a && b) || (c && d));\n"),
+            ("!a || b < c",                 "((!a) || (b < c));\n"),
+            ("a * [1, 2, 3, 4][b * c] * d", "((a * ([1, 2, 3, 4][(b * c)])) * d);\n"),
+            ("add(a * b[2], b[1], 2 * [1, 2][1])", "add((a * (b[2])), (b[1]), (2 * ([1, 2][1])));\n"),
+            ("myArray[1 + 1]",              "(myArray[(1 + 1)]);\n"),
+            ("a[0](x)",                     "(a[0])(x);\n"),
+        ];
+
+        let mut i = 0;
+        for &(input, expected) in tests.iter() {
+            let mut l = lexer::Lexer::new(input);
+            let mut p = Parser::new(&mut l);
+
+            let program = p.parse_program();
+
+            match program {
+                Ok(prog) => assert_eq!(prog.to_string(), expected, "tests[{}]", i),
+                Err(errors) => check_parser_errors(&errors),
+            }
+
+            i += 1;
+        }
+    }
+
+    #[test]
+    fn test_if_expression() {
+        let input = "if (x < y) { x }";
+
+        let mut l = lexer::Lexer::new(input);
+        let mut p = Parser::new(&mut l);
+
+        let program = p.parse_program();
+
+        match program {
+            Ok(prog) => {
+                if prog.statements.len() != 1 {
+                    assert!(false, "program.statements does not contain {} statements, got={}", 1, prog.statements.len());
+                }
+                assert_eq!(prog.statements[0].token_literal(), token::IF.as_str(), "tests[{}]", 0);
+                assert_eq!(prog.statements[0].to_string(), "if ((x < y)) { x; };", "tests[{}]", 0);
+            },
+            Err(errors) => check_parser_errors(&errors),
+        }
+    }
+
+    #[test]
+    fn test_if_else_expression() {
+        let tests: [(&str, &str); 4] = [
+            ("if (x < y) { x } else { y }",                 "if ((x < y)) { x; } else { y; };\n"),
+            ("if (x) { let z = x; z } else { }",            "if (x) { let z = x; z; } else { };\n"),
+            ("let max = if (x > y) { x } else { y };",      "let max = if ((x > y)) { x; } else { y; };\n"),
+            ("if (a) { if (b) { 1 } else { 2 } } else { 3 }", "if (a) { if (b) { 1; } else { 2; }; } else { 3; };\n"),
+        ];
+
+        let mut i = 0;
+        for &(input, expected) in tests.iter() {
+            let mut l = lexer::Lexer::new(input);
+            let mut p = Parser::new(&mut l);
+
+            let program = p.parse_program();
+
+            match program {
+                Ok(prog) => assert_eq!(prog.to_string(), expected, "tests[{}]", i),
+                Err(errors) => check_parser_errors(&errors),
+            }
+
+            i += 1;
+        }
+    }
+
+    #[test]
+    fn test_function_literal_parsing() {
+        let input = "fn(x, y) { x + y; }";
+
+        let mut l = lexer::Lexer::new(input);
+        let mut p = Parser::new(&mut l);
+
+        let program = p.parse_program();
+
+        match program {
+            Ok(prog) => {
+                if prog.statements.len() != 1 {
+                    assert!(false, "program.statements does not contain {} statements, got={}", 1, prog.statements.len());
+                }
+                assert_eq!(prog.statements[0].token_literal(), token::FUNCTION.as_str(), "tests[{}]", 0);
+                assert_eq!(prog.statements[0].to_string(), "fn(x, y) { (x + y); };", "tests[{}]", 0);
+            },
+            Err(errors) => check_parser_errors(&errors),
+        }
+    }
+
+    #[test]
+    fn test_function_parameter_parsing() {
+        let tests: [(&str, &str); 3] = [
+            ("fn() {};",            "fn() { };\n"),
+            ("fn(x) {};",           "fn(x) { };\n"),
+            ("fn(x, y, z) {};",     "fn(x, y, z) { };\n"),
+        ];
+
+        let mut i = 0;
+        for &(input, expected) in tests.iter() {
+            let mut l = lexer::Lexer::new(input);
+            let mut p = Parser::new(&mut l);
+
+            let program = p.parse_program();
+
+            match program {
+                Ok(prog) => assert_eq!(prog.to_string(), expected, "tests[{}]", i),
+                Err(errors) => check_parser_errors(&errors),
+            }
+
+            i += 1;
+        }
+    }
+
+    #[test]
+    fn test_call_expression_parsing() {
+        let tests: [(&str, &str); 4] = [
+            ("add(1, 2 * 3, 4 + 5);",       "add(1, (2 * 3), (4 + 5));\n"),
+            ("add();",                      "add();\n"),
+            ("fn(x) { x; }(5)",             "fn(x) { x; }(5);\n"),
+            ("callback(a)(b)",              "callback(a)(b);\n"),
+        ];
+
+        let mut i = 0;
+        for &(input, expected) in tests.iter() {
+            let mut l = lexer::Lexer::new(input);
+            let mut p = Parser::new(&mut l);
+
+            let program = p.parse_program();
+
+            match program {
+                Ok(prog) => assert_eq!(prog.to_string(), expected, "tests[{}]", i),
+                Err(errors) => check_parser_errors(&errors),
+            }
+
+            i += 1;
+        }
+    }
+
+    #[test]
+    fn test_lexer_sample_program() {
+        let input = "let five = 5;
+let ten = 10;
+
+let add = fn(x, y) {
+    x + y;
+};
+
+let result = add(five, ten);
+";
+
+        let mut l = lexer::Lexer::new(input);
+        let mut p = Parser::new(&mut l);
+
+        let program = p.parse_program();
+
+        match program {
+            Ok(prog) => {
+                if prog.statements.len() != 4 {
+                    assert!(false, "program.statements does not contain {} statements, got={}", 4, prog.statements.len());
+                }
+                assert_eq!(prog.to_string(), "let five = 5;\nlet ten = 10;\nlet add = fn(x, y) { (x + y); };\nlet result = add(five, ten);\n");
+            },
+            Err(errors) => check_parser_errors(&errors),
+        }
+    }
+
+    #[test]
+    fn test_unterminated_block_statement() {
+        let input = "if (x) { x";
+
+        let mut l = lexer::Lexer::new(input);
+        let mut p = Parser::new(&mut l);
+
+        match p.parse_program() {
+            Ok(_) => assert!(false, "parser has no errors"),
+            Err(errors) => {
+                assert_eq!(errors[0], ParseError::UnexpectedEof{ expected: Some(token::RBRACE), span: token::Span::new(10, 10, 1, 11) });
+                assert_eq!(errors[0].to_string(), "1:11: unexpected end of input, expected }");
+            },
+        }
+    }
+
+    #[test]
+    fn test_unbalanced_grouped_expression() {
+        let input = "(5 + 5 * 2;";
+
+        let mut l = lexer::Lexer::new(input);
+        let mut p = Parser::new(&mut l);
+
+        match p.parse_program() {
+            Ok(_) => assert!(false, "parser has no errors"),
+            Err(errors) => {
+                match errors[0] {
+                    ParseError::UnexpectedToken{ expected, ref found } => {
+                        assert_eq!(expected, token::RPAREN);
+                        assert_eq!(found.ttype, token::SEMICOLON);
+                    },
+                    ref err => assert!(false, "error is not UnexpectedToken, got={:?}", err),
+                }
+                assert_eq!(errors[0].to_string(), "1:11: expected next token to be ), got ; instead");
+            },
+        }
+    }
+
+    #[test]
+    fn test_infix_expression_structure() {
+        let input = "add(x) == !y;";
+
+        let mut l = lexer::Lexer::new(input);
+        let mut p = Parser::new(&mut l);
+
+        match p.parse_program() {
+            Ok(prog) => match prog.statements[0] {
+                ast::Statement::Expression(ast::ExpressionStatement{ expression: ast::Expression::Infix(ref ie), .. }) => {
+                    assert_eq!(ie.operator, "==");
+                    match *ie.left {
+                        ast::Expression::Call(ref ce) => {
+                            assert_eq!(ce.function.to_string(), "add");
+                            assert_eq!(ce.arguments.len(), 1);
+                        },
+                        ref exp => assert!(false, "left is not a Call expression, got={:?}", exp),
+                    }
+                    match *ie.right {
+                        ast::Expression::Prefix(ref pe) => assert_eq!(pe.operator, "!"),
+                        ref exp => assert!(false, "right is not a Prefix expression, got={:?}", exp),
+                    }
+                },
+                ref stmt => assert!(false, "statement is not an Infix expression, got={:?}", stmt),
+            },
+            Err(errors) => check_parser_errors(&errors),
+        }
+    }
+
+    #[test]
+    fn test_parser_error_positions() {
+        let tests: [(&str, &str); 5] = [
+            ("let x = ",                            "1:9: unexpected end of input, expected an expression"),
+            ("fn(x",                                "1:5: unexpected end of input, expected )"),
+            ("let x 5;",                            "1:7: expected next token to be =, got INT instead"),
+            ("let x = 1;\nlet y = 2 +;",            "2:12: no prefix parse function for ; found"),
+            ("let x = 99999999999999999999;",       "1:9: could not parse 99999999999999999999 as integer: number too large to fit in target type"),
+        ];
+
+        let mut i = 0;
+        for &(input, expected) in tests.iter() {
+            let mut l = lexer::Lexer::new(input);
+            let mut p = Parser::new(&mut l);
+
+            match p.parse_program() {
+                Ok(_) => assert!(false, "tests[{}]: parser has no errors", i),
+                Err(errors) => assert_eq!(errors[0].to_string(), expected, "tests[{}]", i),
+            }
+
+            i += 1;
+        }
+    }
+
+    #[test]
+    fn test_nodes_carry_spans() {
+        let input = "let x = 5;\n  add(x, 10);";
+
+        let mut l = lexer::Lexer::new(input);
+        let mut p = Parser::new(&mut l);
+
+        let program = p.parse_program();
+
+        match program {
+            Ok(prog) => {
+                assert_eq!(prog.span(), token::Span::new(0, 3, 1, 1));
+                assert_eq!(prog.statements[0].span(), token::Span::new(0, 3, 1, 1));
+                assert_eq!(prog.statements[1].span(), token::Span::new(13, 16, 2, 3));
+            },
+            Err(errors) => check_parser_errors(&errors),
+        }
+    }
+
+    fn check_parser_errors(errors: &[ParseError]) {
+        if errors.len() == 0 {
             return;
         }
         let mut output: String = String::new();
-        output.push_str(&format!("\n\nparser has {} errors", p.errors.len()));
-        for err in p.errors.iter() {
+        output.push_str(&format!("\n\nparser has {} errors", errors.len()));
+        for err in errors.iter() {
             output.push_str(&format!("\nparser error: {}", err));
         }
         output.push_str("\n\n");
-        assert!(false, output);
+        assert!(false, "{}", output);
     }
 }
 
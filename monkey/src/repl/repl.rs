@@ -0,0 +1,190 @@
+use std::cell::RefCell;
+use std::io;
+use std::io::{BufRead, Write};
+use std::rc::Rc;
+use compiler::*;
+use evaluator::*;
+use lexer::*;
+use object::*;
+use parser::*;
+use vm::*;
+use parser::error::ParseError;
+
+pub static PROMPT: &str = ">> ";
+
+// Reads, evaluates and prints lines until the input ends. Fails if the input
+// cannot be read or the output cannot be written, e.g. because it was closed.
+pub fn start<R: BufRead, W: Write>(input: &mut R, output: &mut W) -> io::Result<()> {
+    let env: Rc<RefCell<environment::Environment>> = Rc::new(RefCell::new(environment::Environment::new()));
+
+    loop {
+        write!(output, "{}", PROMPT)?;
+        output.flush()?;
+
+        let mut line: String = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+
+        let mut l = lexer::Lexer::new(&line);
+        let mut p = parser::Parser::new(&mut l);
+
+        match p.parse_program() {
+            Ok(prog) => match evaluator::eval_program(&prog, &env) {
+                Some(object::Object::Error(err)) => writeln!(output, "{}", err.traceback(None))?,
+                Some(evaluated) => writeln!(output, "{}", evaluated.inspect())?,
+                None => (),
+            },
+            Err(errors) => print_parser_errors(output, &errors)?,
+        }
+    }
+}
+
+// Evaluates a whole script. Parser errors and the traceback of a runtime error,
+// quoting the lines of source, go to errors. Returns whether the script ran to
+// completion, or the error that writing to errors failed with.
+pub fn run_script<W: Write>(source: &str, errors: &mut W) -> io::Result<bool> {
+    let env: Rc<RefCell<environment::Environment>> = Rc::new(RefCell::new(environment::Environment::new()));
+
+    let mut l = lexer::Lexer::new(source);
+    let mut p = parser::Parser::new(&mut l);
+
+    return match p.parse_program() {
+        Ok(prog) => match evaluator::eval_program(&prog, &env) {
+            Some(object::Object::Error(err)) => {
+                writeln!(errors, "{}", err.traceback(Some(source)))?;
+                Ok(false)
+            },
+            _ => Ok(true),
+        },
+        Err(parse_errors) => {
+            print_parser_errors(errors, &parse_errors)?;
+            Ok(false)
+        },
+    };
+}
+
+// Runs compiled code on the VM. The traceback of a runtime error goes to errors,
+// quoting the lines of source when it is known. Returns whether the program ran
+// to completion, or the error that writing to errors failed with.
+pub fn run_bytecode<W: Write>(bytecode: compiler::Bytecode, source: Option<&str>, errors: &mut W) -> io::Result<bool> {
+    let mut machine = vm::VM::new(bytecode);
+    return match machine.run() {
+        Ok(()) => Ok(true),
+        Err(err) => {
+            writeln!(errors, "{}", err.traceback(source))?;
+            Ok(false)
+        },
+    };
+}
+
+fn print_parser_errors<W: Write>(output: &mut W, errors: &[ParseError]) -> io::Result<()> {
+    writeln!(output, "parser errors:")?;
+    for msg in errors.iter() {
+        writeln!(output, "\t{}", msg)?;
+    }
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn run(input: &str) -> String {
+        let mut reader = Cursor::new(input.as_bytes());
+        let mut output: Vec<u8> = Vec::new();
+        start(&mut reader, &mut output).unwrap();
+        return String::from_utf8(output).unwrap();
+    }
+
+    // A writer whose reader has gone away, like stdout piped into `head`.
+    struct ClosedPipe;
+
+    impl Write for ClosedPipe {
+        fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+            return Err(io::Error::new(io::ErrorKind::BrokenPipe, "broken pipe"));
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            return Ok(());
+        }
+    }
+
+    #[test]
+    fn test_closed_output_is_an_error() {
+        let mut reader = Cursor::new("1 + 2\n".as_bytes());
+        match start(&mut reader, &mut ClosedPipe) {
+            Err(ref err) if err.kind() == io::ErrorKind::BrokenPipe => (),
+            other => assert!(false, "start did not fail with a broken pipe, got={:?}", other),
+        }
+
+        match run_script("5 + true", &mut ClosedPipe) {
+            Err(ref err) if err.kind() == io::ErrorKind::BrokenPipe => (),
+            other => assert!(false, "run_script did not fail with a broken pipe, got={:?}", other),
+        }
+
+        // nothing to report, so nothing is written
+        assert!(run_script("5 + 5", &mut ClosedPipe).unwrap());
+    }
+
+    #[test]
+    fn test_environment_persists_across_lines() {
+        let output = run("let x = 5;\nlet add = fn(a, b) { a + b };\nadd(x, 10)\n");
+        assert_eq!(output, ">> >> >> 15\n>> ");
+    }
+
+    #[test]
+    fn test_parser_errors_are_printed() {
+        let output = run("let x 5;\n");
+        assert_eq!(output, ">> parser errors:\n\t1:7: expected next token to be =, got INT instead\n>> ");
+    }
+
+    #[test]
+    fn test_runtime_errors_are_printed() {
+        let output = run("5 + true\n");
+        assert_eq!(output, "\
+>> Traceback (most recent call last):
+  line 1, column 3, in <main>
+TypeMismatch: type mismatch: INTEGER + BOOLEAN
+>> ");
+    }
+
+    #[test]
+    fn test_run_script() {
+        let mut errors: Vec<u8> = Vec::new();
+        assert!(run_script("let f = fn(x) { x * 2 };\nf(21);\n", &mut errors).unwrap());
+        assert_eq!(String::from_utf8(errors).unwrap(), "");
+
+        let mut errors: Vec<u8> = Vec::new();
+        assert!(! run_script("let f = fn(x) {\n  x(1)\n};\nf(21);\n", &mut errors).unwrap());
+        assert_eq!(String::from_utf8(errors).unwrap(), "\
+Traceback (most recent call last):
+  line 4, column 2, in <main>
+    f(21);
+  line 2, column 4, in f
+    x(1)
+NotAFunction: not a function: INTEGER
+");
+
+        let mut errors: Vec<u8> = Vec::new();
+        assert!(! run_script("let x 5;", &mut errors).unwrap());
+        assert_eq!(String::from_utf8(errors).unwrap(), "parser errors:\n\t1:7: expected next token to be =, got INT instead\n");
+    }
+
+    #[test]
+    fn test_run_bytecode() {
+        let source: &str = "let f = fn(x) {\n  x(1)\n};\nf(21);\n";
+        let mut l = lexer::Lexer::new(source);
+        let mut p = parser::Parser::new(&mut l);
+        let mut c = compiler::Compiler::new();
+        c.compile_program(&p.parse_program().unwrap()).unwrap();
+
+        let mut errors: Vec<u8> = Vec::new();
+        assert!(! run_bytecode(c.bytecode(), Some(source), &mut errors).unwrap());
+
+        let mut script_errors: Vec<u8> = Vec::new();
+        run_script(source, &mut script_errors).unwrap();
+        assert_eq!(String::from_utf8(errors).unwrap(), String::from_utf8(script_errors).unwrap());
+    }
+}
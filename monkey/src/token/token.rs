@@ -1,55 +1,148 @@
-pub type TokenType = &'static str;
+use std::fmt;
+
+pub use self::TokenType::*;
+
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TokenType {
+    // Metacharacters
+    ILLEGAL,        // unrecognized character
+    EOF,            // end-of-file
+
+    // Identifiers + Literals
+    IDENT,          // add, foobar, x, y, ...
+    INT,            // 1343456
+    STRING,         // "foobar"
+    LET,
+    FUNCTION,
+    RETURN,
+    TRUE,
+    FALSE,
+    IF,
+    ELSE,
+
+    // Operators
+    ASSIGN,
+    PLUS,
+    BANG,
+    MINUS,
+    EQ,
+    NOT_EQ,
+    LT,
+    GT,
+    LT_EQ,
+    GT_EQ,
+    AND,
+    OR,
+    SLASH,
+    ASTERISK,
+    PERCENT,
+
+    // Delimiters
+    COMMA,
+    SEMICOLON,
+    COLON,
+
+    // Collections + Scopes
+    LPAREN,
+    RPAREN,
+    LBRACE,
+    RBRACE,
+    LBRACKET,
+    RBRACKET,
+}
+
+impl TokenType {
+    pub fn as_str(&self) -> &'static str {
+        return match *self {
+            ILLEGAL     => "ILLEGAL",
+            EOF         => "EOF",
+            IDENT       => "IDENT",
+            INT         => "INT",
+            STRING      => "STRING",
+            LET         => "let",
+            FUNCTION    => "fn",
+            RETURN      => "return",
+            TRUE        => "true",
+            FALSE       => "false",
+            IF          => "if",
+            ELSE        => "else",
+            ASSIGN      => "=",
+            PLUS        => "+",
+            BANG        => "!",
+            MINUS       => "-",
+            EQ          => "==",
+            NOT_EQ      => "!=",
+            LT          => "<",
+            GT          => ">",
+            LT_EQ       => "<=",
+            GT_EQ       => ">=",
+            AND         => "&&",
+            OR          => "||",
+            SLASH       => "/",
+            ASTERISK    => "*",
+            PERCENT     => "%",
+            COMMA       => ",",
+            SEMICOLON   => ";",
+            COLON       => ":",
+            LPAREN      => "(",
+            RPAREN      => ")",
+            LBRACE      => "{",
+            RBRACE      => "}",
+            LBRACKET    => "[",
+            RBRACKET    => "]",
+        };
+    }
+}
+
+impl fmt::Display for TokenType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "{}", self.as_str());
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Span {
+    pub start:      usize,      // byte offset of the first character
+    pub end:        usize,      // byte offset just past the last character
+    pub line:       usize,      // line of the first character, starting at 1
+    pub column:     usize,      // column of the first character, starting at 1
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize, line: usize, column: usize) -> Span {
+        return Span{ start: start, end: end, line: line, column: column };
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "{}:{}", self.line, self.column);
+    }
+}
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Token {
     pub ttype:      TokenType,
     pub literal:    String,
+    pub span:       Span,
 }
 
 impl Token {
     pub fn new(ttype: TokenType, literal: String) -> Token {
-        return Token{ ttype: ttype, literal: literal };
+        return Token{ ttype: ttype, literal: literal, span: Span::default() };
     }
 }
 
-// Metacharacters
-pub static ILLEGAL:     TokenType       = "ILLEGAL";    // unrecognized character
-pub static EOF:         TokenType       = "EOF";        // end-of-file
-
-// Identifiers + Literals
-pub static IDENT:       TokenType       = "IDENT";      // add, foobar, x, y, ...
-pub static INT:         TokenType       = "INT";        // 1343456
-pub static LET:         TokenType       = "let";
-pub static FUNCTION:    TokenType       = "fn";
-pub static RETURN:      TokenType       = "return";
-
-// Operators
-pub static ASSIGN:      TokenType       = "=";
-pub static PLUS:        TokenType       = "+";
-pub static BANG:        TokenType       = "!";
-pub static MINUS:       TokenType       = "-";
-pub static EQ:          TokenType       = "==";
-pub static NOT_EQ:      TokenType       = "!=";
-pub static LT:          TokenType       = "<";
-pub static GT:          TokenType       = ">";
-pub static SLASH:       TokenType       = "/";
-pub static ASTERISK:    TokenType       = "*";
-
-// Delimiters
-pub static COMMA:       TokenType       = ",";
-pub static SEMICOLON:   TokenType       = ";";
-
-// Collections + Scopes
-pub static LPAREN:      TokenType       = "(";
-pub static RPAREN:      TokenType       = ")";
-pub static LBRACE:      TokenType       = "{";
-pub static RBRACE:      TokenType       = "}";
-
-pub fn lookup_ident(ident: &String) -> TokenType {
-    match ident.as_str() {
+pub fn lookup_ident(ident: &str) -> TokenType {
+    match ident {
         "fn"        => FUNCTION,
         "let"       => LET,
         "return"    => RETURN,
+        "true"      => TRUE,
+        "false"     => FALSE,
+        "if"        => IF,
+        "else"      => ELSE,
         _           => IDENT,
     }
 }
@@ -63,6 +156,24 @@ mod tests {
         assert_eq!(lookup_ident(&String::from("fn")),       FUNCTION);
         assert_eq!(lookup_ident(&String::from("let")),      LET);
         assert_eq!(lookup_ident(&String::from("return")),   RETURN);
+        assert_eq!(lookup_ident(&String::from("true")),     TRUE);
+        assert_eq!(lookup_ident(&String::from("false")),    FALSE);
+        assert_eq!(lookup_ident(&String::from("if")),       IF);
+        assert_eq!(lookup_ident(&String::from("else")),     ELSE);
+        assert_eq!(lookup_ident(&String::from("truthy")),   IDENT);
+    }
+
+    #[test]
+    fn test_token_type_display() {
+        assert_eq!(IDENT.to_string(),       "IDENT");
+        assert_eq!(LET.to_string(),         "let");
+        assert_eq!(NOT_EQ.to_string(),      "!=");
+        assert_eq!(format!("expected {}, got {}", RPAREN, EOF), "expected ), got EOF");
+    }
+
+    #[test]
+    fn test_span_display() {
+        assert_eq!(Span::new(14, 17, 2, 1).to_string(), "2:1");
     }
 }
 
@@ -0,0 +1,100 @@
+use std::error::Error;
+use std::fmt;
+use object::*;
+
+// Runtime errors of the VM. The messages match the error objects produced by
+// the evaluator, so both engines report the same failure the same way.
+#[derive(Clone, Debug, PartialEq)]
+pub enum VmError {
+    TypeMismatch {                      // binary operator applied to operands of different types
+        left:       object::ObjectType,
+        operator:   &'static str,
+        right:      object::ObjectType,
+    },
+    UnknownOperator {                   // binary operator not defined for the operand type
+        left:       object::ObjectType,
+        operator:   &'static str,
+        right:      object::ObjectType,
+    },
+    UnknownPrefixOperator {             // prefix operator not defined for the operand type
+        operator:   &'static str,
+        right:      object::ObjectType,
+    },
+    DivisionByZero,
+    NotAFunction(object::ObjectType),
+    WrongArgumentCount {
+        want:       usize,
+        got:        usize,
+    },
+    IndexNotSupported(object::ObjectType),
+    UnusableHashKey(object::ObjectType),
+    Builtin(error::ErrorKind, String),  // a builtin function rejected its arguments
+    StackOverflow,
+    StackUnderflow,                     // an instruction takes more values than the stack holds
+    InvalidOpcode(u8),                  // the instructions contain a byte that is no opcode
+    TruncatedInstruction,               // the operands of an instruction run past the end of the instructions
+    InvalidOperand {                    // the operand names a constant, local, builtin or free variable that does not exist
+        opcode:     &'static str,
+        operand:    usize,
+    },
+}
+
+impl VmError {
+    // The kind of the matching evaluator error; failures only the VM can run
+    // into have kinds of their own.
+    pub fn kind(&self) -> error::ErrorKind {
+        return match *self {
+            VmError::TypeMismatch{ .. } => error::ErrorKind::TypeMismatch,
+            VmError::UnknownOperator{ .. } => error::ErrorKind::UnknownOperator,
+            VmError::UnknownPrefixOperator{ .. } => error::ErrorKind::UnknownOperator,
+            VmError::DivisionByZero => error::ErrorKind::DivisionByZero,
+            VmError::NotAFunction(_) => error::ErrorKind::NotAFunction,
+            VmError::WrongArgumentCount{ .. } => error::ErrorKind::WrongArgumentCount,
+            VmError::IndexNotSupported(_) => error::ErrorKind::IndexNotSupported,
+            VmError::UnusableHashKey(_) => error::ErrorKind::UnusableHashKey,
+            VmError::Builtin(kind, _) => kind,
+            VmError::StackOverflow => error::ErrorKind::StackOverflow,
+            VmError::StackUnderflow => error::ErrorKind::InvalidBytecode,
+            VmError::InvalidOpcode(_) => error::ErrorKind::InvalidBytecode,
+            VmError::TruncatedInstruction => error::ErrorKind::InvalidBytecode,
+            VmError::InvalidOperand{ .. } => error::ErrorKind::InvalidBytecode,
+        };
+    }
+}
+
+impl fmt::Display for VmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match *self {
+            VmError::TypeMismatch{ left, operator, right } =>
+                write!(f, "type mismatch: {} {} {}", left, operator, right),
+            VmError::UnknownOperator{ left, operator, right } =>
+                write!(f, "unknown operator: {} {} {}", left, operator, right),
+            VmError::UnknownPrefixOperator{ operator, right } =>
+                write!(f, "unknown operator: {}{}", operator, right),
+            VmError::DivisionByZero =>
+                write!(f, "division by zero"),
+            VmError::NotAFunction(found) =>
+                write!(f, "not a function: {}", found),
+            VmError::WrongArgumentCount{ want, got } =>
+                write!(f, "wrong number of arguments: want={}, got={}", want, got),
+            VmError::IndexNotSupported(found) =>
+                write!(f, "index operator not supported: {}", found),
+            VmError::UnusableHashKey(found) =>
+                write!(f, "unusable as hash key: {}", found),
+            VmError::Builtin(_, ref message) =>
+                write!(f, "{}", message),
+            VmError::StackOverflow =>
+                write!(f, "stack overflow"),
+            VmError::StackUnderflow =>
+                write!(f, "stack underflow"),
+            VmError::InvalidOpcode(byte) =>
+                write!(f, "invalid opcode {}", byte),
+            VmError::TruncatedInstruction =>
+                write!(f, "operands run past the end of the instructions"),
+            VmError::InvalidOperand{ opcode, operand } =>
+                write!(f, "invalid operand {} of {}", operand, opcode),
+        };
+    }
+}
+
+impl Error for VmError {}
@@ -0,0 +1,19 @@
+use std::rc::Rc;
+use object::*;
+
+// The activation record of a function call.
+pub struct Frame {
+    pub closure:        Rc<object::Closure>,
+    pub ip:             usize,          // position of the next instruction to execute
+    pub base_pointer:   usize,          // stack index of the first local of the call
+}
+
+impl Frame {
+    pub fn new(closure: Rc<object::Closure>, base_pointer: usize) -> Frame {
+        return Frame{
+            closure:        closure,
+            ip:             0,
+            base_pointer:   base_pointer,
+        };
+    }
+}
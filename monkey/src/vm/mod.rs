@@ -0,0 +1,5 @@
+pub mod error;
+
+pub mod frame;
+
+pub mod vm;
@@ -0,0 +1,668 @@
+use std::cmp;
+use std::collections::HashMap;
+use std::mem;
+use std::rc::Rc;
+use code::*;
+use compiler::*;
+use object::*;
+use object::error::RuntimeError;
+use vm::error::*;
+use vm::frame::*;
+
+pub const STACK_SIZE: usize = 2048;
+pub const GLOBALS_SIZE: usize = 65536;
+pub const MAX_FRAMES: usize = 1024;
+
+pub struct VM {
+    constants:      Vec<object::Object>,
+    stack:          Vec<object::Object>,
+    sp:             usize,                      // always points to the next free slot; the top of the stack is stack[sp-1]
+    globals:        Vec<object::Object>,
+    frames:         Vec<Frame>,
+    last_popped:    Option<object::Object>,     // value of the last expression statement
+}
+
+impl VM {
+    pub fn new(bytecode: compiler::Bytecode) -> VM {
+        let main_fn: object::CompiledFunction = object::CompiledFunction::new(bytecode.instructions, bytecode.lines, 0, 0);
+        let main_closure: object::Closure = object::Closure::new(Rc::new(main_fn), Vec::new());
+        let main_frame: Frame = Frame::new(Rc::new(main_closure), 0);
+
+        return VM{
+            constants:      bytecode.constants,
+            stack:          vec![object::Object::Null; STACK_SIZE],
+            sp:             0,
+            globals:        vec![object::Object::Null; GLOBALS_SIZE],
+            frames:         vec![main_frame],
+            last_popped:    None,
+        };
+    }
+
+    pub fn last_popped_stack_elem(&self) -> Option<object::Object> {
+        return self.last_popped.clone();
+    }
+
+    // Runs the program. A failure is reported like an evaluator error, with the
+    // call stack taken from the frames still active and their line tables.
+    pub fn run(&mut self) -> Result<(), RuntimeError> {
+        return match self.execute() {
+            Ok(()) => Ok(()),
+            Err(err) => Err(self.runtime_error(&err)),
+        };
+    }
+
+    fn runtime_error(&self, err: &VmError) -> RuntimeError {
+        let mut runtime_error: RuntimeError = RuntimeError::new(err.kind(), err.to_string());
+
+        for (depth, frame) in self.frames.iter().enumerate().rev() {
+            // ip has moved past the opcode of the failed instruction or call
+            match code::lookup_position(&frame.closure.func.lines, frame.ip.saturating_sub(1)) {
+                Some(pos) => runtime_error.locate(pos.line, pos.column),
+                None => (),
+            }
+            let name: &str = match frame.closure.func.name {
+                Some(ref name) => name,
+                None if depth == 0 => "<main>",
+                None => "<anonymous>",
+            };
+            runtime_error.unwind(name);
+        }
+
+        return runtime_error;
+    }
+
+    fn execute(&mut self) -> Result<(), VmError> {
+        loop {
+            let byte: u8 = {
+                let frame: &Frame = self.current_frame();
+                if frame.ip >= frame.closure.func.instructions.len() {
+                    return Ok(());
+                }
+                frame.closure.func.instructions[frame.ip]
+            };
+            self.current_frame_mut().ip += 1;
+
+            let op: code::Opcode = match code::Opcode::from_byte(byte) {
+                Some(op) => op,
+                None => return Err(VmError::InvalidOpcode(byte)),
+            };
+
+            match op {
+                code::OpConstant => {
+                    let index: usize = self.read_u16_operand()?;
+                    let constant: object::Object = match self.constants.get(index) {
+                        Some(constant) => constant.clone(),
+                        None => return Err(VmError::InvalidOperand{ opcode: op.definition().name, operand: index }),
+                    };
+                    self.push(constant)?;
+                },
+                code::OpPop => {
+                    let value: object::Object = self.pop()?;
+                    self.last_popped = Some(value);
+                },
+                code::OpAdd | code::OpSub | code::OpMul | code::OpDiv | code::OpMod |
+                code::OpEqual | code::OpNotEqual |
+                code::OpLessThan | code::OpLessEqual | code::OpGreaterThan | code::OpGreaterEqual => {
+                    self.execute_binary_operation(op)?;
+                },
+                code::OpTrue => self.push(object::Object::Boolean(true))?,
+                code::OpFalse => self.push(object::Object::Boolean(false))?,
+                code::OpNull => self.push(object::Object::Null)?,
+                code::OpMinus => {
+                    let right: object::Object = self.pop()?;
+                    match right {
+                        object::Object::Integer(value) => self.push(object::Object::Integer(value.wrapping_neg()))?,
+                        _ => return Err(VmError::UnknownPrefixOperator{ operator: "-", right: right.object_type() }),
+                    }
+                },
+                code::OpBang => {
+                    let right: object::Object = self.pop()?;
+                    let value: bool = match right {
+                        object::Object::Boolean(value) => ! value,
+                        object::Object::Null => true,
+                        _ => false,
+                    };
+                    self.push(object::Object::Boolean(value))?;
+                },
+                code::OpJump => {
+                    let position: usize = self.read_u16_operand()?;
+                    self.current_frame_mut().ip = position;
+                },
+                code::OpJumpNotTruthy => {
+                    let position: usize = self.read_u16_operand()?;
+                    let condition: object::Object = self.pop()?;
+                    if ! is_truthy(&condition) {
+                        self.current_frame_mut().ip = position;
+                    }
+                },
+                // a two-byte index always lies within the GLOBALS_SIZE globals
+                code::OpGetGlobal => {
+                    let index: usize = self.read_u16_operand()?;
+                    let value: object::Object = self.globals[index].clone();
+                    self.push(value)?;
+                },
+                code::OpSetGlobal => {
+                    let index: usize = self.read_u16_operand()?;
+                    self.globals[index] = self.pop()?;
+                },
+                code::OpGetLocal => {
+                    let slot: usize = self.read_local_operand(op)?;
+                    let value: object::Object = self.stack[slot].clone();
+                    self.push(value)?;
+                },
+                code::OpSetLocal => {
+                    let slot: usize = self.read_local_operand(op)?;
+                    self.stack[slot] = self.pop()?;
+                },
+                code::OpGetBuiltin => {
+                    let index: usize = self.read_u8_operand()?;
+                    let builtin: object::Builtin = match builtins::BUILTINS.get(index) {
+                        Some(builtin) => *builtin,
+                        None => return Err(VmError::InvalidOperand{ opcode: op.definition().name, operand: index }),
+                    };
+                    self.push(object::Object::Builtin(builtin))?;
+                },
+                code::OpGetFree => {
+                    let index: usize = self.read_u8_operand()?;
+                    let value: object::Object = match self.current_frame().closure.free.get(index) {
+                        Some(value) => value.clone(),
+                        None => return Err(VmError::InvalidOperand{ opcode: op.definition().name, operand: index }),
+                    };
+                    self.push(value)?;
+                },
+                code::OpArray => {
+                    let count: usize = self.read_u16_operand()?;
+                    let elements: Vec<object::Object> = self.take_top(count)?;
+                    self.push(object::Object::Array(elements))?;
+                },
+                code::OpHash => {
+                    let count: usize = self.read_u16_operand()?;
+                    let hash: object::Object = self.build_hash(count)?;
+                    self.push(hash)?;
+                },
+                code::OpIndex => {
+                    let index: object::Object = self.pop()?;
+                    let left: object::Object = self.pop()?;
+                    let value: object::Object = execute_index_expression(left, index)?;
+                    self.push(value)?;
+                },
+                code::OpCall => {
+                    let num_args: usize = self.read_u8_operand()?;
+                    self.call_function(num_args)?;
+                },
+                code::OpReturnValue => {
+                    let value: object::Object = self.pop()?;
+                    if self.return_from_frame(value)? {
+                        return Ok(());
+                    }
+                },
+                code::OpReturn => {
+                    if self.return_from_frame(object::Object::Null)? {
+                        return Ok(());
+                    }
+                },
+                code::OpClosure => {
+                    let index: usize = self.read_u16_operand()?;
+                    let num_free: usize = self.read_u8_operand()?;
+                    self.push_closure(index, num_free)?;
+                },
+                code::OpCurrentClosure => {
+                    let closure: Rc<object::Closure> = self.current_frame().closure.clone();
+                    self.push(object::Object::Closure(closure))?;
+                },
+            }
+        }
+    }
+
+    fn current_frame(&self) -> &Frame {
+        return self.frames.last().expect("vm has no frame");
+    }
+
+    fn current_frame_mut(&mut self) -> &mut Frame {
+        return self.frames.last_mut().expect("vm has no frame");
+    }
+
+    fn read_u16_operand(&mut self) -> Result<usize, VmError> {
+        let frame: &mut Frame = self.current_frame_mut();
+        if frame.ip + 2 > frame.closure.func.instructions.len() {
+            return Err(VmError::TruncatedInstruction);
+        }
+        let operand: u16 = code::read_u16(&frame.closure.func.instructions[frame.ip..]);
+        frame.ip += 2;
+        return Ok(operand as usize);
+    }
+
+    fn read_u8_operand(&mut self) -> Result<usize, VmError> {
+        let frame: &mut Frame = self.current_frame_mut();
+        if frame.ip + 1 > frame.closure.func.instructions.len() {
+            return Err(VmError::TruncatedInstruction);
+        }
+        let operand: u8 = code::read_u8(&frame.closure.func.instructions[frame.ip..]);
+        frame.ip += 1;
+        return Ok(operand as usize);
+    }
+
+    // Reads the local operand of op and returns the stack slot it refers to,
+    // which must lie within the locals reserved for the current frame.
+    fn read_local_operand(&mut self, op: code::Opcode) -> Result<usize, VmError> {
+        let index: usize = self.read_u8_operand()?;
+        let frame: &Frame = self.current_frame();
+        if index >= frame.closure.func.num_locals {
+            return Err(VmError::InvalidOperand{ opcode: op.definition().name, operand: index });
+        }
+        return Ok(frame.base_pointer + index);
+    }
+
+    fn push(&mut self, obj: object::Object) -> Result<(), VmError> {
+        if self.sp >= STACK_SIZE {
+            return Err(VmError::StackOverflow);
+        }
+        self.stack[self.sp] = obj;
+        self.sp += 1;
+        return Ok(());
+    }
+
+    fn pop(&mut self) -> Result<object::Object, VmError> {
+        if self.sp == 0 {
+            return Err(VmError::StackUnderflow);
+        }
+        self.sp -= 1;
+        return Ok(mem::replace(&mut self.stack[self.sp], object::Object::Null));
+    }
+
+    // Removes the top count elements, returning them in the order they were pushed.
+    fn take_top(&mut self, count: usize) -> Result<Vec<object::Object>, VmError> {
+        if count > self.sp {
+            return Err(VmError::StackUnderflow);
+        }
+        let start: usize = self.sp - count;
+        let elements: Vec<object::Object> = self.stack[start..self.sp].iter_mut()
+            .map(|slot| mem::replace(slot, object::Object::Null))
+            .collect();
+        self.sp = start;
+        return Ok(elements);
+    }
+
+    fn build_hash(&mut self, count: usize) -> Result<object::Object, VmError> {
+        let elements: Vec<object::Object> = self.take_top(count)?;
+        let mut pairs: HashMap<object::HashKey, object::HashPair> = HashMap::new();
+
+        let mut iter = elements.into_iter();
+        while let (Some(key), Some(value)) = (iter.next(), iter.next()) {
+            let hash_key: object::HashKey = match key.hash_key() {
+                Some(hash_key) => hash_key,
+                None => return Err(VmError::UnusableHashKey(key.object_type())),
+            };
+            pairs.insert(hash_key, object::HashPair{ key: key, value: value });
+        }
+
+        return Ok(object::Object::Hash(pairs));
+    }
+
+    fn push_closure(&mut self, index: usize, num_free: usize) -> Result<(), VmError> {
+        let func: Rc<object::CompiledFunction> = match self.constants.get(index) {
+            Some(object::Object::CompiledFunction(func)) => func.clone(),
+            Some(other) => return Err(VmError::NotAFunction(other.object_type())),
+            None => return Err(VmError::InvalidOperand{ opcode: code::OpClosure.definition().name, operand: index }),
+        };
+        let free: Vec<object::Object> = self.take_top(num_free)?;
+        return self.push(object::Object::Closure(Rc::new(object::Closure::new(func, free))));
+    }
+
+    fn call_function(&mut self, num_args: usize) -> Result<(), VmError> {
+        if num_args >= self.sp {
+            return Err(VmError::StackUnderflow);
+        }
+        let callee: object::Object = self.stack[self.sp - 1 - num_args].clone();
+        return match callee {
+            object::Object::Closure(closure) => self.call_closure(closure, num_args),
+            object::Object::Builtin(builtin) => self.call_builtin(builtin, num_args),
+            _ => Err(VmError::NotAFunction(callee.object_type())),
+        };
+    }
+
+    fn call_closure(&mut self, closure: Rc<object::Closure>, num_args: usize) -> Result<(), VmError> {
+        let func: &object::CompiledFunction = &closure.func;
+        if num_args != func.num_parameters {
+            return Err(VmError::WrongArgumentCount{ want: func.num_parameters, got: num_args });
+        }
+
+        // the parameters are locals too, even if a loaded function claims fewer locals
+        let base_pointer: usize = self.sp - num_args;
+        let stack_top: usize = base_pointer + cmp::max(func.num_locals, num_args);
+        if self.frames.len() >= MAX_FRAMES || stack_top > STACK_SIZE {
+            return Err(VmError::StackOverflow);
+        }
+
+        // locals beyond the parameters may still hold values of an earlier call
+        for slot in self.stack[self.sp..stack_top].iter_mut() {
+            *slot = object::Object::Null;
+        }
+
+        self.frames.push(Frame::new(closure, base_pointer));
+        self.sp = stack_top;
+        return Ok(());
+    }
+
+    // Builtins run on the host right away; their result replaces the callee and
+    // the arguments on the stack.
+    fn call_builtin(&mut self, builtin: object::Builtin, num_args: usize) -> Result<(), VmError> {
+        let args: Vec<object::Object> = self.take_top(num_args)?;
+        self.pop()?;
+
+        return match (builtin.func)(args) {
+            object::Object::Error(err) => Err(VmError::Builtin(err.kind, err.message)),
+            result => self.push(result),
+        };
+    }
+
+    // Pops the current frame and pushes the return value in place of the callee.
+    // A return from the main program instead ends execution, which is reported
+    // by returning true.
+    fn return_from_frame(&mut self, value: object::Object) -> Result<bool, VmError> {
+        if self.frames.len() == 1 {
+            self.last_popped = Some(value);
+            return Ok(true);
+        }
+
+        let frame: Frame = self.frames.pop().expect("vm has no frame");
+        self.sp = frame.base_pointer - 1;
+        self.push(value)?;
+        return Ok(false);
+    }
+
+    fn execute_binary_operation(&mut self, op: code::Opcode) -> Result<(), VmError> {
+        let right: object::Object = self.pop()?;
+        let left: object::Object = self.pop()?;
+        let operator: &'static str = infix_operator(op);
+
+        let result: object::Object = match (&left, &right) {
+            (object::Object::Integer(l), object::Object::Integer(r)) => execute_integer_operation(op, *l, *r)?,
+            (object::Object::Boolean(l), object::Object::Boolean(r)) => match op {
+                code::OpEqual => object::Object::Boolean(l == r),
+                code::OpNotEqual => object::Object::Boolean(l != r),
+                _ => return Err(VmError::UnknownOperator{ left: left.object_type(), operator: operator, right: right.object_type() }),
+            },
+            (object::Object::String(l), object::Object::String(r)) => match op {
+                code::OpAdd => object::Object::String(format!("{}{}", l, r)),
+                code::OpEqual => object::Object::Boolean(l == r),
+                code::OpNotEqual => object::Object::Boolean(l != r),
+                _ => return Err(VmError::UnknownOperator{ left: left.object_type(), operator: operator, right: right.object_type() }),
+            },
+            (object::Object::Null, object::Object::Null) => match op {
+                code::OpEqual => object::Object::Boolean(true),
+                code::OpNotEqual => object::Object::Boolean(false),
+                _ => return Err(VmError::UnknownOperator{ left: left.object_type(), operator: operator, right: right.object_type() }),
+            },
+            _ => {
+                if left.object_type() != right.object_type() {
+                    return Err(VmError::TypeMismatch{ left: left.object_type(), operator: operator, right: right.object_type() });
+                }
+                return Err(VmError::UnknownOperator{ left: left.object_type(), operator: operator, right: right.object_type() });
+            },
+        };
+
+        return self.push(result);
+    }
+}
+
+fn execute_integer_operation(op: code::Opcode, left: i64, right: i64) -> Result<object::Object, VmError> {
+    return Ok(match op {
+        code::OpAdd => object::Object::Integer(left.wrapping_add(right)),
+        code::OpSub => object::Object::Integer(left.wrapping_sub(right)),
+        code::OpMul => object::Object::Integer(left.wrapping_mul(right)),
+        code::OpDiv => {
+            if right == 0 {
+                return Err(VmError::DivisionByZero);
+            }
+            object::Object::Integer(left.wrapping_div(right))
+        },
+        code::OpMod => {
+            if right == 0 {
+                return Err(VmError::DivisionByZero);
+            }
+            object::Object::Integer(left.wrapping_rem(right))
+        },
+        code::OpEqual => object::Object::Boolean(left == right),
+        code::OpNotEqual => object::Object::Boolean(left != right),
+        code::OpLessThan => object::Object::Boolean(left < right),
+        code::OpLessEqual => object::Object::Boolean(left <= right),
+        code::OpGreaterThan => object::Object::Boolean(left > right),
+        code::OpGreaterEqual => object::Object::Boolean(left >= right),
+        _ => return Err(VmError::UnknownOperator{ left: object::INTEGER_OBJ, operator: infix_operator(op), right: object::INTEGER_OBJ }),
+    });
+}
+
+fn execute_index_expression(left: object::Object, index: object::Object) -> Result<object::Object, VmError> {
+    return match (&left, &index) {
+        (object::Object::Array(elements), object::Object::Integer(i)) => {
+            if *i < 0 || *i >= elements.len() as i64 {
+                Ok(object::Object::Null)
+            } else {
+                Ok(elements[*i as usize].clone())
+            }
+        },
+        (object::Object::Hash(pairs), _) => match index.hash_key() {
+            Some(key) => match pairs.get(&key) {
+                Some(pair) => Ok(pair.value.clone()),
+                None => Ok(object::Object::Null),
+            },
+            None => Err(VmError::UnusableHashKey(index.object_type())),
+        },
+        _ => Err(VmError::IndexNotSupported(left.object_type())),
+    };
+}
+
+fn infix_operator(op: code::Opcode) -> &'static str {
+    return match op {
+        code::OpAdd => "+",
+        code::OpSub => "-",
+        code::OpMul => "*",
+        code::OpDiv => "/",
+        code::OpMod => "%",
+        code::OpEqual => "==",
+        code::OpNotEqual => "!=",
+        code::OpLessThan => "<",
+        code::OpLessEqual => "<=",
+        code::OpGreaterThan => ">",
+        code::OpGreaterEqual => ">=",
+        _ => "?",
+    };
+}
+
+fn is_truthy(obj: &object::Object) -> bool {
+    return match *obj {
+        object::Object::Null => false,
+        object::Object::Boolean(value) => value,
+        _ => true,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use super::*;
+    use ast::*;
+    use evaluator::*;
+    use lexer::*;
+    use object::error;
+    use parser::*;
+
+    fn parse(input: &str) -> ast::Program {
+        let mut l = lexer::Lexer::new(input);
+        let mut p = parser::Parser::new(&mut l);
+        return p.parse_program().unwrap();
+    }
+
+    fn run_vm(input: &str) -> String {
+        let mut c = compiler::Compiler::new();
+        match c.compile_program(&parse(input)) {
+            Ok(()) => (),
+            Err(err) => panic!("compiler error: {}", err),
+        }
+
+        let mut machine = VM::new(c.bytecode());
+        return match machine.run() {
+            Ok(()) => match machine.last_popped_stack_elem() {
+                Some(obj) => obj.inspect(),
+                None => String::from("<none>"),
+            },
+            Err(err) => format!("ERROR: {}", err),
+        };
+    }
+
+    fn run_evaluator(input: &str) -> String {
+        let env = Rc::new(RefCell::new(environment::Environment::new()));
+        return match evaluator::eval_program(&parse(input), &env) {
+            Some(obj) => obj.inspect(),
+            None => String::from("<none>"),
+        };
+    }
+
+    // Every program is run by both the tree-walking evaluator and the VM, and
+    // both must produce the expected result.
+    #[test]
+    fn test_vm_matches_evaluator() {
+        let tests: &[(&str, &str)] = &[
+            // integers
+            ("1",                                                   "1"),
+            ("1 + 2 * 3 - 4 / 2",                                   "5"),
+            ("-(5 + 5) * 2",                                        "-20"),
+            ("17 % 5",                                              "2"),
+            ("9223372036854775807 + 1",                             "-9223372036854775808"),
+            // booleans and comparisons
+            ("!true",                                               "false"),
+            ("!!5",                                                 "true"),
+            ("1 < 2 == true",                                       "true"),
+            ("2 <= 2",                                              "true"),
+            ("1 >= 2",                                              "false"),
+            ("(1 > 2) != (2 > 1)",                                  "true"),
+            // strings
+            ("\"mon\" + \"key\"",                                   "monkey"),
+            ("\"a\" == \"a\"",                                      "true"),
+            ("\"a\" != \"a\"",                                      "false"),
+            // conditionals and globals
+            ("if (1 < 2) { 10 } else { 20 }",                       "10"),
+            ("if (false) { 10 }",                                   "null"),
+            ("let a = 5; let b = a * 2; a + b",                     "15"),
+            ("let a = 1; let a = a + 1; a",                         "2"),
+            // logical operators
+            ("true && false",                                       "false"),
+            ("false || 1",                                          "true"),
+            ("false && (1 / 0)",                                    "false"),
+            ("true || (1 / 0)",                                     "true"),
+            // arrays and hashes
+            ("[1, 2 * 2, 3 + 3]",                                   "[1, 4, 6]"),
+            ("[1, 2, 3][1 + 1]",                                    "3"),
+            ("[1, 2, 3][3]",                                        "null"),
+            ("[1][-1]",                                             "null"),
+            ("{1: 2, \"a\": true}",                                 "{1: 2, a: true}"),
+            ("{\"one\": 1, true: 2}[true]",                         "2"),
+            ("{}[\"missing\"]",                                     "null"),
+            // functions
+            ("let add = fn(a, b) { a + b }; add(1, add(2, 3))",     "6"),
+            ("let f = fn(x) { let y = x * 2; return y + 1; }; f(4)", "9"),
+            ("fn() { }()",                                          "null"),
+            ("let a = 5; let f = fn() { let a = a + 1; a }; f()",    "6"),
+            ("let early = fn() { return 1; 2 }; early()",           "1"),
+            ("let fib = fn(n) { if (n < 2) { n } else { fib(n - 1) + fib(n - 2) } }; fib(15)", "610"),
+            ("let twice = fn(f, x) { f(f(x)) }; let inc = fn(x) { x + 1 }; twice(inc, 1)", "3"),
+            ("let g = fn() { 5 }; let h = fn() { g }; h()()",       "5"),
+            ("return 10; 9",                                        "10"),
+            // closures
+            ("let newAdder = fn(a) { fn(b) { a + b } }; let addTwo = newAdder(2); addTwo(3)", "5"),
+            ("let f = fn(a) { let b = a * 2; fn(c) { fn(d) { a + b + c + d } } }; f(1)(2)(3)", "8"),
+            ("let wrapper = fn() { let fibonacci = fn(x) { if (x < 2) { x } else { fibonacci(x - 1) + fibonacci(x - 2) } }; fibonacci(15) }; wrapper()", "610"),
+            ("let map = fn(arr, f) { let iter = fn(arr, acc) { if (len(arr) == 0) { acc } else { iter(rest(arr), push(acc, f(first(arr)))) } }; iter(arr, []) }; map([1, 2, 3], fn(x) { x * x })", "[1, 4, 9]"),
+            // builtins
+            ("len(\"hello\") + len([1, 2])",                        "7"),
+            ("last([1, 2, 3])",                                     "3"),
+            ("rest([1])",                                           "[]"),
+            ("let len = fn(x) { 42 }; len(\"a\")",                  "42"),
+            // errors
+            ("5 + true",                                            "ERROR: type mismatch: INTEGER + BOOLEAN"),
+            ("-true",                                               "ERROR: unknown operator: -BOOLEAN"),
+            ("true + false",                                        "ERROR: unknown operator: BOOLEAN + BOOLEAN"),
+            ("\"a\" - \"b\"",                                       "ERROR: unknown operator: STRING - STRING"),
+            ("10 / (5 - 5)",                                        "ERROR: division by zero"),
+            ("let x = 5; x(1)",                                     "ERROR: not a function: INTEGER"),
+            ("let f = fn(a, b) { a }; f(1)",                        "ERROR: wrong number of arguments: want=2, got=1"),
+            ("1[0]",                                                "ERROR: index operator not supported: INTEGER"),
+            ("{[1]: 2}",                                            "ERROR: unusable as hash key: ARRAY"),
+            ("len(1)",                                              "ERROR: argument to `len` not supported, got INTEGER"),
+            ("first([1], [2])",                                     "ERROR: wrong number of arguments: want=1, got=2"),
+            ("let f = fn(g) { g(1) }; f(len)",                      "ERROR: argument to `len` not supported, got INTEGER"),
+        ];
+
+        for (i, &(input, expected)) in tests.iter().enumerate() {
+            assert_eq!(run_evaluator(input), expected, "tests[{}] (evaluator): {}", i, input);
+            assert_eq!(run_vm(input), expected, "tests[{}] (vm): {}", i, input);
+        }
+    }
+
+    #[test]
+    fn test_stack_overflow() {
+        assert_eq!(run_vm("let f = fn() { f() }; f()"), "ERROR: stack overflow");
+
+        let mut c = compiler::Compiler::new();
+        c.compile_program(&parse("let f = fn() { f() }; f()")).unwrap();
+        let mut machine = VM::new(c.bytecode());
+        match machine.run() {
+            Ok(()) => assert!(false, "vm has no error"),
+            Err(err) => assert_eq!(err.traceback(None), "\
+Traceback (most recent call last):
+  line 1, column 24, in <main>
+  line 1, column 17, in f
+  [previous frame repeated 1022 more times]
+StackOverflow: stack overflow"),
+        }
+    }
+
+    #[test]
+    fn test_invalid_opcode() {
+        let bytecode = compiler::Bytecode{ instructions: vec![255], constants: Vec::new(), lines: Vec::new() };
+        let mut machine = VM::new(bytecode);
+        match machine.run() {
+            Err(err) => {
+                assert_eq!(err.kind, error::ErrorKind::InvalidBytecode);
+                assert_eq!(err.message, "invalid opcode 255");
+            },
+            Ok(()) => assert!(false, "program ran"),
+        }
+    }
+
+    #[test]
+    fn test_vm_errors_match_evaluator() {
+        let tests: &[&str] = &[
+            "5 + true",
+            "-true",
+            "let x = 5; x(1)",
+            "let f = fn(a, b) { a }; f(1)",
+            "[1, 2][true]",
+            "{[1]: 1}",
+            "fn() { 1 / 0 }()",
+            "let f = fn() { len(1) };\nf()",
+            "let add = fn(a, b) { a + b };\nlet apply = fn(f) { f(1, true) };\napply(add)",
+        ];
+
+        for (i, input) in tests.iter().enumerate() {
+            let mut c = compiler::Compiler::new();
+            c.compile_program(&parse(input)).unwrap();
+            let mut machine = VM::new(c.bytecode());
+            let vm_error: RuntimeError = match machine.run() {
+                Err(err) => err,
+                Ok(()) => panic!("tests[{}]: program ran", i),
+            };
+
+            let env = Rc::new(RefCell::new(environment::Environment::new()));
+            let evaluator_error: RuntimeError = match evaluator::eval_program(&parse(input), &env) {
+                Some(object::Object::Error(err)) => err,
+                _ => panic!("tests[{}]: evaluator returned no error", i),
+            };
+
+            assert_eq!(vm_error.kind, evaluator_error.kind, "tests[{}]", i);
+            assert_eq!(vm_error.stack, evaluator_error.stack, "tests[{}]", i);
+            assert_eq!(vm_error.traceback(Some(input)), evaluator_error.traceback(Some(input)), "tests[{}]", i);
+        }
+    }
+}